@@ -0,0 +1,204 @@
+//!护甲与护甲等级，来自第5版玩家手册第5章
+//!护甲等级由身上的护甲决定：轻甲加全部敏捷调整值，中甲最多加2，重甲不加；盾牌另外加2。
+//!没有穿甲时使用10加敏捷调整值，或者野蛮人、武僧的无甲防御以及天生护甲中最高的一个。
+//!穿着不熟练的护甲时，涉及力量或敏捷的属性检定、豁免检定与攻击检定具有劣势，并且不能施法；
+//!力量不满足重甲的要求时速度减少10尺
+use crate::{Abilities,ArmorCategory,CharacterClass,CoinType,DNDChecker,Player,Skill};
+use serde::{Serialize,Deserialize};
+
+#[derive(Clone,Debug,PartialEq,Eq,Serialize,Deserialize)]
+///一件护甲或盾牌
+pub struct Armor{
+    pub name:String,
+    pub category:ArmorCategory,
+    ///基础护甲等级，盾牌为护甲等级的加值
+    pub base_ac:i32,
+    ///最多加上的敏捷调整值，None表示没有上限
+    pub max_dex_bonus:Option<i32>,
+    ///穿着所需的力量值，不满足时速度减少10尺
+    pub strength_requirement:Option<i32>,
+    ///隐匿检定具有劣势
+    pub stealth_disadvantage:bool,
+    ///重量（磅）
+    pub weight:i32,
+    pub price:(CoinType,i32),
+}
+
+///名字、类别、基础护甲等级、力量要求、隐匿劣势、重量、价格（金币）
+type ArmorRow=(&'static str,ArmorCategory,i32,Option<i32>,bool,i32,i32);
+///玩家手册中的所有护甲
+const ARMOR_TABLE:[ArmorRow;13]=[
+    ("Padded",ArmorCategory::Light,11,None,true,8,5),
+    ("Leather",ArmorCategory::Light,11,None,false,10,10),
+    ("Studded Leather",ArmorCategory::Light,12,None,false,13,45),
+    ("Hide",ArmorCategory::Medium,12,None,false,12,10),
+    ("Chain Shirt",ArmorCategory::Medium,13,None,false,20,50),
+    ("Scale Mail",ArmorCategory::Medium,14,None,true,45,50),
+    ("Breastplate",ArmorCategory::Medium,14,None,false,20,400),
+    ("Half Plate",ArmorCategory::Medium,15,None,true,40,750),
+    ("Ring Mail",ArmorCategory::Heavy,14,None,true,40,30),
+    ("Chain Mail",ArmorCategory::Heavy,16,Some(13),true,55,75),
+    ("Splint",ArmorCategory::Heavy,17,Some(15),true,60,200),
+    ("Plate",ArmorCategory::Heavy,18,Some(15),true,65,1500),
+    ("Shield",ArmorCategory::Shield,2,None,false,6,10),
+];
+
+///玩家手册中的所有护甲与盾牌，按价格表中的顺序排列
+/// ```
+/// use minidnd_eecs_havefun::{armor_catalog,ArmorCategory};
+/// let catalog=armor_catalog();
+/// assert_eq!(catalog.len(),13);
+/// assert_eq!(catalog.iter().filter(|a|a.category==ArmorCategory::Heavy).count(),4);
+/// ```
+pub fn armor_catalog()->Vec<Armor>{
+    ARMOR_TABLE.iter().map(|&(name,category,base_ac,strength_requirement,stealth_disadvantage,weight,gold)|{
+        let max_dex_bonus=match category{
+            ArmorCategory::Light=>None,
+            ArmorCategory::Medium=>Some(2),
+            ArmorCategory::Heavy|ArmorCategory::Shield=>Some(0),
+        };
+        Armor{name:name.to_string(),category,base_ac,max_dex_bonus,strength_requirement,stealth_disadvantage,weight,price:(CoinType::Gold,gold)}
+    }).collect()
+}
+
+impl Armor{
+    ///按名字从[`armor_catalog`]中查找，不区分大小写
+    /// ```
+    /// use minidnd_eecs_havefun::Armor;
+    /// let plate=Armor::named("plate").unwrap();
+    /// assert_eq!((plate.base_ac,plate.strength_requirement,plate.weight),(18,Some(15),65));
+    /// assert!(Armor::named("Mithral Shirt").is_none());
+    /// ```
+    pub fn named(name:&str)->Option<Armor>{
+        armor_catalog().into_iter().find(|a|a.name.eq_ignore_ascii_case(name))
+    }
+    pub fn is_shield(&self)->bool{
+        self.category==ArmorCategory::Shield
+    }
+    ///穿着这件护甲时的护甲等级，盾牌返回加值
+    pub fn armor_class(&self,dexterity_modifier:i32)->i32{
+        if self.is_shield() {return self.base_ac}
+        self.base_ac+self.max_dex_bonus.map_or(dexterity_modifier,|cap|dexterity_modifier.min(cap))
+    }
+}
+
+impl Player{
+    ///穿上护甲或拿起盾牌并重新计算护甲等级，返回被换下的那一件
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,Armor,ArmorCategory,Combatant};
+    /// let mut player=Player::new_by_default();
+    /// player.armor_proficiencies.extend([ArmorCategory::Medium,ArmorCategory::Shield]);
+    /// //默认敏捷14
+    /// assert_eq!(player.armor_class(),12);
+    /// player.equip_armor(Armor::named("Half Plate").unwrap());
+    /// player.equip_armor(Armor::named("Shield").unwrap());
+    /// assert_eq!(player.armor_class(),19);
+    /// let old=player.equip_armor(Armor::named("Leather").unwrap());
+    /// assert_eq!(old.unwrap().name,"Half Plate");
+    /// assert_eq!(player.armor_class(),15);
+    /// player.unequip_shield();
+    /// player.unequip_armor();
+    /// assert_eq!(player.armor_class(),12);
+    /// ```
+    pub fn equip_armor(&mut self,armor:Armor)->Option<Armor>{
+        let slot=if armor.is_shield() {&mut self.shield} else {&mut self.worn_armor};
+        let old=slot.replace(armor);
+        self.update_armor_class();
+        old
+    }
+    ///脱下护甲并重新计算护甲等级
+    pub fn unequip_armor(&mut self)->Option<Armor>{
+        let old=self.worn_armor.take();
+        self.update_armor_class();
+        old
+    }
+    ///放下盾牌并重新计算护甲等级
+    pub fn unequip_shield(&mut self)->Option<Armor>{
+        let old=self.shield.take();
+        self.update_armor_class();
+        old
+    }
+    ///按照当前的护甲、属性值与职业计算护甲等级。没有穿甲时取10加敏捷调整值、天生护甲、
+    /// 野蛮人的无甲防御（加体质调整值）与武僧的无甲防御（加感知调整值，不能持盾）中最高的一个
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,Armor,CharacterClass};
+    /// let mut monk=Player::new_by_default();
+    /// monk.classes=vec![(CharacterClass::Monk,1)];
+    /// monk.ability_scores.wisdom=16;
+    /// assert_eq!(monk.calculate_armor_class(),15);
+    /// //持盾时失去武僧的无甲防御
+    /// monk.shield=Armor::named("Shield");
+    /// assert_eq!(monk.calculate_armor_class(),14);
+    /// monk.natural_armor=Some(13);
+    /// assert_eq!(monk.calculate_armor_class(),17);
+    /// let mut barbarian=Player::new_by_default();
+    /// barbarian.classes=vec![(CharacterClass::Barbarian,1)];
+    /// //体质13，调整值+1
+    /// assert_eq!(barbarian.calculate_armor_class(),13);
+    /// ```
+    pub fn calculate_armor_class(&self)->i32{
+        let modifiers=self.ability_scores_to_modifiers();
+        let shield=self.shield.as_ref().map_or(0,|s|s.base_ac);
+        let armor=match &self.worn_armor{
+            Some(armor)=>armor.armor_class(modifiers.dexterity),
+            None=>{
+                let mut candidates=vec![10+modifiers.dexterity];
+                if let Some(base)=self.natural_armor {candidates.push(base+modifiers.dexterity);}
+                if self.class_level(CharacterClass::Barbarian)>0 {candidates.push(10+modifiers.dexterity+modifiers.constitution);}
+                if self.class_level(CharacterClass::Monk)>0&&self.shield.is_none() {
+                    candidates.push(10+modifiers.dexterity+modifiers.wisdom);
+                }
+                candidates.into_iter().max().unwrap_or(10)
+            },
+        };
+        armor+shield
+    }
+    ///重新计算护甲等级并写入[`Player::armor`]。穿脱护甲、设置或撤销种族、选择职业、兼职以及属性值提升时自动调用，
+    /// 直接修改属性值或职业后需要手动调用
+    /// ```
+    /// use minidnd_eecs_havefun::{CharacterBuilder,CharacterClass,Combatant,Race,AbilityScores,Skill};
+    /// let scores=AbilityScores{strength:15,dexterity:14,constitution:14,intelligence:8,wisdom:10,charisma:10};
+    /// let mut fighter=CharacterBuilder::new("Alice").point_buy(scores)
+    /// .class(CharacterClass::Fighter,&[Skill::Athletics,Skill::Perception]).build().unwrap();
+    /// assert_eq!(fighter.armor_class(),12);
+    /// //木精灵敏捷+2
+    /// fighter.set_race(Race::wood_elf());
+    /// assert_eq!(fighter.armor_class(),13);
+    /// fighter.remove_race();
+    /// assert_eq!(fighter.armor_class(),12);
+    /// ```
+    pub fn update_armor_class(&mut self){
+        self.armor=self.calculate_armor_class();
+    }
+    ///是否熟练身上所有的护甲与盾牌
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,Armor,Abilities,Skill,CheckProbability};
+    /// let mut player=Player::new_by_default();
+    /// player.ability_scores.strength=12;
+    /// let stealth=player.skill_check_chance(Skill::Stealth,15,1,0).unwrap();
+    /// let strength=player.saving_throw_chance(Abilities::Strength,15,1,0).unwrap();
+    /// player.equip_armor(Armor::named("Chain Mail").unwrap());
+    /// assert!(!player.armor_proficient());
+    /// assert!(player.skill_check_chance(Skill::Stealth,15,1,0).unwrap()<stealth);
+    /// assert!(player.saving_throw_chance(Abilities::Strength,15,1,0).unwrap()<strength);
+    /// //力量不满足锁子甲的13点要求
+    /// assert_eq!(player.speed(),20);
+    /// ```
+    pub fn armor_proficient(&self)->bool{
+        [&self.worn_armor,&self.shield].into_iter().flatten().all(|a|self.armor_proficiencies.contains(&a.category))
+    }
+    ///力量不满足身上重甲的要求时，速度减少10尺
+    pub fn armor_speed_penalty(&self)->i32{
+        match self.worn_armor.as_ref().and_then(|a|a.strength_requirement){
+            Some(requirement) if self.ability_scores.strength<requirement=>10,
+            _=>0,
+        }
+    }
+    ///护甲给涉及ability的检定带来的劣势：不熟练时力量与敏捷检定、豁免与攻击具有劣势，
+    /// 部分护甲使隐匿检定具有劣势
+    pub(crate) fn armor_disadvantage(&self,ability:Abilities,skill:Option<Skill>)->i32{
+        let unproficient=!self.armor_proficient()&&matches!(ability,Abilities::Strength|Abilities::Dexterity);
+        let noisy=skill==Some(Skill::Stealth)&&self.worn_armor.as_ref().is_some_and(|a|a.stealth_disadvantage);
+        if unproficient||noisy {-1} else {0}
+    }
+}
@@ -0,0 +1,116 @@
+//!武器攻击：攻击检定对抗目标的护甲值，命中后投掷武器伤害并扣除目标生命值
+use crate::{check_count_and_advantage,ActiveCondition,Combatant,DamageType,DiceExpr,DieRoll,DNDChecker,DndError,Player};
+use crate::combatant::dyn_rng;
+use crate::condition::{combine_advantage,Conditions};
+use rand::Rng;
+use serde::{Serialize,Deserialize};
+
+#[derive(Clone,Debug,PartialEq,Eq,Serialize,Deserialize)]
+///一次攻击的完整结果
+pub struct AttackReport{
+    pub attacker:String,
+    pub target:String,
+    pub weapon:String,
+    ///d20的原始点数
+    pub natural:i32,
+    ///属性调整值加上熟练加值
+    pub attack_bonus:i32,
+    ///攻击检定总值
+    pub attack_roll:i32,
+    ///目标的护甲值
+    pub target_armor:i32,
+    pub hit:bool,
+    ///投出20即为重击，伤害骰数量翻倍
+    pub critical:bool,
+    ///合并攻击者与目标的状态之后实际使用的优劣势
+    pub advantage:i32,
+    ///伤害骰加属性调整值的结果
+    pub rolled_damage:i32,
+    ///经过目标抗性、易伤与免疫调整后实际造成的伤害，未命中时为0
+    pub damage:i32,
+    pub damage_type:DamageType,
+    ///伤害骰的每个骰子，未命中时为空
+    pub damage_dice:Vec<DieRoll>,
+    ///目标是否因为这次伤害失去了专注
+    #[serde(default)]
+    pub concentration_lost:bool,
+}
+
+///使用`rand::rng()`且没有优劣势的攻击，见[`attack_with`]
+pub fn attack<T:Combatant+?Sized>(attacker:&Player,weapon_name:&str,target:&mut T)->Result<AttackReport,DndError>{
+    attack_with(&mut rand::rng(),attacker,weapon_name,target,0)
+}
+
+///用攻击者名为weapon_name的武器攻击目标，目标可以是任何[`Combatant`]。
+/// 攻击检定为d20+属性调整值（近战用力量，远程用敏捷）+熟练加值（熟练该武器类别时），不低于目标护甲值即命中。
+/// 投出1必定未命中，投出20必定命中并且重击。命中后伤害为武器伤害骰加上同一属性调整值，至少为0，
+/// 再经过目标的抗性、易伤与免疫调整后从生命值中扣除。
+/// advantage会与双方状态带来的优劣势合并，例如攻击者中毒时有劣势、目标倒地时近战攻击有优势；
+/// 近战攻击命中麻痹或昏迷的目标时必定重击。目标受到伤害后自动进行专注检定
+/// ```
+/// use minidnd_eecs_havefun::{Player,Weapon,WeaponCategory,DamageType,CoinType,DiceExpr,Condition,ConditionDuration,ConditionTracker,attack_with,seeded_rng};
+/// let mut fighter=Player::new_by_default();
+/// fighter.weapon_proficiencies.insert(WeaponCategory::MartialMelee);
+/// fighter.weapons.insert("Longsword".to_string(),Weapon{name:"Longsword".to_string(),
+/// category:WeaponCategory::MartialMelee,damage:DiceExpr::from_dice(1,8).unwrap(),damage_type:DamageType::Slash,price:(CoinType::Gold,15),magical:false});
+/// let mut goblin=Player{name:"Goblin".to_string(),armor:15,hp:30,..Player::new_by_default()};
+/// let mut rng=seeded_rng(11);
+/// let report=attack_with(&mut rng,&fighter,"Longsword",&mut goblin,0).unwrap();
+/// //力量15调整值+2，熟练+2
+/// assert_eq!(report.attack_bonus,4);
+/// assert_eq!(report.attack_roll,report.natural+4);
+/// assert_eq!(report.hit,report.natural==20||(report.natural!=1&&report.attack_roll>=15));
+/// assert_eq!(goblin.hp,30-report.damage);
+/// assert!(attack_with(&mut rng,&fighter,"Greataxe",&mut goblin,0).is_err());
+/// goblin.add_condition(Condition::Paralyzed,ConditionDuration::Indefinite,None);
+/// let report=attack_with(&mut rng,&fighter,"Longsword",&mut goblin,0).unwrap();
+/// assert_eq!(report.advantage,1);
+/// assert_eq!(report.critical,report.hit);
+/// ```
+pub fn attack_with<R:Rng+?Sized,T:Combatant+?Sized>(rng:&mut R,attacker:&Player,weapon_name:&str,target:&mut T,advantage:i32)->Result<AttackReport,DndError>{
+    check_count_and_advantage(1,advantage)?;
+    let weapon=attacker.weapons.get(weapon_name).ok_or_else(||DndError::WeaponNotFound(weapon_name.to_string()))?;
+    let ability=weapon.category.attack_ability();
+    let ability_modifier=attacker.ability_scores_to_modifiers().get(ability);
+    let proficiency=if attacker.is_proficient_with(weapon) {attacker.proficiency_bonus()} else {0};
+    let profile=AttackProfile{name:&weapon.name,attack_bonus:ability_modifier+proficiency,damage:&weapon.damage,damage_bonus:ability_modifier,
+    damage_type:weapon.damage_type,magical:weapon.magical,melee:weapon.category.is_melee(),advantage:attacker.armor_advantage(ability,None)};
+    Ok(resolve_attack(rng,&attacker.name,&attacker.conditions,&profile,target,advantage))
+}
+
+///结算一次攻击所需要的攻击方数据，武器攻击与怪物动作共用
+pub(crate) struct AttackProfile<'a>{
+    pub name:&'a str,
+    pub attack_bonus:i32,
+    pub damage:&'a DiceExpr,
+    ///加在伤害骰上的固定值，重击时不翻倍
+    pub damage_bonus:i32,
+    pub damage_type:DamageType,
+    pub magical:bool,
+    pub melee:bool,
+    ///攻击方自身带来的优劣势，例如不熟练的护甲
+    pub advantage:i32,
+}
+
+///投攻击检定并在命中时结算伤害，advantage应当已经检查过
+pub(crate) fn resolve_attack<R:Rng+?Sized,T:Combatant+?Sized>(rng:&mut R,attacker:&str,attacker_conditions:&[ActiveCondition],
+profile:&AttackProfile,target:&mut T,advantage:i32)->AttackReport{
+    let advantage=combine_advantage(&[advantage,profile.advantage,attacker_conditions.attacking_advantage(),target.conditions().attacked_advantage(profile.melee)]);
+    let target_armor=target.armor_class();
+    let natural=Player::dice_complex(rng,20,1,advantage);
+    let attack_roll=natural+profile.attack_bonus;
+    let hit=natural==20||(natural!=1&&attack_roll>=target_armor);
+    let critical=natural==20||(hit&&profile.melee&&target.conditions().melee_hits_are_critical());
+    let mut report=AttackReport{attacker:attacker.to_string(),target:target.name().to_string(),weapon:profile.name.to_string(),
+    natural,attack_bonus:profile.attack_bonus,attack_roll,target_armor,hit,critical,advantage,
+    rolled_damage:0,damage:0,damage_type:profile.damage_type,damage_dice:Vec::new(),concentration_lost:false};
+    if hit{
+        let damage_expr=if critical {profile.damage.doubled_dice()} else {profile.damage.clone()};
+        let roll=damage_expr.roll(rng);
+        report.rolled_damage=(roll.total+profile.damage_bonus).max(0);
+        report.damage_dice=roll.dice;
+        report.damage=target.take_damage(report.rolled_damage,profile.damage_type,profile.magical,critical);
+        report.concentration_lost=report.damage>0&&!target.resolve_concentration_checks(&mut dyn_rng(rng));
+    }
+    report
+}
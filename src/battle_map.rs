@@ -0,0 +1,414 @@
+//!战斗地图。地图由边长5尺的方格组成，每个方格有自己的地形，每个参与者占据一个方格
+//!距离按照规则书的默认规则计算：斜向移动一格同样算5尺
+use crate::{DndError,Position};
+use serde::{Serialize,Deserialize};
+use std::collections::HashMap;
+
+///一个方格的边长（尺）
+pub const SQUARE_FEET:i32=5;
+
+#[derive(Copy,Clone,Debug,Default,PartialEq,Eq,Serialize,Deserialize)]
+///地形
+pub enum Terrain{
+    #[default] Normal,//普通地形
+    Difficult,//困难地形，每移动1尺额外花费1尺
+    Impassable,//无法通行，例如墙壁
+    Water,//水域，没有游泳速度时按困难地形计算
+}
+impl Terrain{
+    ///进入该地形的一格需要花费几格的移动力，None表示无法进入
+    pub fn movement_cost(&self)->Option<i32>{
+        match self{
+            Terrain::Normal=>Some(1),
+            Terrain::Difficult|Terrain::Water=>Some(2),
+            Terrain::Impassable=>None,
+        }
+    }
+}
+
+#[derive(Copy,Clone,Debug,Default,PartialEq,Eq,Serialize,Deserialize)]
+///阵型，决定一个阵营内部成员的相对位置。阵营的前方即朝向敌方的方向
+pub enum Formation{
+    ///横排，所有人并排站在前线
+    #[default] Line,
+    ///纵列，所有人一个接一个排在领头者身后
+    Column,
+    ///楔形，领头者在前，其余人在两侧向后展开
+    Wedge,
+    ///密集方阵，尽量排成正方形
+    Cluster,
+}
+
+#[derive(Copy,Clone,Debug,PartialEq,Eq,Serialize,Deserialize)]
+///法术等效果的范围形状，尺寸的单位都是尺
+pub enum AreaShape{
+    ///锥形，从起点出发，任意一点的宽度等于它与起点的距离
+    Cone{length:i32},
+    ///立方体，瞄准的方格为靠近起点的一角
+    Cube{size:i32},
+    ///球形，以瞄准的方格为中心
+    Sphere{radius:i32},
+    ///线形，从起点出发经过瞄准的方格
+    Line{length:i32,width:i32},
+    ///圆柱，以瞄准的方格为中心。地图是平面的，高度不影响覆盖的方格
+    Cylinder{radius:i32,height:i32},
+}
+
+#[derive(Copy,Clone,Debug,PartialEq,Eq,Serialize,Deserialize)]
+///站位间距（尺）
+pub struct Spacing{
+    ///同一阵营相邻成员之间的距离
+    pub between_members:i32,
+    ///两个阵营前线之间的距离
+    pub between_factions:i32,
+}
+///默认同伴间隔5尺，双方前线相距60尺
+impl Default for Spacing{
+    fn default() -> Self {
+        Self { between_members: 5, between_factions: 60 }
+    }
+}
+
+#[derive(Clone,Debug,Serialize,Deserialize)]
+#[serde(try_from="BattleMapData")]
+///战斗地图，positions记录每个参与者所在的方格
+pub struct BattleMap{
+    pub width:i32,
+    pub height:i32,
+    terrain:Vec<Terrain>,
+    pub positions:HashMap<String,Position>,
+}
+
+///读档时的原始数据，检查地形数量与地图大小一致后才转换为[`BattleMap`]
+#[derive(Deserialize)]
+struct BattleMapData{
+    width:i32,
+    height:i32,
+    terrain:Vec<Terrain>,
+    positions:HashMap<String,Position>,
+}
+
+impl TryFrom<BattleMapData> for BattleMap{
+    type Error=DndError;
+    fn try_from(data:BattleMapData)->Result<BattleMap,DndError>{
+        let (width,height)=(data.width.max(1),data.height.max(1));
+        let expected=square_count(width,height)?;
+        if data.terrain.len()!=expected {
+            return Err(DndError::TerrainSizeMismatch{expected,actual:data.terrain.len()})
+        }
+        Ok(BattleMap{width,height,terrain:data.terrain,positions:data.positions})
+    }
+}
+
+///地图最多的方格数，相当于边长1000格（5000尺）的正方形
+pub const MAX_MAP_SQUARES:usize=1_000_000;
+
+///地图的方格数，超过[`MAX_MAP_SQUARES`]时返回错误
+fn square_count(width:i32,height:i32)->Result<usize,DndError>{
+    width.checked_mul(height).map(|n|n as usize).filter(|n|*n<=MAX_MAP_SQUARES).ok_or(DndError::MapTooLarge{width,height})
+}
+
+impl BattleMap{
+    ///全部为普通地形的地图，width与height为方格数，至少为1。方格总数超过[`MAX_MAP_SQUARES`]时返回错误
+    /// ```
+    /// use minidnd_eecs_havefun::{BattleMap,Position};
+    /// let map=BattleMap::new(3,2).unwrap();
+    /// assert!(map.in_bounds(Position::new(2,1)));
+    /// assert!(BattleMap::new(i32::MAX,2).is_err());
+    /// assert!(BattleMap::new(46340,46340).is_err());
+    /// //地形数量与地图大小不一致的存档无法读取
+    /// let json=r#"{"width":3,"height":2,"terrain":["Normal"],"positions":{}}"#;
+    /// assert!(serde_json::from_str::<BattleMap>(json).is_err());
+    /// //与new相同，读档时宽和高至少为1
+    /// let json=r#"{"width":0,"height":1,"terrain":["Normal"],"positions":{}}"#;
+    /// assert!(serde_json::from_str::<BattleMap>(json).unwrap().in_bounds(Position::new(0,0)));
+    /// let saved=serde_json::to_string(&map).unwrap();
+    /// assert_eq!(serde_json::from_str::<BattleMap>(&saved).unwrap().width,3);
+    /// ```
+    pub fn new(width:i32,height:i32)->Result<BattleMap,DndError>{
+        let (width,height)=(width.max(1),height.max(1));
+        Ok(BattleMap{width,height,terrain:vec![Terrain::Normal;square_count(width,height)?],positions:HashMap::new()})
+    }
+    ///坐标是否在地图内
+    pub fn in_bounds(&self,position:Position)->bool{
+        (0..self.width).contains(&position.x)&&(0..self.height).contains(&position.y)
+    }
+    fn index(&self,position:Position)->Result<usize,DndError>{
+        if self.in_bounds(position) {Ok((position.y*self.width+position.x) as usize)}
+        else {Err(DndError::PositionOutOfBounds{x:position.x,y:position.y})}
+    }
+    ///某个方格的地形，地图外返回None
+    pub fn terrain_at(&self,position:Position)->Option<Terrain>{
+        self.index(position).ok().map(|i|self.terrain[i])
+    }
+    ///设置某个方格的地形
+    pub fn set_terrain(&mut self,position:Position,terrain:Terrain)->Result<(),DndError>{
+        let index=self.index(position)?;
+        self.terrain[index]=terrain;
+        Ok(())
+    }
+    ///把两个角之间的矩形区域全部设置为某种地形，超出地图的部分被忽略
+    pub fn fill_terrain(&mut self,corner_1:Position,corner_2:Position,terrain:Terrain){
+        for x in corner_1.x.min(corner_2.x)..=corner_1.x.max(corner_2.x){
+            for y in corner_1.y.min(corner_2.y)..=corner_1.y.max(corner_2.y){
+                let _=self.set_terrain(Position::new(x,y),terrain);
+            }
+        }
+    }
+    ///占据某个方格的参与者
+    pub fn occupant(&self,position:Position)->Option<&str>{
+        self.positions.iter().find(|(_,p)|**p==position).map(|(name,_)|name.as_str())
+    }
+    ///方格在地图内、可以通行并且没有被占据
+    pub fn is_free(&self,position:Position)->bool{
+        self.terrain_at(position).is_some_and(|t|t!=Terrain::Impassable)&&self.occupant(position).is_none()
+    }
+    ///方格对name来说是否可以进入，name自己占据的方格也算空闲
+    fn is_free_for(&self,name:&str,position:Position)->bool{
+        self.terrain_at(position).is_some_and(|t|t!=Terrain::Impassable)&&self.occupant(position).is_none_or(|o|o==name)
+    }
+    ///把参与者放到某个方格，已经在地图上的参与者会被移过去
+    pub fn place(&mut self,name:&str,position:Position)->Result<(),DndError>{
+        self.index(position)?;
+        match self.occupant(position){
+            Some(other) if other!=name=>return Err(DndError::SquareUnavailable{x:position.x,y:position.y}),
+            _=>{},
+        }
+        if self.terrain_at(position)==Some(Terrain::Impassable) {return Err(DndError::SquareUnavailable{x:position.x,y:position.y})}
+        self.positions.insert(name.to_string(),position);
+        Ok(())
+    }
+    ///把参与者移出地图
+    pub fn remove(&mut self,name:&str)->Option<Position>{
+        self.positions.remove(name)
+    }
+    ///两个方格之间的距离（尺）
+    /// ```
+    /// use minidnd_eecs_havefun::{BattleMap,Position};
+    /// assert_eq!(BattleMap::distance_feet(Position::new(0,0),Position::new(3,2)),15);
+    /// ```
+    pub fn distance_feet(from:Position,to:Position)->i32{
+        (from.x-to.x).abs().max((from.y-to.y).abs())*SQUARE_FEET
+    }
+    ///两个参与者之间的距离（尺），有人不在地图上时返回None
+    pub fn distance_between(&self,name_1:&str,name_2:&str)->Option<i32>{
+        Some(BattleMap::distance_feet(*self.positions.get(name_1)?,*self.positions.get(name_2)?))
+    }
+    ///距离某个方格不超过feet尺的所有参与者，按名字排序
+    pub fn within(&self,center:Position,feet:i32)->Vec<String>{
+        let mut names:Vec<String>=self.positions.iter()
+        .filter(|(_,p)|BattleMap::distance_feet(center,**p)<=feet)
+        .map(|(name,_)|name.clone()).collect();
+        names.sort();
+        names
+    }
+    ///效果范围覆盖的所有方格，超出地图的部分被忽略。origin为起点（通常是施法者所在的方格），target为瞄准的方格。
+    /// 锥形与线形的方向由origin指向target，不包括起点本身；两者重合时没有方向，锥形与线形不覆盖任何方格。
+    /// 长度与半径按照地图的距离规则计算，锥形与线形的宽度按照实际的几何位置判断
+    /// ```
+    /// use minidnd_eecs_havefun::{BattleMap,AreaShape,Position};
+    /// let map=BattleMap::new(20,20).unwrap();
+    /// let caster=Position::new(5,5);
+    /// //15尺锥形向东
+    /// let cone=map.area_squares(caster,Position::new(6,5),AreaShape::Cone{length:15});
+    /// assert_eq!(cone.len(),7);
+    /// assert!(cone.contains(&Position::new(8,6))&&!cone.contains(&Position::new(6,6)));
+    /// assert_eq!(map.area_squares(caster,Position::new(10,10),AreaShape::Sphere{radius:10}).len(),25);
+    /// //15尺立方体朝向西北展开
+    /// let cube=map.area_squares(caster,Position::new(4,4),AreaShape::Cube{size:15});
+    /// assert!(cube.contains(&Position::new(2,2))&&!cube.contains(&Position::new(5,5)));
+    /// let line=map.area_squares(caster,Position::new(6,6),AreaShape::Line{length:20,width:5});
+    /// assert_eq!(line,(6..10).map(|i|Position::new(i,i)).collect::<Vec<_>>());
+    /// ```
+    pub fn area_squares(&self,origin:Position,target:Position,shape:AreaShape)->Vec<Position>{
+        let (dx,dy)=((target.x-origin.x) as f64,(target.y-origin.y) as f64);
+        let norm=dx.hypot(dy);
+        let covers=|p:Position|->bool{
+            let (wx,wy)=((p.x-origin.x) as f64,(p.y-origin.y) as f64);
+            match shape{
+                AreaShape::Sphere{radius}|AreaShape::Cylinder{radius,..}=>BattleMap::distance_feet(target,p)<=radius,
+                AreaShape::Cube{size}=>{
+                    let n=(size/SQUARE_FEET).max(1);
+                    let step_x=if target.x>=origin.x {1} else {-1};
+                    let step_y=if target.y>=origin.y {1} else {-1};
+                    (0..n).contains(&((p.x-target.x)*step_x))&&(0..n).contains(&((p.y-target.y)*step_y))
+                },
+                _ if p==origin||norm==0.0=>false,
+                AreaShape::Cone{length}=>{
+                    //宽度等于距离，即半角为atan(1/2)
+                    BattleMap::distance_feet(origin,p)<=length&&(dx*wx+dy*wy)/(norm*wx.hypot(wy))>=2.0/5f64.sqrt()-1e-9
+                },
+                AreaShape::Line{length,width}=>{
+                    let along=(dx*wx+dy*wy)/norm;
+                    let across=(dx*wy-dy*wx).abs()/norm;
+                    along>0.0&&BattleMap::distance_feet(origin,p)<=length&&across<=width as f64/SQUARE_FEET as f64/2.0+1e-9
+                },
+            }
+        };
+        let mut squares=Vec::new();
+        for y in 0..self.height{
+            for x in 0..self.width{
+                let p=Position::new(x,y);
+                if covers(p) {squares.push(p);}
+            }
+        }
+        squares
+    }
+    ///效果范围内的所有参与者，按名字排序，参数与[`BattleMap::area_squares`]相同
+    pub fn in_area(&self,origin:Position,target:Position,shape:AreaShape)->Vec<String>{
+        let squares=self.area_squares(origin,target,shape);
+        let mut names:Vec<String>=self.positions.iter()
+        .filter(|(_,p)|squares.contains(p))
+        .map(|(name,_)|name.clone()).collect();
+        names.sort();
+        names
+    }
+    ///沿一条由相邻方格组成的路径移动所需的尺数，路径第一个方格为起点。路径不合法时返回None
+    pub fn path_cost_feet(&self,path:&[Position])->Option<i32>{
+        let mut cost=0;
+        for step in path.windows(2){
+            if BattleMap::distance_feet(step[0],step[1])!=SQUARE_FEET {return None}
+            cost+=self.terrain_at(step[1])?.movement_cost()?*SQUARE_FEET;
+        }
+        Some(cost)
+    }
+    ///让参与者沿路径移动，返回花费的尺数。途经的方格不能被其他参与者占据
+    /// ```
+    /// use minidnd_eecs_havefun::{BattleMap,Position,Terrain};
+    /// let mut map=BattleMap::new(10,10).unwrap();
+    /// map.set_terrain(Position::new(2,0),Terrain::Difficult).unwrap();
+    /// map.set_terrain(Position::new(1,1),Terrain::Impassable).unwrap();
+    /// map.place("Alice",Position::new(0,0)).unwrap();
+    /// assert!(map.move_along("Alice",&[Position::new(1,1)]).is_err());
+    /// assert_eq!(map.move_along("Alice",&[Position::new(1,0),Position::new(2,0)]).unwrap(),15);
+    /// assert_eq!(map.positions["Alice"],Position::new(2,0));
+    /// //路径可以经过自己的起点
+    /// assert_eq!(map.move_along("Alice",&[Position::new(1,0),Position::new(2,0),Position::new(3,0)]).unwrap(),20);
+    /// ```
+    pub fn move_along(&mut self,name:&str,path:&[Position])->Result<i32,DndError>{
+        let start=*self.positions.get(name).ok_or_else(||DndError::UnknownCombatant(name.to_string()))?;
+        let Some(&destination)=path.last() else {return Ok(0)};
+        let full_path:Vec<Position>=if path[0]==start {path.to_vec()} else {[&[start],path].concat()};
+        for &square in &full_path[1..]{
+            if !self.is_free_for(name,square){
+                return Err(DndError::SquareUnavailable{x:square.x,y:square.y});
+            }
+        }
+        let cost=self.path_cost_feet(&full_path).ok_or(DndError::SquareUnavailable{x:destination.x,y:destination.y})?;
+        self.positions.insert(name.to_string(),destination);
+        Ok(cost)
+    }
+    ///离目标方格最近的空闲方格，逐圈向外寻找。目标在地图外时从地图上离它最近的方格开始寻找
+    /// ```
+    /// use minidnd_eecs_havefun::{BattleMap,Position};
+    /// let map=BattleMap::new(5,5).unwrap();
+    /// assert_eq!(map.nearest_free(Position::new(100,-100)),Some(Position::new(4,0)));
+    /// ```
+    pub fn nearest_free(&self,target:Position)->Option<Position>{
+        let target=Position::new(target.x.clamp(0,self.width-1),target.y.clamp(0,self.height-1));
+        let max_radius=self.width.max(self.height);
+        for radius in 0..=max_radius{
+            let mut ring:Vec<Position>=Vec::new();
+            for dx in -radius..=radius{
+                for dy in -radius..=radius{
+                    if dx.abs().max(dy.abs())==radius {ring.push(Position::new(target.x+dx,target.y+dy));}
+                }
+            }
+            //同一圈内优先选择与目标坐标差之和较小的方格，保证结果确定
+            ring.sort_by_key(|p|((p.x-target.x).abs()+(p.y-target.y).abs(),p.x,p.y));
+            if let Some(p)=ring.into_iter().find(|p|self.is_free(*p)) {return Some(p)}
+        }
+        None
+    }
+    ///按照阵型和间距放置两个阵营。阵营1在西侧面朝东，阵营2在东侧面朝西，双方都沿地图的南北中线排开。
+    /// 每个阵营的第一个成员是领头者。目标方格不可用时放到最近的空闲方格
+    /// ```
+    /// use minidnd_eecs_havefun::{BattleMap,Formation,Spacing};
+    /// let mut map=BattleMap::new(40,20).unwrap();
+    /// let heroes=vec!["Alice".to_string(),"Bob".to_string(),"Carol".to_string()];
+    /// let goblins=vec!["Goblin 1".to_string(),"Goblin 2".to_string()];
+    /// map.establish_positions(&heroes,&goblins,Formation::Line,Spacing{between_members:10,between_factions:30}).unwrap();
+    /// assert_eq!(map.distance_between("Alice","Goblin 1"),Some(30));
+    /// assert_eq!(map.distance_between("Alice","Bob"),Some(10));
+    /// ```
+    pub fn establish_positions(&mut self,faction_1:&[String],faction_2:&[String],formation:Formation,spacing:Spacing)->Result<(),DndError>{
+        let gap=(spacing.between_factions/SQUARE_FEET).max(1);
+        let step=(spacing.between_members/SQUARE_FEET).max(1);
+        let middle_x=self.width/2;
+        let middle_y=self.height/2;
+        let front_1=Position::new(middle_x-gap/2-gap%2,middle_y);
+        let front_2=Position::new(middle_x+gap/2,middle_y);
+        for name in faction_1.iter().chain(faction_2){
+            self.positions.remove(name);
+        }
+        for (front,facing,faction) in [(front_1,1,faction_1),(front_2,-1,faction_2)]{
+            for (i,name) in faction.iter().enumerate(){
+                let (forward,side)=formation.offset(i,faction.len());
+                let target=Position::new(front.x+facing*forward*step,front.y+side*step);
+                let square=self.nearest_free(target).ok_or(DndError::NoRoomOnMap)?;
+                self.positions.insert(name.clone(),square);
+            }
+        }
+        Ok(())
+    }
+    ///放置任意多个阵营。两个阵营时与[`BattleMap::establish_positions`]相同；
+    /// 更多阵营时各阵营的领头者均匀分布在以地图中心为圆心、直径为阵营间距的圆上，面朝圆心，第一个阵营在西侧
+    /// ```
+    /// use minidnd_eecs_havefun::{BattleMap,Formation,Spacing};
+    /// let mut map=BattleMap::new(40,40).unwrap();
+    /// let factions=vec![vec!["Alice".to_string(),"Bob".to_string()],vec!["Bandit".to_string()],vec!["Owlbear".to_string()]];
+    /// map.establish_faction_positions(&factions,Formation::Line,Spacing{between_members:5,between_factions:60}).unwrap();
+    /// assert_eq!(map.positions.len(),4);
+    /// assert_eq!(map.distance_between("Alice","Bob"),Some(5));
+    /// assert!(map.distance_between("Alice","Bandit").unwrap()>=40);
+    /// assert!(map.distance_between("Bandit","Owlbear").unwrap()>=40);
+    /// ```
+    pub fn establish_faction_positions(&mut self,factions:&[Vec<String>],formation:Formation,spacing:Spacing)->Result<(),DndError>{
+        if let [faction_1,faction_2]=factions {
+            return self.establish_positions(faction_1,faction_2,formation,spacing)
+        }
+        let radius=f64::from((spacing.between_factions/SQUARE_FEET).max(1))/2.0;
+        let step=f64::from((spacing.between_members/SQUARE_FEET).max(1));
+        let center=(f64::from(self.width/2),f64::from(self.height/2));
+        for name in factions.iter().flatten(){
+            self.positions.remove(name);
+        }
+        for (k,faction) in factions.iter().enumerate(){
+            let angle=std::f64::consts::PI+2.0*std::f64::consts::PI*k as f64/factions.len() as f64;
+            //朝向圆心的方向以及它的垂直方向
+            let (facing_x,facing_y)=(-angle.cos(),-angle.sin());
+            let (side_x,side_y)=(-facing_y,facing_x);
+            let front=(center.0-facing_x*radius,center.1-facing_y*radius);
+            for (i,name) in faction.iter().enumerate(){
+                let (forward,side)=formation.offset(i,faction.len());
+                let (forward,side)=(f64::from(forward)*step,f64::from(side)*step);
+                let target=Position::new((front.0+facing_x*forward+side_x*side).round() as i32,
+                (front.1+facing_y*forward+side_y*side).round() as i32);
+                let square=self.nearest_free(target).ok_or(DndError::NoRoomOnMap)?;
+                self.positions.insert(name.clone(),square);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Formation{
+    ///第i个成员相对领头者的偏移（以间距为单位），第一个分量为向前的距离，第二个为向侧面的距离
+    pub fn offset(&self,i:usize,count:usize)->(i32,i32){
+        let i=i as i32;
+        //0,1,-1,2,-2,...
+        let alternate=|k:i32|if k%2==1 {(k+1)/2} else {-(k/2)};
+        match self{
+            Formation::Line=>(0,alternate(i)),
+            Formation::Column=>(-i,0),
+            Formation::Wedge=>{
+                let rank=(i+1)/2;
+                (-rank,if i%2==1 {-rank} else {rank})
+            },
+            Formation::Cluster=>{
+                let columns=(count as f64).sqrt().ceil().max(1.0) as i32;
+                (-(i/columns),alternate(i%columns))
+            },
+        }
+    }
+}
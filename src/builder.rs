@@ -0,0 +1,166 @@
+//!创建角色，来自第5版玩家手册第1章
+//!属性值有三种生成方式：购点（27点预算，每项8到15）、标准数组（15,14,13,12,10,8）以及投骰（4d6去掉最低的一个）。
+//!之后依次应用种族、背景与职业，得到1级的玩家
+use crate::{Abilities,AbilityScores,Background,CharacterClass,DiceExpr,DndError,Player,Race,Skill};
+use rand::Rng;
+
+///购点的总预算
+pub const POINT_BUY_BUDGET:i32=27;
+///标准数组，从高到低
+pub const STANDARD_ARRAY:[i32;6]=[15,14,13,12,10,8];
+
+///购点时某个属性值的花费，不在8到15之间时为None
+/// ```
+/// use minidnd_eecs_havefun::point_buy_cost;
+/// assert_eq!(point_buy_cost(8),Some(0));
+/// assert_eq!(point_buy_cost(13),Some(5));
+/// assert_eq!(point_buy_cost(15),Some(9));
+/// assert_eq!(point_buy_cost(16),None);
+/// ```
+pub fn point_buy_cost(score:i32)->Option<i32>{
+    match score{
+        8..=13=>Some(score-8),
+        14=>Some(7),
+        15=>Some(9),
+        _=>None,
+    }
+}
+
+///投出6个属性值，每个为4d6去掉最低的一个，按投出的顺序返回
+pub fn roll_ability_scores<R:Rng+?Sized>(rng:&mut R)->[i32;6]{
+    let expr=DiceExpr::parse("4d6dl1").expect("4d6dl1 is valid");
+    [();6].map(|_|expr.roll(rng).total)
+}
+
+#[derive(Clone,Debug)]
+///属性值的生成方式
+enum Generation{
+    PointBuy(AbilityScores),
+    ///按顺序分配的6个值以及对应的属性
+    Assigned([i32;6],[Abilities;6]),
+}
+
+#[derive(Clone,Debug,Default)]
+///角色创建器。必须选择一种属性值生成方式以及一个职业，种族与背景可选。
+/// 生成方式只保留最后一次选择的那一种
+/// ```
+/// use minidnd_eecs_havefun::{CharacterBuilder,CharacterClass,Race,Background,Abilities,Skill};
+/// use Abilities::*;
+/// let player=CharacterBuilder::new("Bruenor")
+/// .standard_array([Strength,Constitution,Wisdom,Dexterity,Charisma,Intelligence])
+/// .race(Race::mountain_dwarf("Smith's tools"))
+/// .background(Background::soldier())
+/// .class(CharacterClass::Fighter,&[Skill::Perception,Skill::Survival])
+/// .build().unwrap();
+/// assert_eq!((player.ability_scores.strength,player.ability_scores.constitution),(17,16));
+/// //战士生命骰d10，体质调整值+3
+/// assert_eq!((player.hp,player.max_hp,player.character_level()),(13,13,1));
+/// assert_eq!((player.walking_speed,player.armor),(25,11));
+/// assert!(player.skills.contains_key(&Skill::Athletics));
+/// ```
+pub struct CharacterBuilder{
+    name:String,
+    generation:Option<Generation>,
+    race:Option<Race>,
+    background:Option<Background>,
+    class:Option<(CharacterClass,Vec<Skill>)>,
+    faction:String,
+}
+
+impl CharacterBuilder{
+    pub fn new(name:&str)->CharacterBuilder{
+        CharacterBuilder{name:name.to_string(),..CharacterBuilder::default()}
+    }
+    ///购点生成属性值，在build时检查每项是否在8到15之间以及总花费是否超过27点
+    /// ```
+    /// use minidnd_eecs_havefun::{CharacterBuilder,CharacterClass,AbilityScores,DndError,Skill};
+    /// let fighter=|scores:AbilityScores|CharacterBuilder::new("Alice").point_buy(scores)
+    /// .class(CharacterClass::Fighter,&[Skill::Athletics,Skill::Perception]).build();
+    /// let scores=AbilityScores{strength:15,dexterity:14,constitution:14,intelligence:8,wisdom:10,charisma:10};
+    /// assert_eq!(fighter(scores).unwrap().ability_scores.constitution,14);
+    /// let scores=AbilityScores{strength:15,dexterity:15,constitution:14,intelligence:8,wisdom:10,charisma:10};
+    /// assert!(matches!(fighter(scores),Err(DndError::PointBuyOverBudget(29))));
+    /// let scores=AbilityScores{strength:16,..AbilityScores::default()};
+    /// assert!(matches!(fighter(scores),Err(DndError::PointBuyScoreOutOfRange{score:16,..})));
+    /// ```
+    pub fn point_buy(mut self,scores:AbilityScores)->CharacterBuilder{
+        self.generation=Some(Generation::PointBuy(scores));
+        self
+    }
+    ///标准数组，order中的属性依次得到15,14,13,12,10,8，每种属性只能出现一次
+    pub fn standard_array(mut self,order:[Abilities;6])->CharacterBuilder{
+        self.generation=Some(Generation::Assigned(STANDARD_ARRAY,order));
+        self
+    }
+    ///投骰生成属性值，投出的6个值从高到低依次分配给order中的属性
+    /// ```
+    /// use minidnd_eecs_havefun::{CharacterBuilder,CharacterClass,Abilities,Skill,seeded_rng};
+    /// use Abilities::*;
+    /// let build=||CharacterBuilder::new("Merlin")
+    /// .roll(&mut seeded_rng(4),[Intelligence,Constitution,Dexterity,Wisdom,Charisma,Strength])
+    /// .class(CharacterClass::Wizard,&[Skill::Arcana,Skill::History]).build().unwrap();
+    /// let player=build();
+    /// assert_eq!(player.ability_scores.intelligence,build().ability_scores.intelligence);
+    /// assert!(player.ability_scores.intelligence>=player.ability_scores.strength);
+    /// assert!((3..=18).contains(&player.ability_scores.strength));
+    /// ```
+    pub fn roll<R:Rng+?Sized>(mut self,rng:&mut R,order:[Abilities;6])->CharacterBuilder{
+        let mut rolls=roll_ability_scores(rng);
+        rolls.sort_unstable_by(|a,b|b.cmp(a));
+        self.generation=Some(Generation::Assigned(rolls,order));
+        self
+    }
+    pub fn race(mut self,race:Race)->CharacterBuilder{
+        self.race=Some(race);
+        self
+    }
+    pub fn background(mut self,background:Background)->CharacterBuilder{
+        self.background=Some(background);
+        self
+    }
+    ///第一个职业以及从职业可选技能中选择的技能
+    pub fn class(mut self,class:CharacterClass,skills:&[Skill])->CharacterBuilder{
+        self.class=Some((class,skills.to_vec()));
+        self
+    }
+    pub fn faction(mut self,faction:&str)->CharacterBuilder{
+        self.faction=faction.to_string();
+        self
+    }
+    ///检查属性值并创建1级玩家。种族的属性值提升在职业之前应用，因此生命值计入种族带来的体质调整值。
+    /// 护甲等级为没有穿甲时的护甲等级，见[`Player::calculate_armor_class`]
+    pub fn build(self)->Result<Player,DndError>{
+        let ability_scores=match self.generation{
+            Some(Generation::PointBuy(scores))=>check_point_buy(&scores)?,
+            Some(Generation::Assigned(values,order))=>assign(values,order)?,
+            None=>return Err(DndError::NoAbilityScores),
+        };
+        let (class,skills)=self.class.ok_or(DndError::NoClass)?;
+        let mut player=Player{name:self.name,faction:self.faction,ability_scores,walking_speed:30,..Player::default()};
+        if let Some(race)=self.race {player.set_race(race);}
+        if let Some(background)=self.background {player.set_background(background);}
+        player.choose_class(class,&skills)?;
+        player.update_armor_class();
+        Ok(player)
+    }
+}
+
+fn check_point_buy(scores:&AbilityScores)->Result<AbilityScores,DndError>{
+    use Abilities::*;
+    let mut spent=0;
+    for ability in [Strength,Dexterity,Constitution,Intelligence,Wisdom,Charisma]{
+        let score=scores.get(ability);
+        spent+=point_buy_cost(score).ok_or(DndError::PointBuyScoreOutOfRange{ability,score})?;
+    }
+    if spent>POINT_BUY_BUDGET {return Err(DndError::PointBuyOverBudget(spent))}
+    Ok(*scores)
+}
+
+fn assign(values:[i32;6],order:[Abilities;6])->Result<AbilityScores,DndError>{
+    let mut scores=AbilityScores::default();
+    for (i,ability) in order.iter().enumerate(){
+        if order[..i].contains(ability) {return Err(DndError::DuplicateAbilityAssignment(*ability))}
+        *scores.get_mut(*ability)=values[i];
+    }
+    Ok(scores)
+}
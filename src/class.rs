@@ -0,0 +1,546 @@
+//!职业。12个核心职业的生命骰、熟练项以及各等级获得的特性，来自第5版玩家手册第3章
+//!选择职业时按照1级的规则设置生命值与熟练项，之后经验值达到下一等级的门槛时通过[`Player::level_up`]升级
+use crate::{Abilities,DndError,Feat,InformationGetter,Player,Proficiency,Skill,WeaponCategory,DNDChecker};
+use rand::Rng;
+use serde::{Serialize,Deserialize};
+use std::collections::HashSet;
+use std::fmt;
+
+///属性值提升特性的名字，获得这个特性的等级需要选择[`AbilityScoreImprovement`]
+pub const ABILITY_SCORE_IMPROVEMENT:&str="Ability Score Improvement";
+///属性值提升的上限
+pub const ABILITY_SCORE_CAP:i32=20;
+
+#[derive(Copy,Clone,Debug,PartialEq,Eq,Hash,PartialOrd,Ord,Serialize,Deserialize)]
+///职业
+pub enum CharacterClass{
+    Barbarian,//野蛮人
+    Bard,//吟游诗人
+    Cleric,//牧师
+    Druid,//德鲁伊
+    Fighter,//战士
+    Monk,//武僧
+    Paladin,//圣武士
+    Ranger,//游侠
+    Rogue,//游荡者
+    Sorcerer,//术士
+    Warlock,//邪术师
+    Wizard,//法师
+}
+
+#[derive(Copy,Clone,Debug,PartialEq,Eq,Hash,Serialize,Deserialize)]
+///护甲类别
+pub enum ArmorCategory{
+    Light,//轻甲
+    Medium,//中甲
+    Heavy,//重甲
+    Shield,//盾牌
+}
+
+#[derive(Copy,Clone,Debug,PartialEq,Eq,Hash,Serialize,Deserialize)]
+///施法能力随职业等级的成长方式，决定兼职时的法术位
+pub enum SpellcastingProgression{
+    None,//不能施法
+    Full,//全施法者，每一级算作1个施法者等级
+    Half,//半施法者，2级开始施法，每两级算作1个施法者等级
+    Pact,//契约魔法，法术位独立计算
+}
+
+#[derive(Copy,Clone,Debug,Default,PartialEq,Eq,Serialize,Deserialize)]
+///升级时生命值的增加方式
+pub enum HitPointGain{
+    ///投一次生命骰
+    Roll,
+    ///取生命骰的平均值（向上取整），即生命骰的一半加1
+    #[default] Average,
+}
+
+#[derive(Copy,Clone,Debug,PartialEq,Eq,Serialize,Deserialize)]
+///属性值提升的选择
+pub enum AbilityScoreImprovement{
+    ///一项属性+2
+    Increase(Abilities),
+    ///两项属性各+1
+    Split(Abilities,Abilities),
+    ///放弃属性值提升，改为获得一个专长
+    Feat(Feat),
+}
+
+#[derive(Clone,Debug,PartialEq,Eq,Serialize,Deserialize)]
+///一次升级的结果
+pub struct LevelUp{
+    ///升级的职业
+    pub class:CharacterClass,
+    ///升级后这个职业的等级
+    pub level:i32,
+    ///增加的生命值上限
+    pub hp_gained:i32,
+    ///新获得的特性
+    pub features:Vec<String>,
+}
+
+impl CharacterClass{
+    ///全部12个职业
+    pub const ALL:[CharacterClass;12]=[
+        CharacterClass::Barbarian,CharacterClass::Bard,CharacterClass::Cleric,CharacterClass::Druid,
+        CharacterClass::Fighter,CharacterClass::Monk,CharacterClass::Paladin,CharacterClass::Ranger,
+        CharacterClass::Rogue,CharacterClass::Sorcerer,CharacterClass::Warlock,CharacterClass::Wizard,
+    ];
+    ///生命骰的面数
+    pub fn hit_die(&self)->i32{
+        match self{
+            CharacterClass::Barbarian=>12,
+            CharacterClass::Fighter|CharacterClass::Paladin|CharacterClass::Ranger=>10,
+            CharacterClass::Sorcerer|CharacterClass::Wizard=>6,
+            _=>8,
+        }
+    }
+    ///生命骰的平均值，向上取整
+    pub fn average_hit_points(&self)->i32{
+        self.hit_die()/2+1
+    }
+    ///熟练的两种豁免
+    pub fn saving_throws(&self)->[Abilities;2]{
+        use Abilities::*;
+        match self{
+            CharacterClass::Barbarian|CharacterClass::Fighter=>[Strength,Constitution],
+            CharacterClass::Bard=>[Dexterity,Charisma],
+            CharacterClass::Cleric|CharacterClass::Paladin|CharacterClass::Warlock=>[Wisdom,Charisma],
+            CharacterClass::Druid|CharacterClass::Wizard=>[Intelligence,Wisdom],
+            CharacterClass::Monk|CharacterClass::Ranger=>[Strength,Dexterity],
+            CharacterClass::Rogue=>[Dexterity,Intelligence],
+            CharacterClass::Sorcerer=>[Constitution,Charisma],
+        }
+    }
+    ///1级时可以选择的技能数量以及可选的技能
+    pub fn skill_choices(&self)->(usize,&'static [Skill]){
+        use Skill::*;
+        match self{
+            CharacterClass::Barbarian=>(2,&[AnimalHandling,Athletics,Intimidation,Nature,Perception,Survival]),
+            CharacterClass::Bard=>(3,&Skill::ALL),
+            CharacterClass::Cleric=>(2,&[History,Insight,Medicine,Persuasion,Religion]),
+            CharacterClass::Druid=>(2,&[Arcana,AnimalHandling,Insight,Medicine,Nature,Perception,Religion,Survival]),
+            CharacterClass::Fighter=>(2,&[Acrobatics,AnimalHandling,Athletics,History,Insight,Intimidation,Perception,Survival]),
+            CharacterClass::Monk=>(2,&[Acrobatics,Athletics,History,Insight,Religion,Stealth]),
+            CharacterClass::Paladin=>(2,&[Athletics,Insight,Intimidation,Medicine,Persuasion,Religion]),
+            CharacterClass::Ranger=>(3,&[AnimalHandling,Athletics,Insight,Investigation,Nature,Perception,Stealth,Survival]),
+            CharacterClass::Rogue=>(4,&[Acrobatics,Athletics,Deception,Insight,Intimidation,Investigation,Perception,
+                Performance,Persuasion,SleightOfHand,Stealth]),
+            CharacterClass::Sorcerer=>(2,&[Arcana,Deception,Insight,Intimidation,Persuasion,Religion]),
+            CharacterClass::Warlock=>(2,&[Arcana,Deception,History,Intimidation,Investigation,Nature,Religion]),
+            CharacterClass::Wizard=>(2,&[Arcana,History,Insight,Investigation,Medicine,Religion]),
+        }
+    }
+    ///熟练的护甲类别
+    pub fn armor_proficiencies(&self)->&'static [ArmorCategory]{
+        use ArmorCategory::*;
+        match self{
+            CharacterClass::Barbarian|CharacterClass::Cleric|CharacterClass::Druid|CharacterClass::Ranger=>&[Light,Medium,Shield],
+            CharacterClass::Fighter|CharacterClass::Paladin=>&[Light,Medium,Heavy,Shield],
+            CharacterClass::Bard|CharacterClass::Rogue|CharacterClass::Warlock=>&[Light],
+            CharacterClass::Monk|CharacterClass::Sorcerer|CharacterClass::Wizard=>&[],
+        }
+    }
+    ///熟练的武器类别。只熟练个别武器的职业（例如法师的匕首与短棍）不在这里列出
+    pub fn weapon_proficiencies(&self)->&'static [WeaponCategory]{
+        use WeaponCategory::*;
+        match self{
+            CharacterClass::Barbarian|CharacterClass::Fighter|CharacterClass::Paladin|CharacterClass::Ranger=>
+            &[SimpleMelee,SimpleRanged,MartialMelee,MartialRanged],
+            CharacterClass::Bard|CharacterClass::Cleric|CharacterClass::Monk|CharacterClass::Rogue|CharacterClass::Warlock=>
+            &[SimpleMelee,SimpleRanged],
+            CharacterClass::Druid|CharacterClass::Sorcerer|CharacterClass::Wizard=>&[],
+        }
+    }
+    ///固定的工具熟练项，需要自选的工具（例如吟游诗人的乐器）不在这里列出
+    pub fn tool_proficiencies(&self)->&'static [&'static str]{
+        match self{
+            CharacterClass::Druid=>&["Herbalism kit"],
+            CharacterClass::Rogue=>&["Thieves' tools"],
+            _=>&[],
+        }
+    }
+    ///在某个等级获得的特性
+    /// ```
+    /// use minidnd_eecs_havefun::{CharacterClass,ABILITY_SCORE_IMPROVEMENT};
+    /// assert_eq!(CharacterClass::Fighter.features(2),["Action Surge (one use)"]);
+    /// assert!(CharacterClass::Fighter.features(6).contains(&ABILITY_SCORE_IMPROVEMENT));
+    /// assert!(!CharacterClass::Wizard.features(6).contains(&ABILITY_SCORE_IMPROVEMENT));
+    /// assert!(CharacterClass::Bard.features(21).is_empty());
+    /// ```
+    pub fn features(&self,level:i32)->&'static [&'static str]{
+        const ASI:&str=ABILITY_SCORE_IMPROVEMENT;
+        match (self,level){
+            (_,4|8|12|16|19)=>match (self,level){
+                (CharacterClass::Cleric,8)=>&[ASI,"Destroy Undead (CR 1)","Divine Domain feature"],
+                (CharacterClass::Druid,4|8)=>&["Wild Shape improvement",ASI],
+                (CharacterClass::Monk,4)=>&[ASI,"Slow Fall"],
+                (CharacterClass::Ranger,8)=>&[ASI,"Land's Stride"],
+                _=>&[ASI],
+            },
+            (CharacterClass::Fighter,6|14)|(CharacterClass::Rogue,10)=>&[ASI],
+            (CharacterClass::Barbarian,l)=>match l{
+                1=>&["Rage","Unarmored Defense"],
+                2=>&["Reckless Attack","Danger Sense"],
+                3=>&["Primal Path"],
+                5=>&["Extra Attack","Fast Movement"],
+                6|10|14=>&["Path feature"],
+                7=>&["Feral Instinct"],
+                9=>&["Brutal Critical (1 die)"],
+                11=>&["Relentless Rage"],
+                13=>&["Brutal Critical (2 dice)"],
+                15=>&["Persistent Rage"],
+                17=>&["Brutal Critical (3 dice)"],
+                18=>&["Indomitable Might"],
+                20=>&["Primal Champion"],
+                _=>&[],
+            },
+            (CharacterClass::Bard,l)=>match l{
+                1=>&["Spellcasting","Bardic Inspiration (d6)"],
+                2=>&["Jack of All Trades","Song of Rest (d6)"],
+                3=>&["Bard College","Expertise"],
+                5=>&["Bardic Inspiration (d8)","Font of Inspiration"],
+                6=>&["Countercharm","Bard College feature"],
+                9=>&["Song of Rest (d8)"],
+                10=>&["Bardic Inspiration (d10)","Expertise","Magical Secrets"],
+                13=>&["Song of Rest (d10)"],
+                14=>&["Magical Secrets","Bard College feature"],
+                15=>&["Bardic Inspiration (d12)"],
+                17=>&["Song of Rest (d12)"],
+                18=>&["Magical Secrets"],
+                20=>&["Superior Inspiration"],
+                _=>&[],
+            },
+            (CharacterClass::Cleric,l)=>match l{
+                1=>&["Spellcasting","Divine Domain"],
+                2=>&["Channel Divinity (1/rest)","Divine Domain feature"],
+                5=>&["Destroy Undead (CR 1/2)"],
+                6=>&["Channel Divinity (2/rest)","Divine Domain feature"],
+                10=>&["Divine Intervention"],
+                11=>&["Destroy Undead (CR 2)"],
+                14=>&["Destroy Undead (CR 3)"],
+                17=>&["Destroy Undead (CR 4)","Divine Domain feature"],
+                18=>&["Channel Divinity (3/rest)"],
+                20=>&["Divine Intervention improvement"],
+                _=>&[],
+            },
+            (CharacterClass::Druid,l)=>match l{
+                1=>&["Druidic","Spellcasting"],
+                2=>&["Wild Shape","Druid Circle"],
+                6|10|14=>&["Druid Circle feature"],
+                18=>&["Timeless Body","Beast Spells"],
+                20=>&["Archdruid"],
+                _=>&[],
+            },
+            (CharacterClass::Fighter,l)=>match l{
+                1=>&["Fighting Style","Second Wind"],
+                2=>&["Action Surge (one use)"],
+                3=>&["Martial Archetype"],
+                5=>&["Extra Attack"],
+                7|10|15|18=>&["Martial Archetype feature"],
+                9=>&["Indomitable (one use)"],
+                11=>&["Extra Attack (2)"],
+                13=>&["Indomitable (two uses)"],
+                17=>&["Action Surge (two uses)","Indomitable (three uses)"],
+                20=>&["Extra Attack (3)"],
+                _=>&[],
+            },
+            (CharacterClass::Monk,l)=>match l{
+                1=>&["Unarmored Defense","Martial Arts"],
+                2=>&["Ki","Unarmored Movement"],
+                3=>&["Monastic Tradition","Deflect Missiles"],
+                5=>&["Extra Attack","Stunning Strike"],
+                6=>&["Ki-Empowered Strikes","Monastic Tradition feature"],
+                7=>&["Evasion","Stillness of Mind"],
+                9=>&["Unarmored Movement improvement"],
+                10=>&["Purity of Body"],
+                11|17=>&["Monastic Tradition feature"],
+                13=>&["Tongue of the Sun and Moon"],
+                14=>&["Diamond Soul"],
+                15=>&["Timeless Body"],
+                18=>&["Empty Body"],
+                20=>&["Perfect Self"],
+                _=>&[],
+            },
+            (CharacterClass::Paladin,l)=>match l{
+                1=>&["Divine Sense","Lay on Hands"],
+                2=>&["Fighting Style","Spellcasting","Divine Smite"],
+                3=>&["Divine Health","Sacred Oath"],
+                5=>&["Extra Attack"],
+                6=>&["Aura of Protection"],
+                7|15|20=>&["Sacred Oath feature"],
+                10=>&["Aura of Courage"],
+                11=>&["Improved Divine Smite"],
+                14=>&["Cleansing Touch"],
+                18=>&["Aura improvements"],
+                _=>&[],
+            },
+            (CharacterClass::Ranger,l)=>match l{
+                1=>&["Favored Enemy","Natural Explorer"],
+                2=>&["Fighting Style","Spellcasting"],
+                3=>&["Ranger Archetype","Primeval Awareness"],
+                5=>&["Extra Attack"],
+                6=>&["Favored Enemy improvement","Natural Explorer improvement"],
+                7|11|15=>&["Ranger Archetype feature"],
+                10=>&["Natural Explorer improvement","Hide in Plain Sight"],
+                14=>&["Favored Enemy improvement","Vanish"],
+                18=>&["Feral Senses"],
+                20=>&["Foe Slayer"],
+                _=>&[],
+            },
+            (CharacterClass::Rogue,l)=>match l{
+                1=>&["Expertise","Sneak Attack","Thieves' Cant"],
+                2=>&["Cunning Action"],
+                3=>&["Roguish Archetype"],
+                5=>&["Uncanny Dodge"],
+                6=>&["Expertise"],
+                7=>&["Evasion"],
+                9|13|17=>&["Roguish Archetype feature"],
+                11=>&["Reliable Talent"],
+                14=>&["Blindsense"],
+                15=>&["Slippery Mind"],
+                18=>&["Elusive"],
+                20=>&["Stroke of Luck"],
+                _=>&[],
+            },
+            (CharacterClass::Sorcerer,l)=>match l{
+                1=>&["Spellcasting","Sorcerous Origin"],
+                2=>&["Font of Magic"],
+                3|10|17=>&["Metamagic"],
+                6|14|18=>&["Sorcerous Origin feature"],
+                20=>&["Sorcerous Restoration"],
+                _=>&[],
+            },
+            (CharacterClass::Warlock,l)=>match l{
+                1=>&["Otherworldly Patron","Pact Magic"],
+                2=>&["Eldritch Invocations"],
+                3=>&["Pact Boon"],
+                6|10|14=>&["Otherworldly Patron feature"],
+                11=>&["Mystic Arcanum (6th level)"],
+                13=>&["Mystic Arcanum (7th level)"],
+                15=>&["Mystic Arcanum (8th level)"],
+                17=>&["Mystic Arcanum (9th level)"],
+                20=>&["Eldritch Master"],
+                _=>&[],
+            },
+            (CharacterClass::Wizard,l)=>match l{
+                1=>&["Spellcasting","Arcane Recovery"],
+                2=>&["Arcane Tradition"],
+                6|10|14=>&["Arcane Tradition feature"],
+                18=>&["Spell Mastery"],
+                20=>&["Signature Spells"],
+                _=>&[],
+            },
+        }
+    }
+    ///施法能力的成长方式
+    pub fn spellcasting_progression(&self)->SpellcastingProgression{
+        match self{
+            CharacterClass::Bard|CharacterClass::Cleric|CharacterClass::Druid|CharacterClass::Sorcerer|CharacterClass::Wizard=>SpellcastingProgression::Full,
+            CharacterClass::Paladin|CharacterClass::Ranger=>SpellcastingProgression::Half,
+            CharacterClass::Warlock=>SpellcastingProgression::Pact,
+            CharacterClass::Barbarian|CharacterClass::Fighter|CharacterClass::Monk|CharacterClass::Rogue=>SpellcastingProgression::None,
+        }
+    }
+    ///在某个等级是否获得属性值提升
+    pub fn grants_ability_score_improvement(&self,level:i32)->bool{
+        self.features(level).contains(&ABILITY_SCORE_IMPROVEMENT)
+    }
+}
+
+impl fmt::Display for CharacterClass{
+    fn fmt(&self,f:&mut fmt::Formatter<'_>)->fmt::Result{
+        write!(f,"{:?}",self)
+    }
+}
+
+impl Player{
+    ///当前经验值对应的等级，即角色等级的上限
+    pub fn experience_level(&self)->i32{
+        <Self as InformationGetter>::exp_to_level(self.exp).unwrap_or(1)
+    }
+    ///角色等级，即所有职业等级之和。还没有选择职业时为0
+    pub fn character_level(&self)->i32{
+        self.classes.iter().map(|(_,levels)|levels).sum()
+    }
+    ///某个职业的等级，没有这个职业时为0
+    pub fn class_level(&self,class:CharacterClass)->i32{
+        self.classes.iter().find(|(c,_)|*c==class).map_or(0,|(_,levels)|*levels)
+    }
+    ///选择第一个职业，按照1级的规则设置：生命值为生命骰的最大值加体质调整值，获得豁免、护甲、武器、工具熟练项，
+    /// 从职业的可选技能中选择skills，以及1级的特性。之后加入其他职业见[`Player::multiclass`]
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,CharacterClass,ArmorCategory,Abilities,Skill,DNDChecker};
+    /// let mut player=Player::new_by_default();
+    /// assert!(player.choose_class(CharacterClass::Fighter,&[Skill::Athletics]).is_err());
+    /// assert!(player.choose_class(CharacterClass::Fighter,&[Skill::Athletics,Skill::Arcana]).is_err());
+    /// player.choose_class(CharacterClass::Fighter,&[Skill::Athletics,Skill::Perception]).unwrap();
+    /// //体质13，调整值+1
+    /// assert_eq!((player.hp,player.max_hp),(11,11));
+    /// assert!(player.armor_proficiencies.contains(&ArmorCategory::Heavy));
+    /// assert_eq!(player.saving_throw_modifiers().get(Abilities::Strength),2);
+    /// assert_eq!(player.features,vec!["Fighting Style".to_string(),"Second Wind".to_string()]);
+    /// assert!(player.choose_class(CharacterClass::Wizard,&[Skill::Arcana,Skill::History]).is_err());
+    /// ```
+    ///已经熟练的技能不能再选，需要另选一个，这样撤销种族或背景时不会失去职业技能
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,CharacterClass,Race,Skill,DndError};
+    /// let mut player=Player::new_by_default();
+    /// player.set_race(Race::wood_elf());
+    /// assert!(matches!(player.choose_class(CharacterClass::Fighter,&[Skill::Perception,Skill::Athletics]),
+    /// Err(DndError::SkillAlreadyProficient(Skill::Perception))));
+    /// player.choose_class(CharacterClass::Fighter,&[Skill::Survival,Skill::Athletics]).unwrap();
+    /// player.remove_race();
+    /// assert!(!player.skills.contains_key(&Skill::Perception));
+    /// assert!(player.skills.contains_key(&Skill::Survival));
+    /// ```
+    pub fn choose_class(&mut self,class:CharacterClass,skills:&[Skill])->Result<(),DndError>{
+        if let Some((existing,_))=self.classes.first() {return Err(DndError::ClassAlreadyChosen(*existing))}
+        let (count,choices)=class.skill_choices();
+        self.choose_skills(count,choices,skills)?;
+        for ability in class.saving_throws(){
+            self.saving_throw_sources(ability).insert(class.to_string());
+        }
+        self.armor_proficiencies.extend(class.armor_proficiencies());
+        self.weapon_proficiencies.extend(class.weapon_proficiencies());
+        self.tool_proficiencies.extend(class.tool_proficiencies().iter().map(|t|t.to_string()));
+        self.classes.push((class,1));
+        let hp=(class.hit_die()+self.ability_scores_to_modifiers().constitution).max(1);
+        self.hp=hp;
+        self.max_hp=hp;
+        self.gain_features(class,1);
+        self.update_armor_class();
+        Ok(())
+    }
+    ///检查选择的技能并获得熟练。已经熟练的技能不能再选
+    pub(crate) fn choose_skills(&mut self,count:usize,choices:&[Skill],skills:&[Skill])->Result<(),DndError>{
+        if skills.len()!=count {return Err(DndError::WrongSkillChoiceCount{expected:count,given:skills.len()})}
+        for (i,skill) in skills.iter().enumerate(){
+            if !choices.contains(skill)||skills[..i].contains(skill) {return Err(DndError::InvalidSkillChoice(*skill))}
+            if self.skills.contains_key(skill) {return Err(DndError::SkillAlreadyProficient(*skill))}
+        }
+        for skill in skills{
+            self.skills.insert(*skill,Proficiency::Proficient);
+        }
+        Ok(())
+    }
+    ///豁免熟练项的来源集合
+    pub(crate) fn saving_throw_sources(&mut self,ability:Abilities)->&mut HashSet<String>{
+        match ability{
+            Abilities::Strength=>&mut self.skills_for_st_strength,
+            Abilities::Dexterity=>&mut self.skills_for_st_dexterity,
+            Abilities::Constitution=>&mut self.skills_for_st_constitution,
+            Abilities::Intelligence=>&mut self.skills_for_st_intelligence,
+            Abilities::Wisdom=>&mut self.skills_for_st_wisdom,
+            Abilities::Charisma=>&mut self.skills_for_st_charisma,
+        }
+    }
+    ///获得某个职业某个等级的特性，返回特性的名字。有规则效果的特性（例如万事通）同时生效
+    pub(crate) fn gain_features(&mut self,class:CharacterClass,level:i32)->Vec<String>{
+        let features:Vec<String>=class.features(level).iter()
+        .filter(|f|**f!=ABILITY_SCORE_IMPROVEMENT).map(|f|f.to_string()).collect();
+        if features.iter().any(|f|f=="Jack of All Trades") {self.jack_of_all_trades=true;}
+        self.features.extend(features.iter().cloned());
+        features
+    }
+    ///经验值是否已经足够升到下一级
+    pub fn can_level_up(&self)->bool{
+        !self.classes.is_empty()&&self.character_level()<self.experience_level()
+    }
+    ///在已有的职业上升一级：生命值上限增加生命骰（投掷或平均值）加体质调整值，至少为1；获得新的特性。
+    /// 属性值提升取决于这个职业的等级，新等级有属性值提升时必须给出asi，否则不能给出
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,CharacterClass,HitPointGain,AbilityScoreImprovement,Abilities,Skill,seeded_rng};
+    /// let mut player=Player::new_by_default();
+    /// let bard=CharacterClass::Bard;
+    /// player.choose_class(bard,&[Skill::Performance,Skill::Persuasion,Skill::Deception]).unwrap();
+    /// let mut rng=seeded_rng(1);
+    /// assert!(player.level_up(&mut rng,bard,HitPointGain::Average,None).is_err());
+    /// player.exp=2700;
+    /// let report=player.level_up(&mut rng,bard,HitPointGain::Average,None).unwrap();
+    /// assert_eq!((report.level,report.hp_gained,player.max_hp),(2,6,15));
+    /// assert!(player.jack_of_all_trades);
+    /// assert!(player.level_up(&mut rng,CharacterClass::Wizard,HitPointGain::Average,None).is_err());
+    /// player.level_up(&mut rng,bard,HitPointGain::Roll,None).unwrap();
+    /// assert!(player.level_up(&mut rng,bard,HitPointGain::Average,None).is_err());
+    /// player.level_up(&mut rng,bard,HitPointGain::Average,Some(AbilityScoreImprovement::Increase(Abilities::Charisma))).unwrap();
+    /// assert_eq!((player.class_level(bard),player.ability_scores.charisma),(4,10));
+    /// assert!(!player.can_level_up());
+    /// ```
+    ///提升体质时，之前每个等级的生命值上限都按新的体质调整值追溯增加
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,CharacterClass,HitPointGain,AbilityScoreImprovement,Abilities,Skill,seeded_rng};
+    /// let mut player=Player::new_by_default();
+    /// let fighter=CharacterClass::Fighter;
+    /// player.choose_class(fighter,&[Skill::Athletics,Skill::Perception]).unwrap();
+    /// player.exp=2700;
+    /// let mut rng=seeded_rng(1);
+    /// for _ in 0..2 {player.level_up(&mut rng,fighter,HitPointGain::Average,None).unwrap();}
+    /// assert_eq!(player.max_hp,25);
+    /// //体质13提升到15，前3级每级+1，第4级为6+2
+    /// player.level_up(&mut rng,fighter,HitPointGain::Average,Some(AbilityScoreImprovement::Increase(Abilities::Constitution))).unwrap();
+    /// assert_eq!((player.hp,player.max_hp),(36,36));
+    /// ```
+    pub fn level_up<R:Rng+?Sized>(&mut self,rng:&mut R,class:CharacterClass,hp:HitPointGain,asi:Option<AbilityScoreImprovement>)->Result<LevelUp,DndError>{
+        if self.classes.is_empty() {return Err(DndError::NoClass)}
+        let index=self.classes.iter().position(|(c,_)|*c==class).ok_or(DndError::NotInClass(class))?;
+        self.check_experience()?;
+        let level=self.classes[index].1+1;
+        match (class.grants_ability_score_improvement(level),asi){
+            (true,None)=>return Err(DndError::MissingAbilityScoreImprovement(level)),
+            (false,Some(_))=>return Err(DndError::UnexpectedAbilityScoreImprovement(level)),
+            (true,Some(asi))=>self.improve_ability_scores(asi)?,
+            (false,None)=>(),
+        }
+        let hp_gained=self.gain_hit_points(rng,class,hp);
+        self.classes[index].1=level;
+        let features=self.gain_features(class,level);
+        Ok(LevelUp{class,level,hp_gained,features})
+    }
+    ///经验值是否足够让角色等级再升一级
+    pub(crate) fn check_experience(&self)->Result<(),DndError>{
+        let level=self.character_level()+1;
+        if level>self.experience_level() {Err(DndError::NotEnoughExperience{level,exp:self.exp})}
+        else {Ok(())}
+    }
+    ///升级时增加生命值，返回增加的生命值
+    pub(crate) fn gain_hit_points<R:Rng+?Sized>(&mut self,rng:&mut R,class:CharacterClass,hp:HitPointGain)->i32{
+        let die=match hp{
+            HitPointGain::Roll=>Player::dice_complex(rng,class.hit_die(),1,0),
+            HitPointGain::Average=>class.average_hit_points(),
+        };
+        let hp_gained=(die+self.ability_scores_to_modifiers().constitution).max(1);
+        self.max_hp+=hp_gained;
+        self.hp+=hp_gained;
+        hp_gained
+    }
+    ///体质调整值从old_modifier变为当前值时，按角色等级追溯调整生命值上限与当前生命值
+    pub(crate) fn update_constitution_hit_points(&mut self,old_modifier:i32){
+        let change=(self.ability_scores_to_modifiers().constitution-old_modifier)*self.character_level();
+        if change==0 {return}
+        self.max_hp=(self.max_hp+change).max(1);
+        if self.hp>0 {self.hp=(self.hp+change).min(self.hit_point_maximum()).max(1);}
+    }
+    ///应用属性值提升，提升后的属性值不能超过20。体质调整值提高时追溯增加生命值
+    fn improve_ability_scores(&mut self,asi:AbilityScoreImprovement)->Result<(),DndError>{
+        let increases:&[(Abilities,i32)]=match &asi{
+            AbilityScoreImprovement::Increase(ability)=>&[(*ability,2)],
+            AbilityScoreImprovement::Split(first,second)=>&[(*first,1),(*second,1)],
+            AbilityScoreImprovement::Feat(feat)=>{
+                self.feats.insert(*feat);
+                return Ok(())
+            },
+        };
+        let mut scores=self.ability_scores;
+        for (ability,amount) in increases{
+            let score=scores.get_mut(*ability);
+            *score+=amount;
+            if *score>ABILITY_SCORE_CAP {return Err(DndError::AbilityScoreTooHigh{ability:*ability,score:*score})}
+        }
+        let constitution=self.ability_scores_to_modifiers().constitution;
+        self.ability_scores=scores;
+        self.update_armor_class();
+        self.update_constitution_hit_points(constitution);
+        Ok(())
+    }
+}
@@ -0,0 +1,304 @@
+//!战斗参与者的通用接口
+//![`Combat`](crate::Combat)中的各个步骤、[`CombatEncounter`](crate::CombatEncounter)以及攻击的目标都只依赖[`Combatant`]，
+//!因此除了玩家与怪物之外，召唤物、陷阱、环境危害等都可以加入战斗。
+//!不同类型的参与者混在一起时使用`Box<dyn Combatant>`
+use crate::{Abilities,ActiveCondition,Concentration,ConcentrationTracker,Feat,Condition,ConditionTracker,DamageType,DeathSaveOutcome,DNDChecker,LifeState,Monster,Player,Skill};
+use crate::condition::{combine_advantage,Conditions};
+use rand::{Rng,RngCore};
+
+///战斗参与者。随机数生成器以`&mut dyn RngCore`传入，使这个trait可以作为trait对象使用
+/// ```
+/// use minidnd_eecs_havefun::{Combatant,DamageType};
+/// use rand::RngCore;
+/// //一个会被打坏的落石陷阱
+/// struct Trap{hp:i32}
+/// impl Combatant for Trap{
+///     fn name(&self)->&str {"Falling rocks"}
+///     fn faction(&self)->&str {"hazards"}
+///     fn initiative_bonus(&self)->i32 {0}
+///     fn passive_perception(&self)->i32 {0}
+///     fn stealth_roll(&self,_rng:&mut dyn RngCore)->i32 {15}
+///     fn armor_class(&self)->i32 {11}
+///     fn hp(&self)->i32 {self.hp}
+///     fn max_hp(&self)->i32 {20}
+///     fn take_damage(&mut self,amount:i32,_damage_type:DamageType,_magical:bool,_critical:bool)->i32{
+///         self.hp=(self.hp-amount).max(0);
+///         amount
+///     }
+/// }
+/// let mut trap:Box<dyn Combatant>=Box::new(Trap{hp:20});
+/// trap.take_damage(25,DamageType::Blugeon,false,false);
+/// assert!(trap.is_defeated());
+/// ```
+pub trait Combatant{
+    ///名字，在一场战斗中唯一
+    fn name(&self)->&str;
+    ///所属阵营的名字
+    fn faction(&self)->&str;
+    ///先攻加值，包括敏捷调整值以及警觉等专长
+    fn initiative_bonus(&self)->i32;
+    ///被动察觉
+    fn passive_perception(&self)->i32;
+    ///一次隐匿检定的检定值
+    fn stealth_roll(&self,rng:&mut dyn RngCore)->i32;
+    ///护甲等级
+    fn armor_class(&self)->i32;
+    fn hp(&self)->i32;
+    fn max_hp(&self)->i32;
+    ///受到伤害，按照自身的抗性等调整后扣除，返回调整后的伤害。critical表示伤害是否来自重击
+    fn take_damage(&mut self,amount:i32,damage_type:DamageType,magical:bool,critical:bool)->i32;
+    ///当前的状态，默认没有
+    fn conditions(&self)->&[ActiveCondition]{
+        &[]
+    }
+    ///每回合的移动力（尺），默认不能移动
+    fn speed(&self)->i32{
+        0
+    }
+    ///是否已经倒下，默认生命值为0即倒下
+    fn is_defeated(&self)->bool{
+        self.hp()<=0
+    }
+    ///是否已经死亡，死亡的参与者从先攻顺序中移除。默认倒下即死亡
+    fn is_dead(&self)->bool{
+        self.is_defeated()
+    }
+    ///能否被突袭，默认可以
+    fn can_be_surprised(&self)->bool{
+        true
+    }
+    ///敏捷值，先攻相同时敏捷值较高的先行动
+    fn dexterity_score(&self)->i32{
+        10
+    }
+    ///投先攻，即一次敏捷检定：d20加上先攻加值。advantage为额外的优劣势，会与状态带来的优劣势合并
+    fn initiative_roll(&self,rng:&mut dyn RngCore,advantage:i32)->i32{
+        let advantage=combine_advantage(&[advantage,self.conditions().ability_check_advantage(Abilities::Dexterity)]);
+        let roll=|rng:&mut dyn RngCore|rng.random_range(1..=20);
+        let natural=match advantage{
+            0=>roll(rng),
+            1=>roll(rng).max(roll(rng)),
+            _=>roll(rng).min(roll(rng)),
+        };
+        natural+self.initiative_bonus()
+    }
+    ///正在专注的效果，默认不能专注
+    fn concentration(&self)->Option<&Concentration>{
+        None
+    }
+    ///对专注时受到的每一次伤害进行专注检定，返回是否保持专注。攻击与法术造成伤害后立即调用，
+    /// 其他来源的伤害在[`clean_up_concentration`](crate::clean_up_concentration)中结算
+    fn resolve_concentration_checks(&mut self,_rng:&mut dyn RngCore)->bool{
+        true
+    }
+    ///取出已经结束、需要清理受影响者的专注效果，见[`clean_up_concentration`](crate::clean_up_concentration)
+    fn take_broken_concentration(&mut self)->Vec<Concentration>{
+        Vec::new()
+    }
+    ///移除来源为source的所有状态，返回移除的状态
+    fn end_effects_from(&mut self,_source:&str)->Vec<Condition>{
+        Vec::new()
+    }
+    ///自己的回合开始时调用，返回自动进行的死亡豁免结果
+    fn start_turn(&mut self,_rng:&mut dyn RngCore)->Option<DeathSaveOutcome>{
+        None
+    }
+    ///自己的回合结束时调用，返回结束的状态
+    fn end_turn(&mut self,_rng:&mut dyn RngCore)->Vec<Condition>{
+        Vec::new()
+    }
+}
+
+impl<C:Combatant+?Sized> Combatant for Box<C>{
+    fn name(&self)->&str{(**self).name()}
+    fn faction(&self)->&str{(**self).faction()}
+    fn initiative_bonus(&self)->i32{(**self).initiative_bonus()}
+    fn passive_perception(&self)->i32{(**self).passive_perception()}
+    fn stealth_roll(&self,rng:&mut dyn RngCore)->i32{(**self).stealth_roll(rng)}
+    fn armor_class(&self)->i32{(**self).armor_class()}
+    fn hp(&self)->i32{(**self).hp()}
+    fn max_hp(&self)->i32{(**self).max_hp()}
+    fn take_damage(&mut self,amount:i32,damage_type:DamageType,magical:bool,critical:bool)->i32{
+        (**self).take_damage(amount,damage_type,magical,critical)
+    }
+    fn conditions(&self)->&[ActiveCondition]{(**self).conditions()}
+    fn speed(&self)->i32{(**self).speed()}
+    fn is_defeated(&self)->bool{(**self).is_defeated()}
+    fn is_dead(&self)->bool{(**self).is_dead()}
+    fn can_be_surprised(&self)->bool{(**self).can_be_surprised()}
+    fn dexterity_score(&self)->i32{(**self).dexterity_score()}
+    fn initiative_roll(&self,rng:&mut dyn RngCore,advantage:i32)->i32{(**self).initiative_roll(rng,advantage)}
+    fn concentration(&self)->Option<&Concentration>{(**self).concentration()}
+    fn resolve_concentration_checks(&mut self,rng:&mut dyn RngCore)->bool{(**self).resolve_concentration_checks(rng)}
+    fn take_broken_concentration(&mut self)->Vec<Concentration>{(**self).take_broken_concentration()}
+    fn end_effects_from(&mut self,source:&str)->Vec<Condition>{(**self).end_effects_from(source)}
+    fn start_turn(&mut self,rng:&mut dyn RngCore)->Option<DeathSaveOutcome>{(**self).start_turn(rng)}
+    fn end_turn(&mut self,rng:&mut dyn RngCore)->Vec<Condition>{(**self).end_turn(rng)}
+}
+
+///把泛型的随机数生成器交给[`Combatant`]的方法。R可能本身就是没有固定大小的`dyn RngCore`，
+/// 不能直接转换为`&mut dyn RngCore`，而`&mut R`总是有固定大小的RngCore，调用处写作`&mut dyn_rng(rng)`
+pub(crate) fn dyn_rng<R:Rng+?Sized>(rng:&mut R)->impl RngCore+'_{
+    rng
+}
+
+///玩家的先攻加值为敏捷调整值、万事通与专长的加值之和；回合开始时濒死的玩家进行死亡豁免
+impl Combatant for Player{
+    fn name(&self)->&str{
+        &self.name
+    }
+    fn faction(&self)->&str{
+        &self.faction
+    }
+    ///与先攻检定加上的值相同，包括万事通
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,Combatant};
+    /// let mut player=Player::new_by_default();
+    /// assert_eq!(player.initiative_bonus(),2);
+    /// player.jack_of_all_trades=true;
+    /// assert_eq!(player.initiative_bonus(),3);
+    /// ```
+    fn initiative_bonus(&self)->i32{
+        self.ability_scores_to_modifiers().dexterity+self.proficiency_modifiers().dexterity+self.feat_initiative_bonus()
+    }
+    fn passive_perception(&self)->i32{
+        self.passive_score(Skill::Perception)
+    }
+    fn stealth_roll(&self,rng:&mut dyn RngCore)->i32{
+        self.skill_check_stat_with(rng,Skill::Stealth,1,0).unwrap_or_default()
+    }
+    fn armor_class(&self)->i32{
+        self.armor
+    }
+    fn dexterity_score(&self)->i32{
+        self.ability_scores.dexterity
+    }
+    fn is_dead(&self)->bool{
+        Player::is_dead(self)
+    }
+    ///拥有警觉专长时，清醒的玩家不会被突袭
+    fn can_be_surprised(&self)->bool{
+        !self.feats.contains(&Feat::Alert)||self.conditions.has_condition(Condition::Unconscious)
+    }
+    fn initiative_roll(&self,rng:&mut dyn RngCore,advantage:i32)->i32{
+        self.ability_check_stat_with(rng,Abilities::Dexterity,1,advantage.clamp(-1,1)).unwrap_or_default()+self.feat_initiative_bonus()
+    }
+    fn hp(&self)->i32{
+        self.hp
+    }
+    fn max_hp(&self)->i32{
+        self.hit_point_maximum()
+    }
+    fn take_damage(&mut self,amount:i32,damage_type:DamageType,magical:bool,critical:bool)->i32{
+        self.apply_damage_with(amount,damage_type,magical,critical)
+    }
+    fn conditions(&self)->&[ActiveCondition]{
+        &self.conditions
+    }
+    fn speed(&self)->i32{
+        Player::speed(self)
+    }
+    fn is_defeated(&self)->bool{
+        Player::is_defeated(self)
+    }
+    fn concentration(&self)->Option<&Concentration>{
+        self.concentration.as_ref()
+    }
+    fn resolve_concentration_checks(&mut self,rng:&mut dyn RngCore)->bool{
+        self.resolve_concentration_checks_with(rng)
+    }
+    fn take_broken_concentration(&mut self)->Vec<Concentration>{
+        self.maintain_concentration();
+        std::mem::take(&mut self.broken_concentration)
+    }
+    fn end_effects_from(&mut self,source:&str)->Vec<Condition>{
+        self.remove_conditions_from(source)
+    }
+    fn start_turn(&mut self,rng:&mut dyn RngCore)->Option<DeathSaveOutcome>{
+        if self.life_state!=LifeState::Dying {return None}
+        self.roll_death_save(rng).ok()
+    }
+    fn end_turn(&mut self,rng:&mut dyn RngCore)->Vec<Condition>{
+        self.end_of_turn_conditions(rng)
+    }
+}
+
+impl Player{
+    ///所有专长提供的先攻加值
+    fn feat_initiative_bonus(&self)->i32{
+        self.feats.iter().map(|f|f.initiative_bonus()).sum()
+    }
+}
+
+///玩家与怪物混在一起战斗
+/// ```
+/// use minidnd_eecs_havefun::{Player,Monster,ChallengeRating,Combat,Combatant,CombatEncounter,DamageType,EncounterState,FactionTable,Outcome};
+/// use std::collections::HashMap;
+/// let alice:Box<dyn Combatant>=Box::new(Player{faction:"heroes".to_string(),..Player::new_by_default()});
+/// let mut goblin=Monster::new("Goblin",ChallengeRating::Quarter,"2d6".parse().unwrap());
+/// goblin.faction="goblins".to_string();
+/// let goblin:Box<dyn Combatant>=Box::new(goblin);
+/// let combatants=HashMap::from([("Alice".to_string(),alice),("Goblin".to_string(),goblin)]);
+/// let relations=FactionTable::new();
+/// let surprise=Player::determine_surprise(&combatants,&relations,&HashMap::new()).unwrap();
+/// let initiative=Player::roll_initiative(&combatants);
+/// let mut encounter=CombatEncounter::new(combatants,relations,&surprise,&initiative.ranks()).unwrap();
+/// let state=Player::take_turns(&mut encounter,|encounter,turn|{
+///     if turn.name=="Alice" {encounter.combatant_mut("Goblin").unwrap().take_damage(7,DamageType::Slash,false,false);}
+/// });
+/// assert_eq!(state,EncounterState::Ended(Outcome::Victory(vec!["heroes".to_string()])));
+/// ```
+impl Combatant for Monster{
+    fn name(&self)->&str{
+        &self.name
+    }
+    fn faction(&self)->&str{
+        &self.faction
+    }
+    fn initiative_bonus(&self)->i32{
+        self.ability_scores_to_modifiers().dexterity
+    }
+    fn passive_perception(&self)->i32{
+        self.passive_score(Skill::Perception)
+    }
+    fn stealth_roll(&self,rng:&mut dyn RngCore)->i32{
+        self.skill_check_stat_with(rng,Skill::Stealth,1,0).unwrap_or_default()
+    }
+    fn armor_class(&self)->i32{
+        self.armor_class
+    }
+    fn dexterity_score(&self)->i32{
+        self.ability_scores.dexterity
+    }
+    fn hp(&self)->i32{
+        self.hp
+    }
+    fn max_hp(&self)->i32{
+        self.hit_point_maximum()
+    }
+    fn take_damage(&mut self,amount:i32,damage_type:DamageType,magical:bool,_critical:bool)->i32{
+        self.apply_damage_from(amount,damage_type,magical)
+    }
+    fn conditions(&self)->&[ActiveCondition]{
+        &self.conditions
+    }
+    fn speed(&self)->i32{
+        Monster::speed(self)
+    }
+    fn concentration(&self)->Option<&Concentration>{
+        self.concentration.as_ref()
+    }
+    fn resolve_concentration_checks(&mut self,rng:&mut dyn RngCore)->bool{
+        self.resolve_concentration_checks_with(rng)
+    }
+    fn take_broken_concentration(&mut self)->Vec<Concentration>{
+        self.maintain_concentration();
+        std::mem::take(&mut self.broken_concentration)
+    }
+    fn end_effects_from(&mut self,source:&str)->Vec<Condition>{
+        self.remove_conditions_from(source)
+    }
+    fn end_turn(&mut self,rng:&mut dyn RngCore)->Vec<Condition>{
+        self.end_of_turn_conditions(rng)
+    }
+}
@@ -0,0 +1,192 @@
+//!专注，来自第5版玩家手册第10章
+//!每个生物同时最多专注于一个效果，开始新的专注会结束旧的。专注的生物受到伤害时进行体质豁免，
+//!难度等级为10与伤害一半中的较大值，失败即失去专注；失能或倒下时同样失去专注。
+//!任何来源的伤害都会记录一次待进行的专注检定：攻击与法术造成伤害后立即结算，其他伤害（陷阱、坠落、DM直接结算的伤害）
+//!在[`clean_up_concentration`]中结算。
+//!依赖专注的状态以[`Concentration::source`]为来源施加，专注结束后由[`clean_up_concentration`]从所有受影响者身上移除
+use crate::{Abilities,Combatant,DNDResult,DndError,Monster,Player};
+use crate::combatant::dyn_rng;
+use crate::condition::{ConditionTracker,Conditions};
+use rand::Rng;
+use serde::{Serialize,Deserialize};
+use std::collections::HashMap;
+
+#[derive(Clone,Debug,Default,PartialEq,Eq,Serialize,Deserialize)]
+///一个需要专注的效果
+pub struct Concentration{
+    ///专注者的名字
+    pub caster:String,
+    ///效果的名字，通常是法术名
+    pub effect:String,
+    ///受到这个效果影响的生物
+    pub affected:Vec<String>,
+}
+
+impl Concentration{
+    pub fn new(caster:&str,effect:&str)->Concentration{
+        Concentration{caster:caster.to_string(),effect:effect.to_string(),affected:Vec::new()}
+    }
+    ///依赖这个效果的状态使用的来源
+    pub fn source(&self)->String{
+        format!("{}:{}",self.caster,self.effect)
+    }
+}
+
+///受到damage点伤害后保持专注的难度等级：10与伤害一半（向下取整）中的较大值。
+/// 检定函数只接受1到50的难度等级，因此超过50的难度按50计算；d20加上调整值达不到50，两者的结果相同
+/// ```
+/// use minidnd_eecs_havefun::concentration_dc;
+/// assert_eq!([1,21,22,35,200].map(concentration_dc),[10,10,11,17,50]);
+/// ```
+pub fn concentration_dc(damage:i32)->i32{
+    (damage/2).clamp(10,50)
+}
+
+///可以专注的生物，玩家与怪物都实现了这个trait
+pub trait ConcentrationTracker:ConditionTracker+Combatant{
+    ///正在专注的效果的可变引用
+    fn concentration_mut(&mut self)->&mut Option<Concentration>;
+    ///已经结束、还没有清理受影响者的效果
+    fn broken_concentration_mut(&mut self)->&mut Vec<Concentration>;
+    ///专注时受到的、还没有进行专注检定的伤害
+    fn pending_concentration_checks_mut(&mut self)->&mut Vec<i32>;
+    ///开始专注于一个效果，正在专注的效果随之结束
+    fn concentrate(&mut self,effect:&str){
+        self.end_concentration();
+        let concentration=Concentration::new(self.name(),effect);
+        *self.concentration_mut()=Some(concentration);
+    }
+    ///把target记为受影响者，返回施加状态时应当使用的来源。没有在专注时返回错误
+    fn add_concentration_target(&mut self,target:&str)->Result<String,DndError>{
+        let name=self.name().to_string();
+        let concentration=self.concentration_mut().as_mut().ok_or(DndError::NotConcentrating(name))?;
+        if !concentration.affected.iter().any(|a|a==target) {concentration.affected.push(target.to_string());}
+        Ok(concentration.source())
+    }
+    ///结束专注，返回是否确实在专注。待进行的专注检定随之作废
+    fn end_concentration(&mut self)->bool{
+        self.pending_concentration_checks_mut().clear();
+        match self.concentration_mut().take(){
+            Some(concentration)=>{
+                self.broken_concentration_mut().push(concentration);
+                true
+            },
+            None=>false,
+        }
+    }
+    ///受到damage点伤害后的体质豁免，返回是否保持专注。没有专注时不投骰，返回true
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,Combatant,ConcentrationTracker,seeded_rng};
+    /// use minidnd_eecs_havefun::condition::{Condition,ConditionDuration,ConditionTracker};
+    /// let mut cleric=Player::new_by_default();
+    /// cleric.concentrate("Bless");
+    /// let mut rng=seeded_rng(6);
+    /// //体质13，难度15
+    /// let kept=cleric.concentration_save_with(&mut rng,30);
+    /// assert_eq!(cleric.concentration().is_some(),kept);
+    /// cleric.concentrate("Bless");
+    /// cleric.add_condition(Condition::Stunned,ConditionDuration::Rounds(1),None);
+    /// assert!(!cleric.concentration_save_with(&mut rng,1));
+    /// assert_eq!(cleric.take_broken_concentration().len(),2);
+    /// //没有专注时不需要豁免
+    /// assert!(cleric.concentration_save_with(&mut rng,30));
+    /// ```
+    fn concentration_save_with<R:Rng+?Sized>(&mut self,rng:&mut R,damage:i32)->bool{
+        if self.concentration().is_none() {return true}
+        if !self.maintain_concentration() {return false}
+        let kept=matches!(self.saving_throw_with(rng,Abilities::Constitution,concentration_dc(damage),1,0),Ok(DNDResult::Win));
+        if !kept {self.end_concentration();}
+        kept
+    }
+    ///对专注时受到的每一次伤害依次进行专注检定，一旦失败就不再继续，返回是否保持专注。没有待进行的检定时返回true
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,Combatant,ConcentrationTracker,DamageType,seeded_rng};
+    /// let mut druid=Player::new_by_default();
+    /// druid.concentrate("Moonbeam");
+    /// //陷阱造成的伤害同样需要专注检定
+    /// druid.apply_damage(12,DamageType::Pierce);
+    /// druid.apply_damage(30,DamageType::Blugeon);
+    /// assert_eq!(druid.pending_concentration_checks,[12,30]);
+    /// let kept=druid.resolve_concentration_checks_with(&mut seeded_rng(3));
+    /// assert_eq!(druid.concentration().is_some(),kept);
+    /// assert!(druid.pending_concentration_checks.is_empty());
+    /// ```
+    fn resolve_concentration_checks_with<R:Rng+?Sized>(&mut self,rng:&mut R)->bool{
+        let pending=std::mem::take(self.pending_concentration_checks_mut());
+        pending.into_iter().all(|damage|self.concentration_save_with(rng,damage))
+    }
+    ///失能或倒下时结束专注，返回是否依然在专注
+    fn maintain_concentration(&mut self)->bool{
+        if self.concentration_mut().is_none() {return false}
+        if self.conditions().is_incapacitated()||self.is_defeated() {
+            self.end_concentration();
+            return false
+        }
+        true
+    }
+}
+
+impl ConcentrationTracker for Player{
+    fn concentration_mut(&mut self)->&mut Option<Concentration>{
+        &mut self.concentration
+    }
+    fn broken_concentration_mut(&mut self)->&mut Vec<Concentration>{
+        &mut self.broken_concentration
+    }
+    fn pending_concentration_checks_mut(&mut self)->&mut Vec<i32>{
+        &mut self.pending_concentration_checks
+    }
+}
+impl ConcentrationTracker for Monster{
+    fn concentration_mut(&mut self)->&mut Option<Concentration>{
+        &mut self.concentration
+    }
+    fn broken_concentration_mut(&mut self)->&mut Vec<Concentration>{
+        &mut self.broken_concentration
+    }
+    fn pending_concentration_checks_mut(&mut self)->&mut Vec<i32>{
+        &mut self.pending_concentration_checks
+    }
+}
+
+///先按名字顺序结算每个生物待进行的专注检定，再清理所有已经结束的专注效果：从受影响者身上移除以这些效果为来源的状态。
+/// 失能或倒下的专注者在这里失去专注。返回清理掉的效果，按专注者的名字排序。
+/// [`CombatEncounter`](crate::CombatEncounter)在每个回合开始前自动调用
+/// ```
+/// use minidnd_eecs_havefun::{Player,Combatant,ConcentrationTracker,DamageType,clean_up_concentration,seeded_rng};
+/// use minidnd_eecs_havefun::condition::{Condition,ConditionDuration,ConditionTracker,Conditions};
+/// use std::collections::HashMap;
+/// let mut wizard=Player::new_by_default();
+/// let mut orc=Player{name:"Orc".to_string(),..Player::new_by_default()};
+/// wizard.concentrate("Hold Person");
+/// let source=wizard.add_concentration_target("Orc").unwrap();
+/// orc.add_condition(Condition::Paralyzed,ConditionDuration::Indefinite,Some(source));
+/// orc.add_condition(Condition::Prone,ConditionDuration::Indefinite,None);
+/// let mut creatures=HashMap::from([("Alice".to_string(),wizard),("Orc".to_string(),orc)]);
+/// let mut rng=seeded_rng(1);
+/// assert!(clean_up_concentration(&mut rng,&mut creatures).is_empty());
+/// //法师倒下，失去专注
+/// creatures.get_mut("Alice").unwrap().take_damage(100,DamageType::Fire,true,false);
+/// let cleaned=clean_up_concentration(&mut rng,&mut creatures);
+/// assert_eq!(cleaned[0].effect,"Hold Person");
+/// assert!(!creatures["Orc"].conditions.has_condition(Condition::Paralyzed));
+/// assert!(creatures["Orc"].conditions.has_condition(Condition::Prone));
+/// ```
+pub fn clean_up_concentration<R:Rng+?Sized,C:Combatant>(rng:&mut R,creatures:&mut HashMap<String,C>)->Vec<Concentration>{
+    let mut names:Vec<String>=creatures.keys().cloned().collect();
+    names.sort();
+    let mut broken=Vec::new();
+    for name in &names{
+        if let Some(creature)=creatures.get_mut(name) {
+            creature.resolve_concentration_checks(&mut dyn_rng(rng));
+            broken.extend(creature.take_broken_concentration());
+        }
+    }
+    for concentration in &broken{
+        let source=concentration.source();
+        for name in &concentration.affected{
+            if let Some(creature)=creatures.get_mut(name) {creature.end_effects_from(&source);}
+        }
+    }
+    broken
+}
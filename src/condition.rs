@@ -0,0 +1,256 @@
+//!状态Conditions，来自第5版规则书附录A
+//!状态会自动影响属性检定、豁免检定以及攻击检定的优劣势：
+//![`DNDChecker`]中的检定函数会把调用者给出的advantage与状态带来的优劣势合并，
+//![`attack_with`](crate::attack_with)同时考虑攻击者与目标的状态。按照规则，只要同时存在优势与劣势来源，二者就互相抵消
+//!4级及以上力竭使生命值上限减半，石化使生物对所有伤害具有抗性
+use crate::{Abilities,DNDChecker,DNDResult,Monster,Player};
+use rand::Rng;
+use serde::{Serialize,Deserialize};
+
+#[derive(Copy,Clone,Debug,PartialEq,Eq,Hash,Serialize,Deserialize)]
+///14种状态以及力竭等级
+pub enum Condition{
+    Blinded,//目盲
+    Charmed,//魅惑
+    Deafened,//耳聋
+    Frightened,//恐慌
+    Grappled,//受擒
+    Incapacitated,//失能
+    Invisible,//隐形
+    Paralyzed,//麻痹
+    Petrified,//石化
+    Poisoned,//中毒
+    Prone,//倒地
+    Restrained,//束缚
+    Stunned,//震慑
+    Unconscious,//昏迷
+    ///力竭，等级为1到6，效果逐级累加
+    Exhaustion(i32),
+}
+
+#[derive(Copy,Clone,Debug,PartialEq,Eq,Serialize,Deserialize)]
+///状态的持续时间
+pub enum ConditionDuration{
+    ///持续若干轮，在受影响者每个回合结束时减1
+    Rounds(i32),
+    ///受影响者在每个回合结束时进行一次豁免，成功即结束
+    UntilSave{ability:Abilities,dc:i32},
+    ///直到被移除
+    Indefinite,
+}
+
+#[derive(Clone,Debug,PartialEq,Eq,Serialize,Deserialize)]
+///生物身上的一个状态
+pub struct ActiveCondition{
+    pub condition:Condition,
+    pub duration:ConditionDuration,
+    ///造成这个状态的来源，例如施法者的名字，便于在来源失效时移除
+    #[serde(default)]
+    pub source:Option<String>,
+}
+
+impl Condition{
+    ///该状态是否包含另一个状态，例如昏迷包含失能与倒地。力竭按等级比较
+    pub fn includes(&self,other:Condition)->bool{
+        match (*self,other){
+            (Condition::Exhaustion(a),Condition::Exhaustion(b))=>a>=b,
+            (a,b) if a==b=>true,
+            (Condition::Paralyzed|Condition::Petrified|Condition::Stunned,Condition::Incapacitated)=>true,
+            (Condition::Unconscious,Condition::Incapacitated|Condition::Prone)=>true,
+            _=>false,
+        }
+    }
+}
+
+///合并多个优劣势来源：只要同时存在优势与劣势就互相抵消，否则有优势为1，有劣势为-1
+/// ```
+/// use minidnd_eecs_havefun::condition::combine_advantage;
+/// assert_eq!(combine_advantage(&[1,1,0]),1);
+/// assert_eq!(combine_advantage(&[1,-1,-1]),0);
+/// assert_eq!(combine_advantage(&[0,-1]),-1);
+/// ```
+pub fn combine_advantage(sources:&[i32])->i32{
+    let advantage=sources.iter().any(|&a|a>0);
+    let disadvantage=sources.iter().any(|&a|a<0);
+    match (advantage,disadvantage){
+        (true,false)=>1,
+        (false,true)=>-1,
+        _=>0,
+    }
+}
+
+///状态列表上的查询
+pub trait Conditions{
+    fn condition_list(&self)->&[ActiveCondition];
+    ///是否处于某个状态（包括被其他状态包含的情况）。力竭等级不低于给定等级即为真
+    fn has_condition(&self,condition:Condition)->bool{
+        self.condition_list().iter().any(|c|c.condition.includes(condition))
+    }
+    ///当前力竭等级，没有力竭时为0
+    fn exhaustion_level(&self)->i32{
+        self.condition_list().iter().filter_map(|c|match c.condition{
+            Condition::Exhaustion(level)=>Some(level),
+            _=>None,
+        }).max().unwrap_or(0)
+    }
+    ///是否失能，失能者不能执行动作与反应
+    fn is_incapacitated(&self)->bool{
+        self.has_condition(Condition::Incapacitated)
+    }
+    ///状态对属性检定（包括技能检定）的优劣势
+    fn ability_check_advantage(&self,_ability:Abilities)->i32{
+        let disadvantage=self.has_condition(Condition::Poisoned)||self.has_condition(Condition::Frightened)||self.exhaustion_level()>=1;
+        if disadvantage {-1} else {0}
+    }
+    ///状态对豁免检定的优劣势
+    fn saving_throw_advantage(&self,ability:Abilities)->i32{
+        let disadvantage=self.exhaustion_level()>=3
+        ||(ability==Abilities::Dexterity&&self.has_condition(Condition::Restrained));
+        if disadvantage {-1} else {0}
+    }
+    ///麻痹、石化、震慑、昏迷时力量与敏捷豁免自动失败
+    fn auto_fails_saving_throw(&self,ability:Abilities)->bool{
+        matches!(ability,Abilities::Strength|Abilities::Dexterity)
+        &&[Condition::Paralyzed,Condition::Petrified,Condition::Stunned,Condition::Unconscious].iter().any(|c|self.has_condition(*c))
+    }
+    ///作为攻击者时，自身状态对攻击检定的优劣势
+    fn attacking_advantage(&self)->i32{
+        let disadvantage=[Condition::Blinded,Condition::Frightened,Condition::Poisoned,Condition::Prone,Condition::Restrained]
+        .iter().any(|c|self.has_condition(*c))||self.exhaustion_level()>=3;
+        combine_advantage(&[if self.has_condition(Condition::Invisible) {1} else {0},if disadvantage {-1} else {0}])
+    }
+    ///作为目标时，自身状态给攻击者带来的优劣势。melee表示攻击者是否在5尺内进行近战攻击
+    fn attacked_advantage(&self,melee:bool)->i32{
+        let advantage=[Condition::Blinded,Condition::Paralyzed,Condition::Petrified,Condition::Restrained,Condition::Stunned,Condition::Unconscious]
+        .iter().any(|c|self.has_condition(*c));
+        let prone=if self.has_condition(Condition::Prone) {if melee {1} else {-1}} else {0};
+        let invisible=if self.has_condition(Condition::Invisible) {-1} else {0};
+        combine_advantage(&[if advantage {1} else {0},prone,invisible])
+    }
+    ///受擒、束缚、5级力竭时速度为0，2级力竭时减半
+    fn adjusted_speed(&self,speed:i32)->i32{
+        let exhaustion=self.exhaustion_level();
+        if self.has_condition(Condition::Grappled)||self.has_condition(Condition::Restrained)||exhaustion>=5 {0}
+        else if exhaustion>=2 {speed/2}
+        else {speed}
+    }
+    ///4级及以上力竭时生命值上限减半（向下取整）
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,Combatant};
+    /// use minidnd_eecs_havefun::condition::{Condition,ConditionDuration,ConditionTracker};
+    /// let mut player=Player::new_by_default();
+    /// player.add_condition(Condition::Exhaustion(4),ConditionDuration::Indefinite,None);
+    /// assert_eq!((player.hp,Combatant::max_hp(&player)),(50,50));
+    /// assert_eq!(player.heal(30),0);
+    /// player.add_condition(Condition::Exhaustion(-1),ConditionDuration::Indefinite,None);
+    /// assert_eq!(player.heal(30),30);
+    /// ```
+    fn adjusted_max_hp(&self,max_hp:i32)->i32{
+        if self.exhaustion_level()>=4 {max_hp/2} else {max_hp}
+    }
+    ///石化时对所有伤害具有抗性
+    fn resists_all_damage(&self)->bool{
+        self.has_condition(Condition::Petrified)
+    }
+    ///麻痹或昏迷的目标被5尺内的近战攻击命中时必定是重击
+    fn melee_hits_are_critical(&self)->bool{
+        self.has_condition(Condition::Paralyzed)||self.has_condition(Condition::Unconscious)
+    }
+}
+
+impl Conditions for [ActiveCondition]{
+    fn condition_list(&self)->&[ActiveCondition]{
+        self
+    }
+}
+
+///可以被施加状态的生物，玩家与怪物都实现了这个trait
+pub trait ConditionTracker:DNDChecker{
+    ///状态列表的可变引用
+    fn conditions_mut(&mut self)->&mut Vec<ActiveCondition>;
+    ///状态改变后调用，默认什么也不做。玩家与怪物在这里把生命值限制在力竭调整后的上限以内
+    fn conditions_changed(&mut self){}
+    ///添加一个状态。力竭会与已有的力竭合并并提升等级（最高6级），其余状态若已存在则替换为新的持续时间
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,Abilities,DNDChecker,DNDResult,seeded_rng};
+    /// use minidnd_eecs_havefun::condition::{Condition,ConditionDuration,ConditionTracker,Conditions};
+    /// let mut player=Player::new_by_default();
+    /// player.add_condition(Condition::Paralyzed,ConditionDuration::UntilSave{ability:Abilities::Constitution,dc:15},None);
+    /// assert!(player.conditions.is_incapacitated());
+    /// //麻痹时敏捷豁免自动失败
+    /// assert_eq!(player.saving_throw_with(&mut seeded_rng(1),Abilities::Dexterity,2,1,0).unwrap(),DNDResult::Lose);
+    /// player.add_condition(Condition::Exhaustion(1),ConditionDuration::Indefinite,None);
+    /// player.add_condition(Condition::Exhaustion(2),ConditionDuration::Indefinite,None);
+    /// assert_eq!(player.conditions.exhaustion_level(),3);
+    /// ```
+    fn add_condition(&mut self,condition:Condition,duration:ConditionDuration,source:Option<String>){
+        let conditions=self.conditions_mut();
+        if let Condition::Exhaustion(level)=condition{
+            let level=(conditions.exhaustion_level()+level).clamp(0,6);
+            conditions.retain(|c|!matches!(c.condition,Condition::Exhaustion(_)));
+            if level>0 {
+                conditions.push(ActiveCondition{condition:Condition::Exhaustion(level),duration,source});
+            }
+        }
+        else{
+            conditions.retain(|c|c.condition!=condition);
+            conditions.push(ActiveCondition{condition,duration,source});
+        }
+        self.conditions_changed();
+    }
+    ///移除某个状态，返回是否确实移除了。移除力竭会清除全部力竭等级
+    fn remove_condition(&mut self,condition:Condition)->bool{
+        let conditions=self.conditions_mut();
+        let before=conditions.len();
+        conditions.retain(|c|match (c.condition,condition){
+            (Condition::Exhaustion(_),Condition::Exhaustion(_))=>false,
+            (a,b)=>a!=b,
+        });
+        before!=conditions.len()
+    }
+    ///移除来源为source的所有状态
+    fn remove_conditions_from(&mut self,source:&str)->Vec<Condition>{
+        let conditions=self.conditions_mut();
+        let (removed,kept):(Vec<ActiveCondition>,Vec<ActiveCondition>)=std::mem::take(conditions)
+        .into_iter().partition(|c|c.source.as_deref()==Some(source));
+        *conditions=kept;
+        removed.into_iter().map(|c|c.condition).collect()
+    }
+    ///在受影响者的回合结束时调用：按轮计时的状态减1轮，需要豁免的状态各豁免一次。返回结束的状态
+    fn end_of_turn_conditions<R:Rng+?Sized>(&mut self,rng:&mut R)->Vec<Condition>{
+        let mut ended=Vec::new();
+        let mut i=0;
+        while i<self.conditions_mut().len(){
+            //豁免时依然受到其余状态（包括这个状态本身）的影响
+            let over=match self.conditions_mut()[i].duration{
+                ConditionDuration::Rounds(rounds)=>{
+                    self.conditions_mut()[i].duration=ConditionDuration::Rounds(rounds-1);
+                    rounds<=1
+                },
+                ConditionDuration::UntilSave{ability,dc}=>
+                matches!(self.saving_throw_with(rng,ability,dc.clamp(1,50),1,0),Ok(DNDResult::Win)),
+                ConditionDuration::Indefinite=>false,
+            };
+            if over {ended.push(self.conditions_mut().remove(i).condition);}
+            else {i+=1;}
+        }
+        ended
+    }
+}
+
+impl ConditionTracker for Player{
+    fn conditions_mut(&mut self)->&mut Vec<ActiveCondition>{
+        &mut self.conditions
+    }
+    fn conditions_changed(&mut self){
+        if self.max_hp>0 {self.hp=self.hp.min(self.hit_point_maximum());}
+    }
+}
+impl ConditionTracker for Monster{
+    fn conditions_mut(&mut self)->&mut Vec<ActiveCondition>{
+        &mut self.conditions
+    }
+    fn conditions_changed(&mut self){
+        self.hp=self.hp.min(self.hit_point_maximum());
+    }
+}
@@ -0,0 +1,114 @@
+//!伤害抗性、易伤与免疫
+//!规则书：免疫时伤害为0；抗性使伤害减半（向下取整）；易伤使伤害加倍。同一种抗性或易伤多次出现也只计算一次，
+//!抗性与易伤同时存在时先减半再加倍
+use crate::{ActiveCondition,DamageType,Player};
+use crate::condition::Conditions;
+use serde::{Serialize,Deserialize};
+use std::collections::HashSet;
+
+#[derive(Clone,Debug,Default,PartialEq,Eq,Serialize,Deserialize)]
+///一个生物对各种伤害类型的防御
+pub struct DamageDefenses{
+    #[serde(default)]
+    pub resistances:HashSet<DamageType>,
+    #[serde(default)]
+    pub vulnerabilities:HashSet<DamageType>,
+    #[serde(default)]
+    pub immunities:HashSet<DamageType>,
+    ///只对非魔法攻击造成的钝击、穿刺、劈砍伤害生效的抗性，很多怪物只有这种抗性
+    #[serde(default)]
+    pub nonmagical_resistances:HashSet<DamageType>,
+    ///只对非魔法攻击造成的钝击、穿刺、劈砍伤害生效的免疫
+    #[serde(default)]
+    pub nonmagical_immunities:HashSet<DamageType>,
+}
+
+impl DamageDefenses{
+    ///经过抗性、易伤与免疫调整后的伤害。magical表示伤害是否来自魔法攻击
+    /// ```
+    /// use minidnd_eecs_havefun::{DamageDefenses,DamageType};
+    /// let mut werewolf=DamageDefenses::default();
+    /// werewolf.nonmagical_immunities.insert(DamageType::Slash);
+    /// werewolf.vulnerabilities.insert(DamageType::Radiant);
+    /// werewolf.resistances.insert(DamageType::Cold);
+    /// assert_eq!(werewolf.adjust(9,DamageType::Slash,false),0);
+    /// assert_eq!(werewolf.adjust(9,DamageType::Slash,true),9);
+    /// assert_eq!(werewolf.adjust(9,DamageType::Radiant,false),18);
+    /// assert_eq!(werewolf.adjust(9,DamageType::Cold,false),4);
+    /// ```
+    pub fn adjust(&self,amount:i32,damage_type:DamageType,magical:bool)->i32{
+        self.adjust_with(amount,damage_type,magical,false)
+    }
+    ///与[`DamageDefenses::adjust`]相同，同时考虑状态：石化时对所有伤害具有抗性，与已有的抗性不叠加
+    /// ```
+    /// use minidnd_eecs_havefun::{DamageDefenses,DamageType,ActiveCondition,Condition,ConditionDuration};
+    /// let mut statue=DamageDefenses::default();
+    /// statue.resistances.insert(DamageType::Fire);
+    /// let petrified=[ActiveCondition{condition:Condition::Petrified,duration:ConditionDuration::Indefinite,source:None}];
+    /// assert_eq!(statue.adjust_under(9,DamageType::Force,true,&petrified),4);
+    /// assert_eq!(statue.adjust_under(9,DamageType::Fire,true,&petrified),4);
+    /// assert_eq!(statue.adjust_under(9,DamageType::Force,true,&[]),9);
+    /// ```
+    pub fn adjust_under(&self,amount:i32,damage_type:DamageType,magical:bool,conditions:&[ActiveCondition])->i32{
+        self.adjust_with(amount,damage_type,magical,conditions.resists_all_damage())
+    }
+    fn adjust_with(&self,amount:i32,damage_type:DamageType,magical:bool,resists_all:bool)->i32{
+        let amount=amount.max(0);
+        let nonmagical_physical=!magical&&damage_type.is_physical();
+        if self.immunities.contains(&damage_type)
+        ||(nonmagical_physical&&self.nonmagical_immunities.contains(&damage_type)){
+            return 0;
+        }
+        let mut adjusted=amount;
+        if resists_all||self.resistances.contains(&damage_type)
+        ||(nonmagical_physical&&self.nonmagical_resistances.contains(&damage_type)){
+            adjusted/=2;
+        }
+        if self.vulnerabilities.contains(&damage_type){
+            adjusted*=2;
+        }
+        adjusted
+    }
+}
+
+impl DamageType{
+    ///全部13种伤害类型
+    pub const ALL:[DamageType;13]=[
+        DamageType::Blugeon,DamageType::Slash,DamageType::Pierce,
+        DamageType::Acid,DamageType::Cold,DamageType::Fire,DamageType::Force,DamageType::Lightning,
+        DamageType::Necrotic,DamageType::Poison,DamageType::Psychic,DamageType::Radiant,DamageType::Thunder,
+    ];
+    ///钝击、劈砍、穿刺属于物理伤害
+    pub fn is_physical(&self)->bool{
+        matches!(self,DamageType::Blugeon|DamageType::Slash|DamageType::Pierce)
+    }
+}
+
+impl Player{
+    ///受到非魔法来源的伤害，返回实际扣除的生命值，见[`Player::apply_damage_from`]
+    pub fn apply_damage(&mut self,amount:i32,damage_type:DamageType)->i32{
+        self.apply_damage_from(amount,damage_type,false)
+    }
+    ///受到伤害，先按照抗性、易伤与免疫调整，再从生命值中扣除，生命值最低为0。返回调整后的伤害。
+    /// 生命值降到0时进入濒死状态，见[`death`](crate::death)
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,DamageType,LifeState};
+    /// let mut player=Player::new_by_default();
+    /// player.damage_defenses.resistances.insert(DamageType::Fire);
+    /// assert_eq!(player.apply_damage(11,DamageType::Fire),5);
+    /// assert_eq!(player.hp,95);
+    /// //剩余的伤害不低于生命值上限100，直接死亡
+    /// player.apply_damage(195,DamageType::Slash);
+    /// assert_eq!((player.hp,player.life_state),(0,LifeState::Dead));
+    /// ```
+    pub fn apply_damage_from(&mut self,amount:i32,damage_type:DamageType,magical:bool)->i32{
+        self.apply_damage_with(amount,damage_type,magical,false)
+    }
+    ///与[`Player::apply_damage_from`]相同，critical表示伤害是否来自重击：濒死时受到重击算作两次死亡豁免失败。
+    /// 石化时对所有伤害具有抗性
+    pub fn apply_damage_with(&mut self,amount:i32,damage_type:DamageType,magical:bool,critical:bool)->i32{
+        let damage=self.damage_defenses.adjust_under(amount,damage_type,magical,&self.conditions);
+        self.take_adjusted_damage(damage,critical);
+        damage
+    }
+}
@@ -0,0 +1,154 @@
+//!濒死与死亡豁免
+//!生命值降到0时角色倒地昏迷并进入濒死状态，此后每个回合开始时进行一次死亡豁免：投d20，10以上为成功。
+//!累计3次成功即稳定，累计3次失败即死亡。投出20立即恢复1点生命值，投出1算作两次失败。
+//!濒死时受到任何伤害算作一次失败，重击算作两次；一次伤害在把生命值降到0之后的剩余部分不低于生命值上限时直接死亡
+use crate::{DNDChecker,DndError,Player};
+use crate::condition::{Condition,ConditionDuration,ConditionTracker,Conditions};
+use rand::Rng;
+use serde::{Serialize,Deserialize};
+
+///倒地与昏迷状态的来源，恢复意识时只移除这个来源的状态
+pub const DYING_SOURCE:&str="dying";
+
+#[derive(Copy,Clone,Debug,Default,PartialEq,Eq,Hash,Serialize,Deserialize)]
+///生命状态
+pub enum LifeState{
+    #[default] Conscious,//清醒
+    Dying,//濒死，需要进行死亡豁免
+    Stable,//稳定，昏迷但不再进行死亡豁免
+    Dead,//死亡
+}
+
+#[derive(Copy,Clone,Debug,Default,PartialEq,Eq,Serialize,Deserialize)]
+///累计的死亡豁免次数，恢复意识或稳定时清零
+pub struct DeathSaves{
+    pub successes:i32,
+    pub failures:i32,
+}
+
+#[derive(Copy,Clone,Debug,PartialEq,Eq,Serialize,Deserialize)]
+///一次死亡豁免的结果
+pub enum DeathSaveOutcome{
+    Success,
+    Failure,
+    ///第3次成功，角色稳定
+    Stabilized,
+    ///投出20，恢复1点生命值并恢复意识
+    Revived,
+    ///第3次失败，角色死亡
+    Died,
+}
+
+impl Player{
+    ///是否已经死亡
+    pub fn is_dead(&self)->bool{
+        self.life_state==LifeState::Dead
+    }
+    ///是否处于濒死或稳定状态，即生命值为0但还活着
+    pub fn is_down(&self)->bool{
+        matches!(self.life_state,LifeState::Dying|LifeState::Stable)
+    }
+    ///进行一次死亡豁免，只有濒死的角色可以进行
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,DamageType,LifeState,DeathSaveOutcome,seeded_rng};
+    /// let mut player=Player::new_by_default();
+    /// player.apply_damage(100,DamageType::Slash);
+    /// assert_eq!((player.hp,player.life_state),(0,LifeState::Dying));
+    /// let mut rng=seeded_rng(8);
+    /// let outcome=loop{
+    ///     let outcome=player.roll_death_save(&mut rng).unwrap();
+    ///     if !matches!(outcome,DeathSaveOutcome::Success|DeathSaveOutcome::Failure) {break outcome}
+    /// };
+    /// match outcome{
+    ///     DeathSaveOutcome::Stabilized=>assert_eq!(player.life_state,LifeState::Stable),
+    ///     DeathSaveOutcome::Revived=>assert_eq!((player.hp,player.life_state),(1,LifeState::Conscious)),
+    ///     _=>assert!(player.is_dead()),
+    /// }
+    /// assert!(player.roll_death_save(&mut rng).is_err());
+    /// ```
+    pub fn roll_death_save<R:Rng+?Sized>(&mut self,rng:&mut R)->Result<DeathSaveOutcome,DndError>{
+        if self.life_state!=LifeState::Dying {return Err(DndError::NotDying(self.name.clone()))}
+        let natural=Player::dice_complex(rng,20,1,0);
+        let outcome=match natural{
+            20=>{
+                self.heal(1);
+                DeathSaveOutcome::Revived
+            },
+            1=>self.add_death_save_failures(2),
+            n if n>=10=>{
+                self.death_saves.successes+=1;
+                if self.death_saves.successes>=3 {
+                    self.stabilize();
+                    DeathSaveOutcome::Stabilized
+                }
+                else {DeathSaveOutcome::Success}
+            },
+            _=>self.add_death_save_failures(1),
+        };
+        Ok(outcome)
+    }
+    fn add_death_save_failures(&mut self,failures:i32)->DeathSaveOutcome{
+        self.death_saves.failures+=failures;
+        if self.death_saves.failures>=3 {
+            self.die();
+            DeathSaveOutcome::Died
+        }
+        else {DeathSaveOutcome::Failure}
+    }
+    ///使濒死的角色稳定，例如通过DC10的医疗检定。对其他状态的角色没有效果
+    pub fn stabilize(&mut self){
+        if self.life_state==LifeState::Dying {
+            self.life_state=LifeState::Stable;
+            self.death_saves=DeathSaves::default();
+        }
+    }
+    ///当前的生命值上限，4级及以上力竭时减半
+    pub fn hit_point_maximum(&self)->i32{
+        self.conditions.adjusted_max_hp(self.max_hp)
+    }
+    ///恢复生命值，不超过生命值上限（上限为0时视为没有上限）。濒死或稳定的角色恢复意识，死亡的角色不受影响。
+    /// 返回实际恢复的生命值
+    pub fn heal(&mut self,amount:i32)->i32{
+        if self.is_dead()||amount<=0 {return 0}
+        let before=self.hp.max(0);
+        let healed=before+amount;
+        self.hp=if self.max_hp>0 {healed.min(self.hit_point_maximum())} else {healed};
+        if self.hp>0&&self.is_down() {
+            self.life_state=LifeState::Conscious;
+            self.death_saves=DeathSaves::default();
+            self.remove_conditions_from(DYING_SOURCE);
+        }
+        self.hp-before
+    }
+    fn die(&mut self){
+        self.hp=0;
+        self.life_state=LifeState::Dead;
+        self.remove_conditions_from(DYING_SOURCE);
+    }
+    ///在调整过抗性等之后结算伤害：先扣除临时生命值，再扣除生命值并处理倒地、濒死时受伤以及巨额伤害致死。critical表示伤害是否来自重击。
+    /// 专注时记录一次待进行的专注检定，临时生命值吸收的伤害同样计入
+    pub(crate) fn take_adjusted_damage(&mut self,damage:i32,critical:bool){
+        if damage<=0||self.is_dead() {return}
+        if self.concentration.is_some() {self.pending_concentration_checks.push(damage);}
+        let absorbed=damage.min(self.temp_hp.max(0));
+        self.temp_hp-=absorbed;
+        let damage=damage-absorbed;
+        if damage==0 {return}
+        let massive=|remainder:i32,max_hp:i32|max_hp>0&&remainder>=max_hp;
+        if self.is_down() {
+            if massive(damage,self.hit_point_maximum()) {self.die();return}
+            self.life_state=LifeState::Dying;
+            self.add_death_save_failures(if critical {2} else {1});
+            return;
+        }
+        let remainder=damage-self.hp.max(0);
+        self.hp=(self.hp-damage).max(0);
+        if self.hp>0 {return}
+        if massive(remainder,self.hit_point_maximum()) {self.die();return}
+        self.life_state=LifeState::Dying;
+        self.death_saves=DeathSaves::default();
+        self.add_condition(Condition::Unconscious,ConditionDuration::Indefinite,Some(DYING_SOURCE.to_string()));
+        //恢复意识后依然倒地，因此倒地不记录来源
+        self.add_condition(Condition::Prone,ConditionDuration::Indefinite,None);
+    }
+}
@@ -0,0 +1,445 @@
+//!骰子表达式，例如"2d6+1d4+3"、"4d6kh3"、"1d20adv"、"2d6ro<2"
+//!语法如下（不区分大小写，忽略空白）：
+//! - 表达式由若干项用`+`或`-`连接，每项是一个常数或者一组骰子`XdY`，X省略时为1
+//! - `khN`/`klN`保留最高/最低的N个骰子，`kN`等价于`khN`；`dhN`/`dlN`丢弃最高/最低的N个骰子
+//! - `adv`/`dis`表示整组骰子投两次，取总和较高/较低的一次
+//! - `rN`把等于N的骰子一直重投，`r<N`把不超过N的骰子一直重投，`ro`只重投一次
+use crate::DndError;
+use rand::Rng;
+use serde::{Serialize,Deserialize,Serializer,Deserializer};
+use std::fmt;
+use std::str::FromStr;
+
+///一组骰子最多的数量以及面数上界，与`DNDChecker::dice`保持一致
+pub const MAX_DICE_COUNT:i32=100;
+pub const MAX_DICE_SIDES:i32=100;
+
+#[derive(Clone,Debug,PartialEq,Eq)]
+///解析后的骰子表达式，即带符号的项之和
+pub struct DiceExpr{
+    pub terms:Vec<Term>,
+}
+#[derive(Clone,Debug,PartialEq,Eq)]
+///表达式中的一项，sign为1或-1
+pub struct Term{
+    pub sign:i32,
+    pub kind:TermKind,
+}
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub enum TermKind{
+    Constant(i32),
+    Dice(DiceTerm),
+}
+#[derive(Clone,Debug,PartialEq,Eq)]
+///一组骰子XdY以及它的各种修饰。只能通过[`DiceTerm::new`]或解析得到，保证数量、面数与修饰都合法
+pub struct DiceTerm{
+    pub(crate) count:i32,
+    pub(crate) sides:i32,
+    pub(crate) keep:Option<Keep>,
+    ///-1代表劣势，0代表没有优势或劣势，1代表优势，与`dice_complex`相同
+    pub(crate) advantage:i32,
+    pub(crate) reroll:Option<Reroll>,
+}
+#[derive(Copy,Clone,Debug,PartialEq,Eq)]
+///保留或丢弃的规则
+pub enum Keep{
+    Highest(i32),
+    Lowest(i32),
+    DropHighest(i32),
+    DropLowest(i32),
+}
+#[derive(Copy,Clone,Debug,PartialEq,Eq)]
+///重投规则。at_most为true时重投不超过value的骰子，否则只重投等于value的骰子
+pub struct Reroll{
+    pub value:i32,
+    pub at_most:bool,
+    pub once:bool,
+}
+#[derive(Copy,Clone,Debug,PartialEq,Eq,Serialize,Deserialize)]
+///单个骰子的结果。kept为false说明这个骰子被丢弃、被重投或者属于优劣势中落选的那一次
+pub struct DieRoll{
+    pub sides:i32,
+    pub value:i32,
+    pub kept:bool,
+}
+#[derive(Clone,Debug,Default,PartialEq,Eq,Serialize,Deserialize)]
+///一次投掷的结果，包括总值以及所有投出的骰子
+pub struct RollResult{
+    pub total:i32,
+    pub dice:Vec<DieRoll>,
+}
+
+impl Keep{
+    ///在n个骰子中实际保留的个数
+    pub fn kept_count(&self,n:i32)->i32{
+        match *self{
+            Keep::Highest(k)|Keep::Lowest(k)=>k.min(n),
+            Keep::DropHighest(k)|Keep::DropLowest(k)=>(n-k).max(0),
+        }
+    }
+    ///保留的骰子是否为较高的那些
+    pub fn keeps_highest(&self)->bool{
+        matches!(self,Keep::Highest(_)|Keep::DropLowest(_))
+    }
+}
+impl Reroll{
+    ///该面值是否需要重投
+    pub fn matches(&self,value:i32)->bool{
+        if self.at_most {value<=self.value} else {value==self.value}
+    }
+}
+
+impl DiceTerm{
+    ///检查后构造一组骰子，合法范围与解析时相同。advantage只取符号
+    /// ```
+    /// use minidnd_eecs_havefun::dice::{DiceTerm,Keep};
+    /// let stats=DiceTerm::new(4,6,Some(Keep::Highest(3)),0,None).unwrap();
+    /// assert_eq!((stats.count(),stats.sides(),stats.to_string()),(4,6,"4d6kh3".to_string()));
+    /// assert!(DiceTerm::new(-1,6,None,0,None).is_err());
+    /// assert!(DiceTerm::new(1,0,None,0,None).is_err());
+    /// assert!(DiceTerm::new(2,6,Some(Keep::DropLowest(2)),0,None).is_err());
+    /// ```
+    pub fn new(count:i32,sides:i32,keep:Option<Keep>,advantage:i32,reroll:Option<Reroll>)->Result<DiceTerm,DndError>{
+        let term=DiceTerm{count,sides,keep,advantage:advantage.signum(),reroll};
+        term.validate().map_err(|reason|DndError::InvalidDiceNotation{notation:term.to_string(),reason})?;
+        Ok(term)
+    }
+    ///没有任何修饰的XdY，尚未检查
+    fn plain(count:i32,sides:i32)->DiceTerm{
+        DiceTerm{count,sides,keep:None,advantage:0,reroll:None}
+    }
+    pub fn count(&self)->i32{
+        self.count
+    }
+    pub fn sides(&self)->i32{
+        self.sides
+    }
+    pub fn keep(&self)->Option<Keep>{
+        self.keep
+    }
+    pub fn advantage(&self)->i32{
+        self.advantage
+    }
+    pub fn reroll(&self)->Option<Reroll>{
+        self.reroll
+    }
+    fn roll_die<R:Rng+?Sized>(&self,rng:&mut R,dice:&mut Vec<DieRoll>)->i32{
+        let mut value=rng.random_range(1..=self.sides);
+        if let Some(reroll)=self.reroll{
+            while reroll.matches(value){
+                dice.push(DieRoll{sides:self.sides,value,kept:false});
+                value=rng.random_range(1..=self.sides);
+                if reroll.once {break}
+            }
+        }
+        value
+    }
+    ///投一次整组骰子，返回保留骰子之和
+    fn roll_once<R:Rng+?Sized>(&self,rng:&mut R,dice:&mut Vec<DieRoll>)->i32{
+        let mut values:Vec<(usize,i32)>=Vec::with_capacity(self.count as usize);
+        for _ in 0..self.count{
+            let value=self.roll_die(rng,dice);
+            dice.push(DieRoll{sides:self.sides,value,kept:true});
+            values.push((dice.len()-1,value));
+        }
+        if let Some(keep)=self.keep{
+            //按面值排序，保留需要的那一端
+            values.sort_by_key(|&(_,v)|v);
+            if keep.keeps_highest(){values.reverse();}
+            let kept=keep.kept_count(self.count) as usize;
+            for &(index,_) in &values[kept..]{
+                dice[index].kept=false;
+            }
+            values.truncate(kept);
+        }
+        values.iter().map(|&(_,v)|v).sum()
+    }
+    ///考虑优劣势的投掷。落选那一次的骰子全部标记为未保留
+    pub fn roll<R:Rng+?Sized>(&self,rng:&mut R,dice:&mut Vec<DieRoll>)->i32{
+        let start=dice.len();
+        let first=self.roll_once(rng,dice);
+        if self.advantage==0 {return first}
+        let middle=dice.len();
+        let second=self.roll_once(rng,dice);
+        let first_wins=if self.advantage>0 {first>=second} else {first<=second};
+        let dropped=if first_wins {middle..dice.len()} else {start..middle};
+        for die in &mut dice[dropped]{
+            die.kept=false;
+        }
+        if first_wins {first} else {second}
+    }
+    fn validate(&self)->Result<(),&'static str>{
+        if !(1..=MAX_DICE_COUNT).contains(&self.count) {return Err("dice count is not in the range of 1 to 100")}
+        if !(2..=MAX_DICE_SIDES).contains(&self.sides) {return Err("dice sides are not in the range of 2 to 100")}
+        if let Some(keep)=self.keep{
+            let k=match keep {Keep::Highest(k)|Keep::Lowest(k)|Keep::DropHighest(k)|Keep::DropLowest(k)=>k};
+            if k<0||k>self.count {return Err("keep/drop count is larger than the number of dice")}
+            if keep.kept_count(self.count)==0 {return Err("keep/drop leaves no dice")}
+        }
+        if let Some(reroll)=self.reroll{
+            if !reroll.once&&(1..=self.sides).all(|v|reroll.matches(v)) {return Err("reroll would never stop")}
+        }
+        Ok(())
+    }
+}
+
+impl DiceExpr{
+    ///XdY，对应旧版武器伤害中的(x,y)。骰子数量与面数的合法范围与解析时相同
+    /// ```
+    /// use minidnd_eecs_havefun::DiceExpr;
+    /// assert_eq!(DiceExpr::from_dice(2,6).unwrap(),"2d6".parse().unwrap());
+    /// assert!(DiceExpr::from_dice(1,0).is_err());
+    /// assert!(DiceExpr::from_dice(-1,6).is_err());
+    /// ```
+    pub fn from_dice(count:i32,sides:i32)->Result<DiceExpr,DndError>{
+        let term=DiceTerm::new(count,sides,None,0,None)?;
+        Ok(DiceExpr{terms:vec![Term{sign:1,kind:TermKind::Dice(term)}]})
+    }
+    ///解析骰子表达式
+    /// ```
+    /// use minidnd_eecs_havefun::{DiceExpr,DndError};
+    /// let expr=DiceExpr::parse("2d6 + 1d4 + 3").unwrap();
+    /// assert_eq!(expr.to_string(),"2d6+1d4+3");
+    /// assert_eq!(expr.bounds(),(6,19));
+    /// assert_eq!(DiceExpr::parse("1d20ADV").unwrap().to_string(),"1d20adv");
+    /// assert_eq!(DiceExpr::parse("2d6ro<2").unwrap().to_string(),"2d6ro<2");
+    /// assert!(DiceExpr::parse("4d6kh5").is_err());
+    /// assert!(matches!(DiceExpr::parse("1d6r<6"),Err(DndError::InvalidDiceNotation{reason:"reroll would never stop",..})));
+    /// assert!(DiceExpr::parse("2d").is_err());
+    /// //旧存档中的(x,y)依然可以读取
+    /// let old:DiceExpr=serde_json::from_str("[1,8]").unwrap();
+    /// assert_eq!(old.to_string(),"1d8");
+    /// assert!(serde_json::from_str::<DiceExpr>("[1,0]").is_err());
+    /// ```
+    pub fn parse(notation:&str)->Result<DiceExpr,DndError>{
+        Parser::new(notation).parse()
+        .map_err(|reason|DndError::InvalidDiceNotation{notation:notation.to_string(),reason})
+    }
+    ///投掷表达式，返回总值以及每个骰子
+    /// ```
+    /// use minidnd_eecs_havefun::{DiceExpr,seeded_rng};
+    /// let expr=DiceExpr::parse("4d6kh3+2").unwrap();
+    /// let result=expr.roll(&mut seeded_rng(3));
+    /// assert_eq!(result,expr.roll(&mut seeded_rng(3)));
+    /// assert_eq!(result.dice.len(),4);
+    /// assert_eq!(result.dice.iter().filter(|d|d.kept).count(),3);
+    /// let kept:i32=result.dice.iter().filter(|d|d.kept).map(|d|d.value).sum();
+    /// assert_eq!(result.total,kept+2);
+    /// //超出i32范围的总值取最接近的一端
+    /// assert_eq!(DiceExpr::parse("2147483647+1d4").unwrap().roll(&mut seeded_rng(3)).total,i32::MAX);
+    /// ```
+    pub fn roll<R:Rng+?Sized>(&self,rng:&mut R)->RollResult{
+        let mut dice=Vec::new();
+        let mut total:i64=0;
+        for term in &self.terms{
+            let value=match &term.kind{
+                TermKind::Constant(c)=>*c,
+                TermKind::Dice(d)=>d.roll(rng,&mut dice),
+            };
+            total+=if term.sign>0 {value as i64} else {-(value as i64)};
+        }
+        RollResult{total:saturate(total),dice}
+    }
+    ///重击时骰子数量翻倍，常数不变，例如2d6+3变为4d6+3
+    /// ```
+    /// use minidnd_eecs_havefun::DiceExpr;
+    /// assert_eq!(DiceExpr::parse("2d6+1d4+3").unwrap().doubled_dice().to_string(),"4d6+2d4+3");
+    /// ```
+    pub fn doubled_dice(&self)->DiceExpr{
+        self.multiplied_dice(2)
+    }
+    ///骰子数量乘以factor，常数不变，例如戏法伤害随等级增长时1d10变为3d10。
+    /// 数量最多为[`MAX_DICE_COUNT`]，保留或丢弃的个数按实际的数量等比例缩放，并且至少保留一个骰子
+    /// ```
+    /// use minidnd_eecs_havefun::DiceExpr;
+    /// assert_eq!(DiceExpr::parse("1d10+1").unwrap().multiplied_dice(3).to_string(),"3d10+1");
+    /// assert_eq!(DiceExpr::parse("4d6kh3").unwrap().multiplied_dice(2).to_string(),"8d6kh6");
+    /// //60个骰子翻倍时只能变为100个，丢弃的个数也按100/60缩放
+    /// let doubled=DiceExpr::parse("60d6dl50").unwrap().multiplied_dice(2);
+    /// assert_eq!(doubled.to_string(),"100d6dl83");
+    /// assert_eq!(doubled.bounds(),(17,102));
+    /// ```
+    pub fn multiplied_dice(&self,factor:i32)->DiceExpr{
+        let mut multiplied=self.clone();
+        for term in &mut multiplied.terms{
+            if let TermKind::Dice(d)=&mut term.kind{
+                let count=d.count.saturating_mul(factor.max(1)).min(MAX_DICE_COUNT);
+                let scale=|k:i32|k*count/d.count;
+                d.keep=match d.keep{
+                    Some(Keep::Highest(k))=>Some(Keep::Highest(scale(k).max(1))),
+                    Some(Keep::Lowest(k))=>Some(Keep::Lowest(scale(k).max(1))),
+                    Some(Keep::DropHighest(k))=>Some(Keep::DropHighest(scale(k).min(count-1))),
+                    Some(Keep::DropLowest(k))=>Some(Keep::DropLowest(scale(k).min(count-1))),
+                    None=>None,
+                };
+                d.count=count;
+            }
+        }
+        multiplied
+    }
+    ///表达式可能取到的最小值与最大值，超出i32范围时取最接近的一端
+    /// ```
+    /// use minidnd_eecs_havefun::DiceExpr;
+    /// assert_eq!(DiceExpr::parse("2147483647+1").unwrap().bounds(),(i32::MAX,i32::MAX));
+    /// ```
+    pub fn bounds(&self)->(i32,i32){
+        let (low,high)=self.wide_bounds();
+        (saturate(low),saturate(high))
+    }
+    ///不会溢出的最小值与最大值
+    pub(crate) fn wide_bounds(&self)->(i64,i64){
+        let mut low:i64=0;
+        let mut high:i64=0;
+        for term in &self.terms{
+            let (min,max)=match &term.kind{
+                TermKind::Constant(c)=>(*c as i64,*c as i64),
+                TermKind::Dice(d)=>{
+                    let n=d.keep.map_or(d.count,|k|k.kept_count(d.count)) as i64;
+                    (n,n*d.sides as i64)
+                },
+            };
+            if term.sign>0 {low+=min;high+=max;}
+            else {low-=max;high-=min;}
+        }
+        (low,high)
+    }
+}
+
+impl FromStr for DiceExpr{
+    type Err=DndError;
+    fn from_str(s:&str)->Result<Self,Self::Err>{
+        DiceExpr::parse(s)
+    }
+}
+
+impl fmt::Display for DiceExpr{
+    fn fmt(&self,f:&mut fmt::Formatter<'_>)->fmt::Result{
+        for (i,term) in self.terms.iter().enumerate(){
+            if term.sign<0 {write!(f,"-")?;}
+            else if i>0 {write!(f,"+")?;}
+            match &term.kind{
+                TermKind::Constant(c)=>write!(f,"{}",c)?,
+                TermKind::Dice(d)=>write!(f,"{}",d)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for DiceTerm{
+    fn fmt(&self,f:&mut fmt::Formatter<'_>)->fmt::Result{
+        write!(f,"{}d{}",self.count,self.sides)?;
+        match self.keep{
+            Some(Keep::Highest(k))=>write!(f,"kh{}",k)?,
+            Some(Keep::Lowest(k))=>write!(f,"kl{}",k)?,
+            Some(Keep::DropHighest(k))=>write!(f,"dh{}",k)?,
+            Some(Keep::DropLowest(k))=>write!(f,"dl{}",k)?,
+            None=>{},
+        }
+        if let Some(r)=self.reroll{
+            write!(f,"r{}{}{}",if r.once {"o"} else {""},if r.at_most {"<"} else {""},r.value)?;
+        }
+        if self.advantage>0 {write!(f,"adv")?;}
+        else if self.advantage<0 {write!(f,"dis")?;}
+        Ok(())
+    }
+}
+
+///存档中以字符串形式保存表达式
+impl Serialize for DiceExpr{
+    fn serialize<S:Serializer>(&self,serializer:S)->Result<S::Ok,S::Error>{
+        serializer.collect_str(self)
+    }
+}
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DiceExprRepr{
+    Notation(String),
+    ///旧存档中的(x,y)
+    Tuple(i32,i32),
+}
+///既能读取字符串形式，也能读取旧存档中的(x,y)元组
+impl<'de> Deserialize<'de> for DiceExpr{
+    fn deserialize<D:Deserializer<'de>>(deserializer:D)->Result<Self,D::Error>{
+        match DiceExprRepr::deserialize(deserializer)?{
+            DiceExprRepr::Notation(s)=>DiceExpr::parse(&s).map_err(serde::de::Error::custom),
+            DiceExprRepr::Tuple(count,sides)=>DiceExpr::from_dice(count,sides).map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+///把i64截断到i32的范围内
+fn saturate(value:i64)->i32{
+    value.clamp(i32::MIN as i64,i32::MAX as i64) as i32
+}
+
+///手写的递归下降解析器
+struct Parser{
+    chars:Vec<char>,
+    pos:usize,
+}
+impl Parser{
+    fn new(notation:&str)->Parser{
+        Parser{chars:notation.chars().filter(|c|!c.is_whitespace()).map(|c|c.to_ascii_lowercase()).collect(),pos:0}
+    }
+    fn peek(&self)->Option<char>{
+        self.chars.get(self.pos).copied()
+    }
+    fn eat(&mut self,s:&str)->bool{
+        let len=s.chars().count();
+        if self.pos+len<=self.chars.len()&&self.chars[self.pos..self.pos+len].iter().copied().eq(s.chars()){
+            self.pos+=len;
+            true
+        }
+        else {false}
+    }
+    fn number(&mut self)->Result<Option<i32>,&'static str>{
+        let start=self.pos;
+        while self.peek().is_some_and(|c|c.is_ascii_digit()){
+            self.pos+=1;
+        }
+        if start==self.pos {return Ok(None)}
+        let s:String=self.chars[start..self.pos].iter().collect();
+        s.parse().map(Some).map_err(|_|"number is too large")
+    }
+    fn required_number(&mut self)->Result<i32,&'static str>{
+        self.number()?.ok_or("expected a number")
+    }
+    fn parse(mut self)->Result<DiceExpr,&'static str>{
+        if self.chars.is_empty() {return Err("empty dice expression")}
+        let mut terms=Vec::new();
+        let mut sign=if self.eat("-") {-1} else {self.eat("+");1};
+        loop{
+            terms.push(Term{sign,kind:self.term()?});
+            if self.eat("+") {sign=1;}
+            else if self.eat("-") {sign= -1;}
+            else if self.peek().is_none() {break}
+            else {return Err("unexpected character in dice expression")}
+        }
+        Ok(DiceExpr{terms})
+    }
+    fn term(&mut self)->Result<TermKind,&'static str>{
+        let count=self.number()?;
+        if !self.eat("d") {
+            return count.map(TermKind::Constant).ok_or("expected a number or dice");
+        }
+        let mut dice=DiceTerm::plain(count.unwrap_or(1),self.required_number()?);
+        loop{
+            if self.eat("adv") {dice.advantage=1;}
+            else if self.eat("dis") {dice.advantage= -1;}
+            else if self.eat("kh") {dice.keep=Some(Keep::Highest(self.required_number()?));}
+            else if self.eat("kl") {dice.keep=Some(Keep::Lowest(self.required_number()?));}
+            else if self.eat("k") {dice.keep=Some(Keep::Highest(self.required_number()?));}
+            else if self.eat("dh") {dice.keep=Some(Keep::DropHighest(self.required_number()?));}
+            else if self.eat("dl") {dice.keep=Some(Keep::DropLowest(self.required_number()?));}
+            else if self.eat("r") {
+                let once=self.eat("o");
+                let at_most=self.eat("<");
+                dice.reroll=Some(Reroll{value:self.required_number()?,at_most,once});
+            }
+            else {break}
+        }
+        dice.validate()?;
+        Ok(TermKind::Dice(dice))
+    }
+}
@@ -0,0 +1,393 @@
+//!回合制战斗引擎，对应战斗步骤中的第4、5步
+//![`CombatEncounter`]接收`determine_surprise`与`roll_initiative`的结果，按照先攻顺序逐个执行回合。
+//!DM前端通过`next_turn`推进战斗，并在每个回合中消耗动作、附赠动作、反应以及移动力。
+//!失能的参与者在自己的回合中没有动作与反应；每个回合结束时结算该参与者状态的持续时间与豁免。
+//!濒死的参与者在自己的回合开始时自动进行死亡豁免，投出20恢复意识后照常行动，否则跳过这个回合。
+//!一场战斗可以有任意多个阵营，阵营之间的关系由[`FactionTable`]决定，参与者可以在战斗中改变阵营
+use crate::{clean_up_concentration,seeded_rng,Combatant,Concentration,DeathSaveOutcome,DndError,FactionTable,LifeState,Player,SurpriseStatus};
+use crate::condition::Conditions;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::{Serialize,Deserialize};
+use std::collections::{HashMap,HashSet};
+
+#[derive(Clone,Debug,PartialEq,Eq,Serialize,Deserialize)]
+///战斗的结局
+pub enum Outcome{
+    ///仍然站着的参与者之间不再有敌对关系，包含他们所在的阵营（按名字排序）
+    Victory(Vec<String>),
+    ///所有人同时倒下
+    Draw,
+}
+#[derive(Clone,Debug,Default,PartialEq,Eq,Serialize,Deserialize)]
+///战斗所处的状态
+pub enum EncounterState{
+    #[default] NotStarted,
+    InProgress,
+    Ended(Outcome),
+}
+#[derive(Copy,Clone,Debug,Default,PartialEq,Eq,Serialize,Deserialize)]
+///一个回合内还剩下的行动资源，移动力以尺计
+pub struct TurnBudget{
+    pub action:bool,
+    pub bonus_action:bool,
+    pub reaction:bool,
+    pub movement:i32,
+}
+#[derive(Clone,Debug,PartialEq,Eq,Serialize,Deserialize)]
+///当前回合的信息
+pub struct Turn{
+    pub round:i32,
+    pub name:String,
+    pub faction:String,
+}
+
+#[derive(Clone,Debug,Serialize,Deserialize)]
+///一场战斗。参与者可以是任何[`Combatant`]，不同类型的参与者混在一起时使用`Box<dyn Combatant>`
+pub struct CombatEncounter<C=Player>{
+    pub combatants:HashMap<String,C>,
+    ///阵营之间的关系
+    pub relations:FactionTable,
+    ///每个参与者当前所在的阵营，开始时取自[`Combatant::faction`]，见[`CombatEncounter::switch_faction`]
+    factions:HashMap<String,String>,
+    ///先攻顺序
+    order:Vec<String>,
+    ///仍处于突袭状态的参与者，他们会跳过自己的第一个回合，并且在该回合结束前不能使用反应
+    surprised:HashSet<String>,
+    ///每个参与者的反应是否可用，反应在自己的回合开始时恢复
+    reactions:HashMap<String,bool>,
+    pub round:i32,
+    current:Option<usize>,
+    budget:TurnBudget,
+    state:EncounterState,
+    ///自动进行的死亡豁免及其结果，按时间顺序排列
+    death_saves:Vec<(String,DeathSaveOutcome)>,
+    ///回合结束时状态豁免以及死亡豁免使用的随机数生成器，见[`CombatEncounter::seed`]
+    #[serde(skip,default="StdRng::from_os_rng")]
+    rng:StdRng,
+}
+
+impl Player{
+    ///战斗中每回合可用的移动力，取行走速度与飞行速度中的较大者。
+    /// 受擒、束缚、5级力竭时为0，2级力竭时减半；力量不满足重甲的要求时减少10尺
+    pub fn speed(&self)->i32{
+        self.conditions.adjusted_speed((self.walking_speed.max(self.flying_speed)-self.armor_speed_penalty()).max(0))
+    }
+    ///生命值降到0（濒死、稳定或死亡）或者6级力竭即视为倒下
+    pub fn is_defeated(&self)->bool{
+        self.hp<=0||self.life_state!=LifeState::Conscious||self.conditions.exhaustion_level()>=6
+    }
+}
+
+impl<C:Combatant> CombatEncounter<C>{
+    ///用突袭与先攻的结果建立战斗。每个参与者的阵营取自[`Combatant::faction`]，阵营之间的关系由relations决定。
+    /// surprise给出每个参与者的突袭状态；initiative中数值较小的先行动
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,Combat,EncounterState,CombatEncounter,FactionTable};
+    /// use std::collections::HashMap;
+    /// let alice=Player{faction:"heroes".to_string(),..Player::new_by_default()};
+    /// let goblin=Player{name:"Goblin".to_string(),hp:7,faction:"goblins".to_string(),..Player::new_by_default()};
+    /// let combatants=HashMap::from([("Alice".to_string(),alice),("Goblin".to_string(),goblin)]);
+    /// let relations=FactionTable::new();
+    /// let surprise=Player::determine_surprise(&combatants,&relations,&HashMap::new()).unwrap();
+    /// let initiative=Player::roll_initiative(&combatants);
+    /// let mut encounter=CombatEncounter::new(combatants,relations,&surprise,&initiative.ranks()).unwrap();
+    /// let turn=encounter.next_turn().unwrap();
+    /// assert_eq!(turn.round,1);
+    /// encounter.use_action().unwrap();
+    /// assert!(encounter.use_action().is_err());
+    /// encounter.spend_movement(30).unwrap();
+    /// assert!(encounter.spend_movement(5).is_err());
+    /// encounter.combatant_mut("Goblin").unwrap().hp=0;
+    /// assert!(encounter.next_turn().is_none());
+    /// assert!(matches!(encounter.state(),EncounterState::Ended(_)));
+    /// ```
+    pub fn new(combatants:HashMap<String,C>,relations:FactionTable,
+    surprise:&HashMap<String,SurpriseStatus>,initiative:&HashMap<String,i32>)->Result<CombatEncounter<C>,DndError>{
+        let mut ranked:Vec<(i32,String)>=Vec::with_capacity(combatants.len());
+        for name in combatants.keys(){
+            let rank=initiative.get(name).ok_or_else(||DndError::UnknownCombatant(name.clone()))?;
+            ranked.push((*rank,name.clone()));
+        }
+        ranked.sort();
+        let surprised=surprise.iter()
+        .filter(|(name,status)|**status==SurpriseStatus::Surprised&&combatants.contains_key(*name))
+        .map(|(name,_)|name.clone()).collect();
+        let reactions=ranked.iter().map(|(_,name)|(name.clone(),true)).collect();
+        let factions=combatants.iter().map(|(name,c)|(name.clone(),c.faction().to_string())).collect();
+        Ok(CombatEncounter{combatants,relations,factions,order:ranked.into_iter().map(|(_,name)|name).collect(),
+        surprised,reactions,round:0,current:None,budget:TurnBudget::default(),state:EncounterState::NotStarted,
+        death_saves:Vec::new(),rng:StdRng::from_os_rng()})
+    }
+    ///到目前为止自动进行的死亡豁免，每一项为参与者名字与结果
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,CombatEncounter,DamageType,FactionTable};
+    /// use std::collections::HashMap;
+    /// let mut bob=Player{name:"Bob".to_string(),..Player::new_by_default()};
+    /// bob.apply_damage(100,DamageType::Fire);
+    /// let goblin=Player{name:"Goblin".to_string(),faction:"goblins".to_string(),..Player::new_by_default()};
+    /// let combatants=HashMap::from([("Alice".to_string(),Player::new_by_default()),("Bob".to_string(),bob),("Goblin".to_string(),goblin)]);
+    /// let initiative=HashMap::from([("Alice".to_string(),1),("Bob".to_string(),2),("Goblin".to_string(),3)]);
+    /// let mut encounter=CombatEncounter::new(combatants,FactionTable::new(),&HashMap::new(),&initiative).unwrap();
+    /// encounter.seed(5);
+    /// encounter.next_turn();
+    /// //Bob濒死，轮到他时自动进行死亡豁免
+    /// let next=encounter.next_turn().unwrap();
+    /// assert_eq!(encounter.death_saves()[0].0,"Bob");
+    /// assert_eq!(next.name=="Bob",encounter.combatant("Bob").unwrap().hp==1);
+    /// ```
+    pub fn death_saves(&self)->&[(String,DeathSaveOutcome)]{
+        &self.death_saves
+    }
+    ///设置状态豁免与死亡豁免使用的种子，使战斗可以复现
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,CombatEncounter,Condition,ConditionDuration,ConditionTracker,FactionTable};
+    /// use std::collections::HashMap;
+    /// let mut alice=Player::new_by_default();
+    /// alice.add_condition(Condition::Stunned,ConditionDuration::Rounds(1),None);
+    /// let goblin=Player{name:"Goblin".to_string(),faction:"goblins".to_string(),..Player::new_by_default()};
+    /// let combatants=HashMap::from([("Alice".to_string(),alice),("Goblin".to_string(),goblin)]);
+    /// let initiative=HashMap::from([("Alice".to_string(),1),("Goblin".to_string(),2)]);
+    /// let mut encounter=CombatEncounter::new(combatants,FactionTable::new(),&HashMap::new(),&initiative).unwrap();
+    /// encounter.seed(3);
+    /// assert_eq!(encounter.next_turn().unwrap().name,"Alice");
+    /// //震慑包含失能，没有动作与反应
+    /// assert!(encounter.use_action().is_err());
+    /// encounter.next_turn();
+    /// assert!(encounter.combatant("Alice").unwrap().conditions.is_empty());
+    /// ```
+    pub fn seed(&mut self,seed:u64){
+        self.rng=seeded_rng(seed);
+    }
+    ///战斗状态
+    pub fn state(&self)->EncounterState{
+        self.state.clone()
+    }
+    ///先攻顺序
+    pub fn order(&self)->&[String]{
+        &self.order
+    }
+    ///当前回合剩余的行动资源
+    pub fn budget(&self)->TurnBudget{
+        self.budget
+    }
+    ///某个参与者当前所在的阵营
+    pub fn faction_of(&self,name:&str)->Option<&str>{
+        self.factions.get(name).map(|f|f.as_str())
+    }
+    ///参与者改变阵营，例如被魅惑或者背叛，从下一次检查胜负起生效。
+    /// 只改变这场战斗中的阵营，不修改参与者本身
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,CombatEncounter,EncounterState,FactionTable,Outcome};
+    /// use std::collections::HashMap;
+    /// let alice=Player{faction:"party".to_string(),..Player::new_by_default()};
+    /// let bandit=Player{name:"Bandit".to_string(),faction:"bandits".to_string(),..Player::new_by_default()};
+    /// let combatants=HashMap::from([("Alice".to_string(),alice),("Bandit".to_string(),bandit)]);
+    /// let initiative=HashMap::from([("Alice".to_string(),1),("Bandit".to_string(),2)]);
+    /// let mut encounter=CombatEncounter::new(combatants,FactionTable::new(),&HashMap::new(),&initiative).unwrap();
+    /// assert!(encounter.is_hostile("Alice","Bandit"));
+    /// //强盗投降并加入队伍
+    /// encounter.switch_faction("Bandit","party").unwrap();
+    /// assert_eq!(encounter.faction_of("Bandit"),Some("party"));
+    /// assert_eq!(encounter.check_victory(),EncounterState::Ended(Outcome::Victory(vec!["party".to_string()])));
+    /// assert!(encounter.switch_faction("Carol","party").is_err());
+    /// ```
+    pub fn switch_faction(&mut self,name:&str,faction:&str)->Result<(),DndError>{
+        let current=self.factions.get_mut(name).ok_or_else(||DndError::UnknownCombatant(name.to_string()))?;
+        *current=faction.to_string();
+        Ok(())
+    }
+    ///两个参与者当前是否敌对，不在战斗中的参与者不与任何人敌对
+    pub fn is_hostile(&self,name_1:&str,name_2:&str)->bool{
+        match (self.faction_of(name_1),self.faction_of(name_2)){
+            (Some(faction_1),Some(faction_2))=>self.relations.is_hostile(faction_1,faction_2),
+            _=>false,
+        }
+    }
+    ///某个参与者当前的敌人，按名字排序
+    pub fn enemies_of(&self,name:&str)->Vec<String>{
+        let mut enemies:Vec<String>=self.factions.keys().filter(|other|self.is_hostile(name,other)).cloned().collect();
+        enemies.sort();
+        enemies
+    }
+    ///按名字查找参与者
+    pub fn combatant(&self,name:&str)->Option<&C>{
+        self.combatants.get(name)
+    }
+    ///按名字查找参与者，用于结算伤害等
+    pub fn combatant_mut(&mut self,name:&str)->Option<&mut C>{
+        self.combatants.get_mut(name)
+    }
+    ///某个参与者是否仍处于突袭状态
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,CombatEncounter,FactionTable,SurpriseStatus};
+    /// use minidnd_eecs_havefun::condition::{Condition,ConditionDuration,ConditionTracker,Conditions};
+    /// use std::collections::HashMap;
+    /// let mut alice=Player::new_by_default();
+    /// alice.add_condition(Condition::Blinded,ConditionDuration::Rounds(1),None);
+    /// let goblin=Player{name:"Goblin".to_string(),faction:"goblins".to_string(),..Player::new_by_default()};
+    /// let combatants=HashMap::from([("Alice".to_string(),alice),("Goblin".to_string(),goblin)]);
+    /// let initiative=HashMap::from([("Alice".to_string(),1),("Goblin".to_string(),2)]);
+    /// let surprise=HashMap::from([("Alice".to_string(),SurpriseStatus::Surprised)]);
+    /// let mut encounter=CombatEncounter::new(combatants,FactionTable::new(),&surprise,&initiative).unwrap();
+    /// assert!(encounter.is_surprised("Alice"));
+    /// //被突袭的Alice跳过第一个回合，但持续1轮的目盲依然结束
+    /// assert_eq!(encounter.next_turn().unwrap().name,"Goblin");
+    /// assert!(!encounter.is_surprised("Alice"));
+    /// assert!(!encounter.combatant("Alice").unwrap().conditions.has_condition(Condition::Blinded));
+    /// ```
+    pub fn is_surprised(&self,name:&str)->bool{
+        self.surprised.contains(name)
+    }
+    ///当前回合，战斗未开始或已结束时为None
+    pub fn current_turn(&self)->Option<Turn>{
+        if self.state!=EncounterState::InProgress {return None}
+        let name=self.order.get(self.current?)?;
+        Some(Turn{round:self.round,name:name.clone(),faction:self.faction_of(name)?.to_string()})
+    }
+    ///检查仍然站着的参与者之间是否还有敌对关系，如果没有则结束战斗
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,CombatEncounter,EncounterState,FactionTable,Outcome,Relation};
+    /// use std::collections::HashMap;
+    /// let member=|name:&str,faction:&str|(name.to_string(),Player{name:name.to_string(),faction:faction.to_string(),..Player::new_by_default()});
+    /// let combatants=HashMap::from([member("Alice","party"),member("Bandit","bandits"),member("Owlbear","owlbear"),member("Elder","villagers")]);
+    /// let initiative=HashMap::from([("Alice".to_string(),1),("Bandit".to_string(),2),("Owlbear".to_string(),3),("Elder".to_string(),4)]);
+    /// let mut relations=FactionTable::new();
+    /// relations.set_relation("party","villagers",Relation::Allied);
+    /// relations.set_relation("bandits","villagers",Relation::Neutral);
+    /// relations.set_relation("owlbear","villagers",Relation::Neutral);
+    /// let mut encounter=CombatEncounter::new(combatants,relations,&HashMap::new(),&initiative).unwrap();
+    /// encounter.combatant_mut("Owlbear").unwrap().hp=0;
+    /// //队伍与强盗仍然敌对
+    /// assert_eq!(encounter.check_victory(),EncounterState::NotStarted);
+    /// encounter.combatant_mut("Bandit").unwrap().hp=0;
+    /// assert_eq!(encounter.check_victory(),EncounterState::Ended(Outcome::Victory(vec!["party".to_string(),"villagers".to_string()])));
+    /// ```
+    pub fn check_victory(&mut self)->EncounterState{
+        if matches!(self.state,EncounterState::Ended(_)) {return self.state.clone()}
+        let mut standing:Vec<&String>=self.combatants.iter().filter(|(_,c)|!c.is_defeated()).map(|(name,_)|name).collect();
+        standing.sort();
+        let hostile=standing.iter().enumerate()
+        .any(|(i,name_1)|standing[i+1..].iter().any(|name_2|self.is_hostile(name_1,name_2)));
+        if hostile {return self.state.clone()}
+        let mut winners:Vec<String>=standing.iter().filter_map(|name|self.faction_of(name)).map(|f|f.to_string()).collect();
+        winners.sort();
+        winners.dedup();
+        let outcome=if winners.is_empty() {Outcome::Draw} else {Outcome::Victory(winners)};
+        self.state=EncounterState::Ended(outcome);
+        self.current=None;
+        self.state.clone()
+    }
+    ///结束当前回合并开始下一个回合，返回新回合的信息；战斗结束时返回None。
+    /// 结束回合时，当前参与者按轮计时的状态减1轮，需要豁免的状态进行豁免，随后清理已经结束的专注效果。
+    /// 倒下的参与者被跳过；被突袭的参与者跳过第一轮中自己的回合（回合结束时的状态结算照常进行），之后恢复正常
+    pub fn next_turn(&mut self)->Option<Turn>{
+        self.advance(false)
+    }
+    ///与`next_turn`相同，但是当本轮所有人都已行动时不开始新的一轮而是返回None，
+    /// 之后再调用`next_turn`或本函数会开始下一轮
+    pub fn next_turn_in_round(&mut self)->Option<Turn>{
+        self.advance(true)
+    }
+    ///结算待进行的专注检定并清理已经结束的专注效果，见[`clean_up_concentration`]。DM在回合中结算了伤害之后可以立即调用，
+    /// 否则在下一个回合开始前自动进行
+    pub fn clean_up_concentration(&mut self)->Vec<Concentration>{
+        clean_up_concentration(&mut self.rng,&mut self.combatants)
+    }
+    fn advance(&mut self,within_round:bool)->Option<Turn>{
+        if self.state==EncounterState::NotStarted {
+            self.state=EncounterState::InProgress;
+        }
+        if let Some(name)=self.current.and_then(|i|self.order.get(i)){
+            if let Some(player)=self.combatants.get_mut(name) {
+                player.end_turn(&mut self.rng);
+            }
+        }
+        self.clean_up_concentration();
+        if let EncounterState::Ended(_)=self.check_victory() {return None}
+        let mut index=match self.current{
+            Some(i)=>i+1,
+            None=>{self.round+=1;0},
+        };
+        //最多检查两轮，防止所有人都无法行动时死循环
+        for _ in 0..=self.order.len()*2{
+            if index>=self.order.len() {
+                self.current=None;
+                if within_round {return None}
+                index=0;
+                self.round+=1;
+            }
+            let name=self.order[index].clone();
+            if !self.surprised.contains(&name) {
+                if let Some(outcome)=self.combatants.get_mut(&name).and_then(|p|p.start_turn(&mut self.rng)){
+                    self.death_saves.push((name.clone(),outcome));
+                }
+            }
+            if self.surprised.remove(&name) {
+                //被突袭者不能行动，但回合依然结束：状态减1轮，需要豁免的状态进行豁免
+                if let Some(combatant)=self.combatants.get_mut(&name) {combatant.end_turn(&mut self.rng);}
+                index+=1;
+                continue;
+            }
+            if self.combatant(&name).is_none_or(|p|p.is_defeated()) {
+                index+=1;
+                continue;
+            }
+            let speed=self.combatant(&name).map_or(0,|p|p.speed());
+            let able=!self.combatant(&name).is_some_and(|p|p.conditions().is_incapacitated());
+            self.reactions.insert(name.clone(),true);
+            self.budget=TurnBudget{action:able,bonus_action:able,reaction:able,movement:speed};
+            self.current=Some(index);
+            return self.current_turn();
+        }
+        self.current=None;
+        None
+    }
+    fn active(&self)->Result<(),DndError>{
+        match self.state{
+            EncounterState::InProgress if self.current.is_some()=>Ok(()),
+            EncounterState::Ended(_)=>Err(DndError::EncounterOver),
+            _=>Err(DndError::NoActiveTurn),
+        }
+    }
+    ///消耗当前回合的动作
+    pub fn use_action(&mut self)->Result<(),DndError>{
+        self.active()?;
+        if !self.budget.action {return Err(DndError::ActionUnavailable("action"))}
+        self.budget.action=false;
+        Ok(())
+    }
+    ///消耗当前回合的附赠动作
+    pub fn use_bonus_action(&mut self)->Result<(),DndError>{
+        self.active()?;
+        if !self.budget.bonus_action {return Err(DndError::ActionUnavailable("bonus action"))}
+        self.budget.bonus_action=false;
+        Ok(())
+    }
+    ///冲刺：消耗动作，本回合移动力增加一倍速度
+    pub fn dash(&mut self)->Result<(),DndError>{
+        self.use_action()?;
+        let name=&self.order[self.current.unwrap_or_default()];
+        self.budget.movement+=self.combatant(name).map_or(0,|p|p.speed());
+        Ok(())
+    }
+    ///消耗移动力（尺）
+    pub fn spend_movement(&mut self,feet:i32)->Result<(),DndError>{
+        self.active()?;
+        if feet>self.budget.movement {return Err(DndError::NotEnoughMovement{requested:feet,remaining:self.budget.movement})}
+        self.budget.movement-=feet.max(0);
+        Ok(())
+    }
+    ///某个参与者使用反应，反应可以在别人的回合中使用。仍处于突袭状态或者失能的参与者不能使用反应
+    pub fn use_reaction(&mut self,name:&str)->Result<(),DndError>{
+        if let EncounterState::Ended(_)=self.state {return Err(DndError::EncounterOver)}
+        if self.surprised.contains(name)||self.combatant(name).is_some_and(|p|p.conditions().is_incapacitated()) {
+            return Err(DndError::ActionUnavailable("reaction"))
+        }
+        let available=self.reactions.get_mut(name).ok_or_else(||DndError::UnknownCombatant(name.to_string()))?;
+        if !*available {return Err(DndError::ActionUnavailable("reaction"))}
+        *available=false;
+        if self.current_turn().is_some_and(|t|t.name==name) {self.budget.reaction=false;}
+        Ok(())
+    }
+}
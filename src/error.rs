@@ -0,0 +1,200 @@
+//!整个库统一使用的错误类型
+use crate::{Abilities,CharacterClass,Skill};
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+///所有可能失败的函数返回的错误。携带导致错误的具体数值，便于调用者匹配处理
+pub enum DndError{
+    ///难度等级不在1到50之间
+    DcOutOfRange(i32),
+    ///d骰数量不在1到10之间
+    CountOutOfRange(i32),
+    ///优劣势不在-1到1之间
+    AdvantageOutOfRange(i32),
+    ///单次投掷的上界不在2到100之间
+    UpperboundOutOfRange(i32),
+    ///经验值不合法（小于0）
+    InvalidExperience(i32),
+    ///货币总价值为负，携带换算成铜币后的总值
+    NegativeCoins(i32),
+    ///骰子表达式无法解析
+    InvalidDiceNotation{notation:String,reason:&'static str},
+    ///骰子太多，无法精确计算概率分布
+    DistributionTooLarge,
+    ///无法识别的技能名
+    UnknownSkill(String),
+    ///攻击者没有给定名字的武器
+    WeaponNotFound(String),
+    ///找不到给定名字的战斗参与者
+    UnknownCombatant(String),
+    ///已经存在同名的战斗参与者
+    DuplicateCombatant(String),
+    ///参与者没有准备好的动作
+    NothingReadied(String),
+    ///坐标在战斗地图之外
+    PositionOutOfBounds{x:i32,y:i32},
+    ///地图的方格数超出范围
+    MapTooLarge{width:i32,height:i32},
+    ///存档中地形的数量与地图大小不一致
+    TerrainSizeMismatch{expected:usize,actual:usize},
+    ///方格无法通行或者已经被占据
+    SquareUnavailable{x:i32,y:i32},
+    ///战斗地图上已经没有空闲方格
+    NoRoomOnMap,
+    ///当前没有正在进行的回合
+    NoActiveTurn,
+    ///战斗已经结束
+    EncounterOver,
+    ///本回合的某种行动已经用掉了
+    ActionUnavailable(&'static str),
+    ///移动力不足
+    NotEnoughMovement{requested:i32,remaining:i32},
+    ///只有濒死的生物才能进行死亡豁免
+    NotDying(String),
+    ///无法识别的挑战等级
+    InvalidChallengeRating(String),
+    ///玩家还没有选择职业
+    NoClass,
+    ///玩家已经选择了职业
+    ClassAlreadyChosen(CharacterClass),
+    ///玩家没有这个职业
+    NotInClass(CharacterClass),
+    ///属性值不满足兼职的要求
+    MulticlassPrerequisite(CharacterClass),
+    ///选择的技能不在职业的可选范围内，或者重复选择
+    InvalidSkillChoice(Skill),
+    ///选择的技能数量不对
+    WrongSkillChoiceCount{expected:usize,given:usize},
+    ///已经从种族、背景或其他职业获得了这个技能的熟练，需要另选一个
+    SkillAlreadyProficient(Skill),
+    ///经验值不足以升到给定等级
+    NotEnoughExperience{level:i32,exp:i32},
+    ///这个等级有属性值提升，但是没有给出选择
+    MissingAbilityScoreImprovement(i32),
+    ///这个等级没有属性值提升
+    UnexpectedAbilityScoreImprovement(i32),
+    ///属性值超过上限
+    AbilityScoreTooHigh{ability:Abilities,score:i32},
+    ///还没有选择属性值的生成方式
+    NoAbilityScores,
+    ///购点的属性值不在8到15之间
+    PointBuyScoreOutOfRange{ability:Abilities,score:i32},
+    ///购点的总花费超过预算，携带实际花费
+    PointBuyOverBudget(i32),
+    ///同一种属性被分配了两次
+    DuplicateAbilityAssignment(Abilities),
+    ///职业不能施法
+    NotASpellcaster(CharacterClass),
+    ///法术环阶或者法术位环阶不合法
+    InvalidSpellLevel(i32),
+    ///没有剩余的该环阶法术位
+    NoSpellSlot(i32),
+    ///法术没有效果范围
+    NoAreaOfEffect(String),
+    ///目标超出施法距离
+    TargetOutOfRange{distance:i32,range:i32},
+    ///没有在专注
+    NotConcentrating(String),
+    ///休息被战斗等剧烈活动打断
+    RestInterrupted,
+    ///休息的时间不够
+    RestIncomplete{minutes:i32,required:i32},
+    ///生命值为0的角色无法从长休中获益
+    CannotRest(String),
+    ///没有足够的生命骰
+    NoHitDice(CharacterClass),
+    ///没有这个职业资源
+    UnknownResource(String),
+    ///职业资源的剩余次数不够
+    ResourceExhausted(String),
+    ///穿着不熟练的护甲时不能施法
+    ArmorNotProficient(String),
+    ///读写文件失败
+    Io(io::Error),
+    ///序列化或反序列化失败
+    Serialization(serde_json::Error),
+}
+
+impl fmt::Display for DndError{
+    fn fmt(&self,f:&mut fmt::Formatter<'_>)->fmt::Result{
+        match self{
+            DndError::DcOutOfRange(dc)=>write!(f,"dc {} is not in the range of 1 to 50",dc),
+            DndError::CountOutOfRange(count)=>write!(f,"count {} is not in the range of 1 to 10",count),
+            DndError::AdvantageOutOfRange(advantage)=>write!(f,"advantage {} is not in the range of -1 to 1",advantage),
+            DndError::UpperboundOutOfRange(upperbound)=>write!(f,"upperbound {} is not in the range of 2-100",upperbound),
+            DndError::InvalidExperience(exp)=>write!(f,"exp {} is not valid",exp),
+            DndError::NegativeCoins(total)=>write!(f,"given coins are wrong as the sum of them is negative ({} cp)",total),
+            DndError::InvalidDiceNotation{notation,reason}=>write!(f,"invalid dice notation \"{}\": {}",notation,reason),
+            DndError::DistributionTooLarge=>write!(f,"too many dice to compute the distribution exactly"),
+            DndError::UnknownSkill(name)=>write!(f,"unknown skill \"{}\"",name),
+            DndError::WeaponNotFound(name)=>write!(f,"no weapon named \"{}\"",name),
+            DndError::UnknownCombatant(name)=>write!(f,"no combatant named \"{}\"",name),
+            DndError::DuplicateCombatant(name)=>write!(f,"a combatant named \"{}\" already exists",name),
+            DndError::NothingReadied(name)=>write!(f,"{} has no readied action",name),
+            DndError::PositionOutOfBounds{x,y}=>write!(f,"position ({},{}) is outside the battle map",x,y),
+            DndError::MapTooLarge{width,height}=>write!(f,"a {}x{} battle map is too large",width,height),
+            DndError::TerrainSizeMismatch{expected,actual}=>write!(f,"the battle map needs {} terrain squares but {} were saved",expected,actual),
+            DndError::SquareUnavailable{x,y}=>write!(f,"square ({},{}) is impassable or occupied",x,y),
+            DndError::NoRoomOnMap=>write!(f,"there is no free square left on the battle map"),
+            DndError::NoActiveTurn=>write!(f,"no turn is in progress"),
+            DndError::EncounterOver=>write!(f,"the encounter has already ended"),
+            DndError::ActionUnavailable(kind)=>write!(f,"the {} has already been used",kind),
+            DndError::NotEnoughMovement{requested,remaining}=>write!(f,"cannot move {} feet with only {} feet of movement left",requested,remaining),
+            DndError::NotDying(name)=>write!(f,"{} is not dying",name),
+            DndError::InvalidChallengeRating(cr)=>write!(f,"\"{}\" is not a challenge rating",cr),
+            DndError::NoClass=>write!(f,"the player has not chosen a class"),
+            DndError::ClassAlreadyChosen(class)=>write!(f,"the player is already a {}",class),
+            DndError::NotInClass(class)=>write!(f,"the player has no {} levels",class),
+            DndError::MulticlassPrerequisite(class)=>write!(f,"ability scores do not meet the multiclass prerequisites of {}",class),
+            DndError::InvalidSkillChoice(skill)=>write!(f,"{:?} cannot be chosen",skill),
+            DndError::WrongSkillChoiceCount{expected,given}=>write!(f,"{} skills must be chosen but {} were given",expected,given),
+            DndError::SkillAlreadyProficient(skill)=>write!(f,"already proficient in {:?}, choose another skill",skill),
+            DndError::NotEnoughExperience{level,exp}=>write!(f,"{} exp is not enough to reach level {}",exp,level),
+            DndError::MissingAbilityScoreImprovement(level)=>write!(f,"level {} requires an ability score improvement",level),
+            DndError::UnexpectedAbilityScoreImprovement(level)=>write!(f,"level {} grants no ability score improvement",level),
+            DndError::AbilityScoreTooHigh{ability,score}=>write!(f,"{:?} {} exceeds the maximum of 20",ability,score),
+            DndError::NoAbilityScores=>write!(f,"no ability score generation method was chosen"),
+            DndError::PointBuyScoreOutOfRange{ability,score}=>write!(f,"{:?} {} is not in the point-buy range of 8 to 15",ability,score),
+            DndError::PointBuyOverBudget(spent)=>write!(f,"point buy spends {} points but the budget is 27",spent),
+            DndError::DuplicateAbilityAssignment(ability)=>write!(f,"{:?} is assigned more than once",ability),
+            DndError::NotASpellcaster(class)=>write!(f,"{} cannot cast spells",class),
+            DndError::InvalidSpellLevel(level)=>write!(f,"spell level {} is not valid",level),
+            DndError::NoSpellSlot(level)=>write!(f,"no level {} spell slot is left",level),
+            DndError::NoAreaOfEffect(spell)=>write!(f,"{} has no area of effect",spell),
+            DndError::TargetOutOfRange{distance,range}=>write!(f,"the target is {} feet away but the range is {} feet",distance,range),
+            DndError::NotConcentrating(name)=>write!(f,"{} is not concentrating on anything",name),
+            DndError::RestInterrupted=>write!(f,"the rest was interrupted"),
+            DndError::RestIncomplete{minutes,required}=>write!(f,"rested for {} minutes but {} are required",minutes,required),
+            DndError::CannotRest(name)=>write!(f,"{} must have at least 1 hit point to benefit from a long rest",name),
+            DndError::NoHitDice(class)=>write!(f,"not enough {:?} hit dice left",class),
+            DndError::UnknownResource(name)=>write!(f,"no class resource named {}",name),
+            DndError::ResourceExhausted(name)=>write!(f,"not enough {} left",name),
+            DndError::ArmorNotProficient(name)=>write!(f,"{} cannot cast spells while wearing armor without proficiency",name),
+            DndError::Io(e)=>write!(f,"io failure: {}",e),
+            DndError::Serialization(e)=>write!(f,"serialization failure: {}",e),
+        }
+    }
+}
+
+impl Error for DndError{
+    fn source(&self)->Option<&(dyn Error+'static)>{
+        match self{
+            DndError::Io(e)=>Some(e),
+            DndError::Serialization(e)=>Some(e),
+            _=>None,
+        }
+    }
+}
+
+impl From<io::Error> for DndError{
+    fn from(e:io::Error)->Self{
+        DndError::Io(e)
+    }
+}
+impl From<serde_json::Error> for DndError{
+    fn from(e:serde_json::Error)->Self{
+        DndError::Serialization(e)
+    }
+}
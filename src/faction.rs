@@ -0,0 +1,54 @@
+//!阵营以及阵营之间的关系
+//!一场战斗可以有任意多个阵营，每个参与者属于一个阵营（见[`Combatant::faction`](crate::Combatant::faction)）。
+//!阵营之间的关系是对称的，没有特别设置时不同阵营互相敌对，同一阵营总是同盟
+use serde::{Serialize,Deserialize};
+use std::collections::HashMap;
+
+#[derive(Copy,Clone,Debug,Default,PartialEq,Eq,Hash,Serialize,Deserialize)]
+///两个阵营之间的关系
+pub enum Relation{
+    ///同盟，一起取得胜利
+    Allied,
+    ///中立，互不攻击，也不阻止对方获胜
+    Neutral,
+    ///敌对
+    #[default] Hostile,
+}
+
+#[derive(Clone,Debug,Default,PartialEq,Eq,Serialize,Deserialize)]
+///阵营关系表，即敌对矩阵
+pub struct FactionTable{
+    relations:HashMap<String,HashMap<String,Relation>>,
+}
+
+impl FactionTable{
+    ///所有不同阵营互相敌对的关系表
+    pub fn new()->FactionTable{
+        FactionTable::default()
+    }
+    ///设置两个阵营之间的关系，关系是对称的。同一阵营之间的关系无法修改
+    /// ```
+    /// use minidnd_eecs_havefun::{FactionTable,Relation};
+    /// let mut table=FactionTable::new();
+    /// table.set_relation("party","villagers",Relation::Allied);
+    /// table.set_relation("bandits","owlbear",Relation::Neutral);
+    /// assert_eq!(table.relation("villagers","party"),Relation::Allied);
+    /// assert!(table.is_hostile("party","bandits"));
+    /// assert!(!table.is_hostile("owlbear","bandits"));
+    /// assert!(!table.is_hostile("party","party"));
+    /// ```
+    pub fn set_relation(&mut self,faction_1:&str,faction_2:&str,relation:Relation){
+        if faction_1==faction_2 {return}
+        self.relations.entry(faction_1.to_string()).or_default().insert(faction_2.to_string(),relation);
+        self.relations.entry(faction_2.to_string()).or_default().insert(faction_1.to_string(),relation);
+    }
+    ///两个阵营之间的关系
+    pub fn relation(&self,faction_1:&str,faction_2:&str)->Relation{
+        if faction_1==faction_2 {return Relation::Allied}
+        self.relations.get(faction_1).and_then(|r|r.get(faction_2)).copied().unwrap_or_default()
+    }
+    ///两个阵营是否敌对
+    pub fn is_hostile(&self,faction_1:&str,faction_2:&str)->bool{
+        self.relation(faction_1,faction_2)==Relation::Hostile
+    }
+}
@@ -0,0 +1,37 @@
+//!专长。玩家可以在提升属性值时改为选择一个专长，见第5版玩家手册第6章
+use crate::Skill;
+use serde::{Serialize,Deserialize};
+
+#[derive(Copy,Clone,Debug,PartialEq,Eq,Hash,Serialize,Deserialize)]
+///专长
+pub enum Feat{
+    ///警觉：先攻+5，清醒时不会被突袭
+    Alert,
+    ///观察入微：被动察觉与被动调查+5
+    Observant,
+}
+
+impl Feat{
+    ///这个专长提供的先攻加值
+    pub fn initiative_bonus(&self)->i32{
+        match self{
+            Feat::Alert=>5,
+            _=>0,
+        }
+    }
+    ///这个专长提供的被动检定加值
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,Feat,Skill,DNDChecker};
+    /// let mut player=Player::new_by_default();
+    /// let before=player.passive_score(Skill::Perception);
+    /// player.feats.insert(Feat::Observant);
+    /// assert_eq!(player.passive_score(Skill::Perception),before+5);
+    /// assert_eq!(player.passive_score(Skill::Investigation)-player.skill_modifier(Skill::Investigation),15);
+    /// ```
+    pub fn passive_bonus(&self,skill:Skill)->i32{
+        match (self,skill){
+            (Feat::Observant,Skill::Perception|Skill::Investigation)=>5,
+            _=>0,
+        }
+    }
+}
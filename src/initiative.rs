@@ -0,0 +1,279 @@
+//!先攻顺序
+//!先攻是一次敏捷检定，结果较高的先行动。结果相同时敏捷值较高的先行动，仍然相同时双方投d20比大小直到分出先后。
+//![`InitiativeTracker`]按照行动顺序保存所有参与者，支持延迟行动、准备动作、战斗中加入新的参与者以及移除死亡的参与者
+use crate::{Combatant,DndError};
+use crate::combatant::dyn_rng;
+use rand::Rng;
+use serde::{Serialize,Deserialize};
+use std::collections::HashMap;
+
+#[derive(Clone,Debug,PartialEq,Eq,Serialize,Deserialize)]
+///先攻顺序中的一项
+pub struct InitiativeEntry{
+    pub name:String,
+    ///先攻检定的结果
+    pub initiative:i32,
+    ///敏捷值，先攻相同时用于决定先后
+    pub dexterity:i32,
+    ///准备的动作的触发条件，在自己的下一个回合开始时失效
+    pub readied:Option<String>,
+}
+
+#[derive(Clone,Debug,Default,PartialEq,Eq,Serialize,Deserialize)]
+///按行动顺序排列的先攻表
+pub struct InitiativeTracker{
+    ///按行动顺序排列
+    entries:Vec<InitiativeEntry>,
+    ///本轮中下一个行动的参与者在entries中的位置
+    next:usize,
+    ///当前正在行动的参与者
+    current:Option<String>,
+    round:i32,
+    ///正在延迟行动的参与者，不在行动顺序中
+    delayed:Vec<InitiativeEntry>,
+}
+
+impl InitiativeTracker{
+    ///所有参与者进行先攻检定并排序。advantage给出部分参与者额外的优劣势，会与状态带来的优劣势合并
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,Feat,InitiativeTracker,seeded_rng};
+    /// use std::collections::HashMap;
+    /// let mut alice=Player::new_by_default();
+    /// alice.ability_scores.dexterity=14;
+    /// alice.feats.insert(Feat::Alert);
+    /// let bob=Player{name:"Bob".to_string(),..Player::new_by_default()};
+    /// let combatants=HashMap::from([("Alice".to_string(),alice),("Bob".to_string(),bob)]);
+    /// let tracker=InitiativeTracker::roll(&mut seeded_rng(1),&combatants,&HashMap::from([("Bob".to_string(),1)]));
+    /// let order:Vec<&str>=tracker.iter().map(|e|e.name.as_str()).collect();
+    /// assert_eq!(order.len(),2);
+    /// let (first,second)=(tracker.entry(order[0]).unwrap(),tracker.entry(order[1]).unwrap());
+    /// assert!((first.initiative,first.dexterity)>=(second.initiative,second.dexterity));
+    /// assert!(tracker.iter().all(|e|e.name!="Alice"||e.initiative>=8));
+    /// ```
+    pub fn roll<C:Combatant,R:Rng+?Sized>(rng:&mut R,combatants:&HashMap<String,C>,advantage:&HashMap<String,i32>)->InitiativeTracker{
+        let mut tracker=InitiativeTracker::default();
+        let mut names:Vec<&String>=combatants.keys().collect();
+        names.sort();
+        for name in names{
+            let combatant=&combatants[name];
+            let initiative=combatant.initiative_roll(&mut dyn_rng(rng),advantage.get(name).copied().unwrap_or_default());
+            tracker.place(rng,InitiativeEntry{name:name.clone(),initiative,dexterity:combatant.dexterity_score(),readied:None});
+        }
+        tracker
+    }
+    ///把一项放到正确的位置，返回位置。与已有的项完全相同时进行比大小
+    fn place<R:Rng+?Sized>(&mut self,rng:&mut R,entry:InitiativeEntry)->usize{
+        let mut index=self.entries.len();
+        for (i,other) in self.entries.iter().enumerate(){
+            let ahead=match (entry.initiative,entry.dexterity).cmp(&(other.initiative,other.dexterity)){
+                std::cmp::Ordering::Greater=>true,
+                std::cmp::Ordering::Less=>false,
+                std::cmp::Ordering::Equal=>roll_off(rng),
+            };
+            if ahead {index=i;break}
+        }
+        self.entries.insert(index,entry);
+        //本轮已经过去的位置，新加入的参与者下一轮才行动
+        if index<self.next {self.next+=1;}
+        index
+    }
+    ///战斗中加入新的参与者，进行先攻检定并放到对应的位置，返回先攻检定的结果。
+    /// 如果这个位置本轮已经过去，新的参与者从下一轮开始行动
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,InitiativeTracker,seeded_rng};
+    /// use std::collections::HashMap;
+    /// let mut rng=seeded_rng(2);
+    /// let combatants=HashMap::from([("Alice".to_string(),Player::new_by_default())]);
+    /// let mut tracker=InitiativeTracker::roll(&mut rng,&combatants,&HashMap::new());
+    /// let bob=Player{name:"Bob".to_string(),..Player::new_by_default()};
+    /// tracker.insert(&mut rng,&bob,0).unwrap();
+    /// assert_eq!(tracker.len(),2);
+    /// assert!(tracker.insert(&mut rng,&bob,0).is_err());
+    /// ```
+    pub fn insert<C:Combatant+?Sized,R:Rng+?Sized>(&mut self,rng:&mut R,combatant:&C,advantage:i32)->Result<i32,DndError>{
+        let name=combatant.name();
+        if self.contains(name) {return Err(DndError::DuplicateCombatant(name.to_string()))}
+        let initiative=combatant.initiative_roll(&mut dyn_rng(rng),advantage);
+        self.place(rng,InitiativeEntry{name:name.to_string(),initiative,dexterity:combatant.dexterity_score(),readied:None});
+        Ok(initiative)
+    }
+    ///移除参与者，包括正在延迟行动的参与者
+    pub fn remove(&mut self,name:&str)->Option<InitiativeEntry>{
+        if let Some(i)=self.delayed.iter().position(|e|e.name==name) {return Some(self.delayed.remove(i))}
+        let index=self.entries.iter().position(|e|e.name==name)?;
+        if index<self.next {self.next-=1;}
+        if self.current.as_deref()==Some(name) {self.current=None;}
+        Some(self.entries.remove(index))
+    }
+    ///移除所有已经死亡的参与者，返回他们的名字。不在combatants中的参与者不受影响
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,DamageType,InitiativeTracker,seeded_rng};
+    /// use std::collections::HashMap;
+    /// let mut combatants=HashMap::from([("Alice".to_string(),Player::new_by_default()),
+    /// ("Bob".to_string(),Player{name:"Bob".to_string(),..Player::new_by_default()}),
+    /// ("Carol".to_string(),Player{name:"Carol".to_string(),..Player::new_by_default()})]);
+    /// let mut tracker=InitiativeTracker::roll(&mut seeded_rng(4),&combatants,&HashMap::new());
+    /// //Bob只是濒死，仍然需要在自己的回合进行死亡豁免
+    /// combatants.get_mut("Bob").unwrap().apply_damage(100,DamageType::Fire);
+    /// combatants.get_mut("Carol").unwrap().apply_damage(200,DamageType::Fire);
+    /// assert_eq!(tracker.remove_dead(&combatants),vec!["Carol".to_string()]);
+    /// assert!(tracker.contains("Bob"));
+    /// ```
+    pub fn remove_dead<C:Combatant>(&mut self,combatants:&HashMap<String,C>)->Vec<String>{
+        let dead:Vec<String>=self.entries.iter().chain(self.delayed.iter())
+        .filter(|e|combatants.get(&e.name).is_some_and(|c|c.is_dead()))
+        .map(|e|e.name.clone()).collect();
+        for name in &dead{
+            self.remove(name);
+        }
+        dead
+    }
+    ///结束当前回合并开始下一个参与者的回合，返回他的名字。所有人都行动后开始新的一轮。
+    /// 这个参与者之前准备的动作失效
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,InitiativeTracker,seeded_rng};
+    /// use std::collections::HashMap;
+    /// let combatants=HashMap::from([("Alice".to_string(),Player::new_by_default()),
+    /// ("Bob".to_string(),Player{name:"Bob".to_string(),..Player::new_by_default()})]);
+    /// let mut tracker=InitiativeTracker::roll(&mut seeded_rng(3),&combatants,&HashMap::new());
+    /// let order:Vec<String>=tracker.iter().map(|e|e.name.clone()).collect();
+    /// assert_eq!(tracker.next_turn(),Some(order[0].as_str()));
+    /// assert_eq!(tracker.next_turn(),Some(order[1].as_str()));
+    /// assert_eq!(tracker.round(),1);
+    /// assert_eq!(tracker.next_turn(),Some(order[0].as_str()));
+    /// assert_eq!(tracker.round(),2);
+    /// ```
+    pub fn next_turn(&mut self)->Option<&str>{
+        if self.entries.is_empty() {
+            self.current=None;
+            return None
+        }
+        if self.round==0 {self.round=1;}
+        if self.next>=self.entries.len() {
+            self.next=0;
+            self.round+=1;
+        }
+        let entry=&mut self.entries[self.next];
+        entry.readied=None;
+        self.next+=1;
+        self.current=Some(entry.name.clone());
+        self.current.as_deref()
+    }
+    ///当前正在行动的参与者
+    pub fn current(&self)->Option<&str>{
+        self.current.as_deref()
+    }
+    ///当前是第几轮，战斗开始前为0
+    pub fn round(&self)->i32{
+        self.round
+    }
+    ///当前的参与者延迟行动：离开行动顺序，之后通过[`InitiativeTracker::resume`]在任意一个回合结束后行动
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,InitiativeTracker,seeded_rng};
+    /// use std::collections::HashMap;
+    /// let combatants=HashMap::from([("Alice".to_string(),Player::new_by_default()),
+    /// ("Bob".to_string(),Player{name:"Bob".to_string(),..Player::new_by_default()}),
+    /// ("Carol".to_string(),Player{name:"Carol".to_string(),..Player::new_by_default()})]);
+    /// let mut tracker=InitiativeTracker::roll(&mut seeded_rng(5),&combatants,&HashMap::new());
+    /// let order:Vec<String>=tracker.iter().map(|e|e.name.clone()).collect();
+    /// tracker.next_turn();
+    /// tracker.delay().unwrap();
+    /// assert_eq!(tracker.delayed().count(),1);
+    /// assert_eq!(tracker.next_turn(),Some(order[1].as_str()));
+    /// //第二个人的回合结束后，第一个人行动，并且之后一直在这个位置
+    /// tracker.resume(&order[0]).unwrap();
+    /// assert_eq!(tracker.next_turn(),Some(order[0].as_str()));
+    /// assert_eq!(tracker.next_turn(),Some(order[2].as_str()));
+    /// let new_order:Vec<&str>=tracker.iter().map(|e|e.name.as_str()).collect();
+    /// assert_eq!(new_order,vec![order[1].as_str(),order[0].as_str(),order[2].as_str()]);
+    /// ```
+    pub fn delay(&mut self)->Result<(),DndError>{
+        let name=self.current.take().ok_or(DndError::NoActiveTurn)?;
+        let index=self.entries.iter().position(|e|e.name==name).ok_or(DndError::UnknownCombatant(name))?;
+        if index<self.next {self.next-=1;}
+        let entry=self.entries.remove(index);
+        self.delayed.push(entry);
+        Ok(())
+    }
+    ///延迟行动的参与者在当前回合结束后立即行动，之后的先攻与刚刚行动的参与者相同
+    pub fn resume(&mut self,name:&str)->Result<(),DndError>{
+        let index=self.delayed.iter().position(|e|e.name==name).ok_or_else(||DndError::UnknownCombatant(name.to_string()))?;
+        let mut entry=self.delayed.remove(index);
+        if let Some(previous)=self.next.checked_sub(1).and_then(|i|self.entries.get(i)) {
+            entry.initiative=previous.initiative;
+        }
+        self.entries.insert(self.next,entry);
+        Ok(())
+    }
+    ///正在延迟行动的参与者
+    pub fn delayed(&self)->impl Iterator<Item=&InitiativeEntry>{
+        self.delayed.iter()
+    }
+    ///当前的参与者准备一个动作，trigger为触发条件。准备的动作用反应执行，在自己的下一个回合开始时失效
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,InitiativeTracker,seeded_rng};
+    /// use std::collections::HashMap;
+    /// let combatants=HashMap::from([("Alice".to_string(),Player::new_by_default())]);
+    /// let mut tracker=InitiativeTracker::roll(&mut seeded_rng(6),&combatants,&HashMap::new());
+    /// assert!(tracker.ready("the door opens").is_err());
+    /// tracker.next_turn();
+    /// tracker.ready("the door opens").unwrap();
+    /// assert_eq!(tracker.trigger_readied("Alice").unwrap(),"the door opens");
+    /// assert!(tracker.trigger_readied("Alice").is_err());
+    /// ```
+    pub fn ready(&mut self,trigger:&str)->Result<(),DndError>{
+        let name=self.current.clone().ok_or(DndError::NoActiveTurn)?;
+        let entry=self.entries.iter_mut().find(|e|e.name==name).ok_or(DndError::UnknownCombatant(name))?;
+        entry.readied=Some(trigger.to_string());
+        Ok(())
+    }
+    ///触发准备的动作，返回触发条件
+    pub fn trigger_readied(&mut self,name:&str)->Result<String,DndError>{
+        let entry=self.entries.iter_mut().find(|e|e.name==name).ok_or_else(||DndError::UnknownCombatant(name.to_string()))?;
+        entry.readied.take().ok_or_else(||DndError::NothingReadied(name.to_string()))
+    }
+    ///按名字查找，不包括正在延迟行动的参与者
+    pub fn entry(&self,name:&str)->Option<&InitiativeEntry>{
+        self.entries.iter().find(|e|e.name==name)
+    }
+    ///是否包含某个参与者，包括正在延迟行动的参与者
+    pub fn contains(&self,name:&str)->bool{
+        self.entries.iter().chain(self.delayed.iter()).any(|e|e.name==name)
+    }
+    ///按行动顺序遍历
+    pub fn iter(&self)->std::slice::Iter<'_,InitiativeEntry>{
+        self.entries.iter()
+    }
+    ///从下一个行动的参与者开始，按顺序列出接下来一整轮的行动顺序
+    pub fn upcoming(&self)->impl Iterator<Item=&InitiativeEntry>{
+        let next=self.next.min(self.entries.len());
+        self.entries[next..].iter().chain(self.entries[..next].iter())
+    }
+    ///行动顺序中的参与者数量，不包括正在延迟行动的参与者
+    pub fn len(&self)->usize{
+        self.entries.len()
+    }
+    pub fn is_empty(&self)->bool{
+        self.entries.is_empty()
+    }
+    ///每个参与者的名次，1最先行动，用于[`CombatEncounter::new`](crate::CombatEncounter::new)
+    pub fn ranks(&self)->HashMap<String,i32>{
+        self.entries.iter().enumerate().map(|(i,e)|(e.name.clone(),i as i32+1)).collect()
+    }
+}
+
+impl<'a> IntoIterator for &'a InitiativeTracker{
+    type Item=&'a InitiativeEntry;
+    type IntoIter=std::slice::Iter<'a,InitiativeEntry>;
+    fn into_iter(self)->Self::IntoIter{
+        self.entries.iter()
+    }
+}
+
+///两个先攻与敏捷值都相同的参与者各投一次d20，直到分出大小。返回新来的一方是否在前
+fn roll_off<R:Rng+?Sized>(rng:&mut R)->bool{
+    loop{
+        let (mine,theirs)=(rng.random_range(1..=20),rng.random_range(1..=20));
+        if mine!=theirs {return mine>theirs}
+    }
+}
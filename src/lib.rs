@@ -1,25 +1,76 @@
 use rand::prelude::*;
-use serde_json;
 use std::collections::{HashMap,HashSet};
 use std::cmp;
 use serde::{Serialize, Deserialize};
+pub mod error;
+pub use error::DndError;
+pub mod dice;
+pub use dice::{DiceExpr,DieRoll,RollResult};
+pub mod probability;
+pub use probability::{CheckProbability,Distribution};
+pub mod skill;
+pub use skill::{Skill,Proficiency};
+pub mod battle_map;
+pub use battle_map::{AreaShape,BattleMap,Formation,Spacing,Terrain};
+pub mod encounter;
+pub use encounter::{CombatEncounter,EncounterState,Outcome,Turn,TurnBudget};
+pub mod damage;
+pub use damage::DamageDefenses;
+pub mod attack;
+pub use attack::{attack,attack_with,AttackReport};
+pub mod condition;
+pub use condition::{ActiveCondition,Condition,ConditionDuration,ConditionTracker,Conditions};
+pub mod death;
+pub use death::{DeathSaveOutcome,DeathSaves,LifeState};
+pub mod monster;
+pub use monster::{ChallengeRating,CreatureType,Monster,MonsterAction,Senses,Size};
+pub mod combatant;
+pub use combatant::Combatant;
+pub mod faction;
+pub use faction::{FactionTable,Relation};
+pub mod feat;
+pub use feat::Feat;
+pub mod initiative;
+pub use initiative::{InitiativeEntry,InitiativeTracker};
+pub mod surprise;
+pub use surprise::{determine_surprise_with,SurpriseStatus};
+pub mod class;
+pub use class::{AbilityScoreImprovement,ArmorCategory,CharacterClass,HitPointGain,LevelUp,SpellcastingProgression,ABILITY_SCORE_IMPROVEMENT};
+pub mod multiclass;
+pub use multiclass::spell_slots_for;
+pub mod origin;
+pub use origin::{Background,Grants,Race};
+pub mod builder;
+pub use builder::{point_buy_cost,roll_ability_scores,CharacterBuilder,POINT_BUY_BUDGET,STANDARD_ARRAY};
+pub mod spell;
+pub use spell::{cantrip_dice,CastingTime,Components,School,Spell,SpellDuration,SpellRange,SpellReport,SpellResolution};
+pub mod spell_catalog;
+pub use spell_catalog::SpellCatalog;
+pub mod concentration;
+pub use concentration::{clean_up_concentration,concentration_dc,Concentration,ConcentrationTracker};
+pub mod rest;
+pub use rest::{ClassResource,Rest,RestKind,RestReport};
+pub mod armor;
+pub use armor::{armor_catalog,Armor};
+use condition::combine_advantage;
 //use serde_json::Result as SerdeResult;
 ///这是DND模拟器的库。六种属性的定义如下，来自第5版规则书。
-///力量Strength，体能的量化	
-///敏捷Dexterity，灵活度的量化	
-///体质Constitution，耐受力的量化	
-///智力Intelligence，记忆与思维能力的量化	
-///感知Wisdom，直觉与感受能力的量化	
+///力量Strength，体能的量化
+///敏捷Dexterity，灵活度的量化
+///体质Constitution，耐受力的量化
+///智力Intelligence，记忆与思维能力的量化
+///感知Wisdom，直觉与感受能力的量化
 ///魅力Charisma，个性气质的量化
 /// 关于数值以及判定方法，请参考第5版规则书
 /// 战斗步骤摘自规则书，如下：
-/// 战斗步骤Combat	Step	by	Step	
-///1. 判定突袭Determine	surprise。DM 判定战斗遭遇参与者中是否有人遭受突袭。	
-///2. 决定位置 Establish	positions。DM 决定好所有角色和怪物的位置。
-/// 即DM以冒险者的行进方向，及其在房间或其他地点的具体位置为基础，再确定其敌对者在哪（距离的远近和具体位置）。
-/// 3. 骰先攻Roll	initiative。战斗遭遇的每位参与者投先攻骰，以决定战斗回合的顺序。
-/// 4.执行回合Take	turns。战斗的每位参与者按照先攻序列进行其战斗回合。	
-/// 5. 开始新一轮Begin	the	next	round。当所有战斗参与者完成其回合后，该轮结束。重复步骤4直至战斗结束。	
+/// 战斗步骤Combat Step by Step
+/// 1. 判定突袭Determine surprise。DM 判定战斗遭遇参与者中是否有人遭受突袭。
+/// 2. 决定位置 Establish positions。DM 决定好所有角色和怪物的位置。
+///    即DM以冒险者的行进方向，及其在房间或其他地点的具体位置为基础，再确定其敌对者在哪（距离的远近和具体位置）。
+/// 3. 骰先攻Roll initiative。战斗遭遇的每位参与者投先攻骰，以决定战斗回合的顺序。
+/// 4. 执行回合Take turns。战斗的每位参与者按照先攻序列进行其战斗回合。
+/// 5. 开始新一轮Begin the next round。当所有战斗参与者完成其回合后，该轮结束。重复步骤4直至战斗结束。
+///
 #[derive(Clone,Debug,Default,Serialize,Deserialize)]
 ///玩家的各种信息，注意由于rust的安全性，没有采用getter和setter方法，而是直接让字段可见
 pub struct Player{
@@ -34,16 +85,25 @@ pub struct Player{
     pub armor:i32,
     pub exp:i32,
     pub hp:i32,
-    ///通过玩家熟练项的名字（包括技能以及工具）查找对应的熟练项以及对应的熟练度加值
-    /// 并且玩家职业本身带来的熟练项也直接写到对应哈希表中。哈希表加值为0含义是加上对应等级的加值
-    /// 根据规则，一种技能/工具只能提供一个熟练度加值，并且我们不会添加新的属性，因此直接写死6个hashmap，下同
-    /// ac即为AbilityCheck属性检定
-    pub skills_for_ac_strength:HashSet<String>,
-    pub skills_for_ac_dexterity:HashSet<String>,
-    pub skills_for_ac_constitution:HashSet<String>,
-    pub skills_for_ac_intelligence:HashSet<String>,
-    pub skills_for_ac_wisdom:HashSet<String>,
-    pub skills_for_ac_charisma:HashSet<String>,
+    ///生命值上限。旧存档没有这个字段，读档时取当时的生命值
+    #[serde(default)]
+    pub max_hp:i32,
+    ///清醒、濒死、稳定或死亡
+    #[serde(default)]
+    pub life_state:LifeState,
+    ///濒死时累计的死亡豁免
+    #[serde(default)]
+    pub death_saves:DeathSaves,
+    ///每种技能的熟练程度，没有出现的技能即不熟练。技能检定使用技能对应的关键属性
+    /// 旧存档中的skills_for_ac_*在读档时转换到这里，见[`SaveLoad::load_players`]
+    #[serde(default)]
+    pub skills:HashMap<Skill,Proficiency>,
+    ///万事通Jack of All Trades：所有不熟练的属性检定（包括技能检定）加上一半熟练加值
+    #[serde(default)]
+    pub jack_of_all_trades:bool,
+    ///工具熟练项
+    #[serde(default)]
+    pub tool_proficiencies:HashSet<String>,
     ///通过玩家熟练项的名字（包括技能以及工具）查找对应的熟练项以及对应的豁免加值
     /// st即为SavingThrow豁免检定
     pub skills_for_st_strength:HashSet<String>,
@@ -54,9 +114,90 @@ pub struct Player{
     pub skills_for_st_charisma:HashSet<String>,
     // ///人物拥有的武器以及魔法
     pub weapons:HashMap<String,Weapon>,
+    ///熟练的武器类别，攻击时加上熟练加值
+    #[serde(default)]
+    pub weapon_proficiencies:HashSet<WeaponCategory>,
+    ///熟练的具体武器，例如矮人战斗训练的战斧，按武器名字记录
+    #[serde(default)]
+    pub named_weapon_proficiencies:HashSet<String>,
+    ///伤害抗性、易伤与免疫
+    #[serde(default)]
+    pub damage_defenses:DamageDefenses,
+    ///当前的状态，例如中毒、倒地、力竭，见[`condition`]
+    #[serde(default)]
+    pub conditions:Vec<ActiveCondition>,
+    ///所属阵营的名字
+    #[serde(default)]
+    pub faction:String,
+    ///拥有的专长
+    #[serde(default)]
+    pub feats:HashSet<Feat>,
+    ///所有职业以及各自的等级，第一个是最先选择的职业，见[`Player::choose_class`]与[`Player::multiclass`]。
+    /// 角色等级为所有职业等级之和，可能低于经验值对应的等级，见[`Player::level_up`]
+    #[serde(default)]
+    pub classes:Vec<(CharacterClass,i32)>,
+    ///获得的职业特性
+    #[serde(default)]
+    pub features:Vec<String>,
+    ///熟练的护甲类别
+    #[serde(default)]
+    pub armor_proficiencies:HashSet<ArmorCategory>,
+    ///体型，由种族决定
+    #[serde(default)]
+    pub size:Size,
+    ///特殊感官，例如种族的黑暗视觉
+    #[serde(default)]
+    pub senses:Senses,
+    ///会说的语言
+    #[serde(default)]
+    pub languages:HashSet<String>,
+    ///种族，见[`Player::set_race`]
+    #[serde(default)]
+    pub race:Option<Race>,
+    ///种族实际带来的改变，用于撤销
+    #[serde(default)]
+    pub race_grants:Grants,
+    ///背景，见[`Player::set_background`]
+    #[serde(default)]
+    pub background:Option<Background>,
+    ///背景实际带来的改变，用于撤销
+    #[serde(default)]
+    pub background_grants:Grants,
+    ///已经消耗的1到9环法术位，下标0为1环
+    #[serde(default)]
+    pub expended_spell_slots:[i32;9],
+    ///已经消耗的契约魔法法术位
+    #[serde(default)]
+    pub expended_pact_magic_slots:i32,
+    ///正在专注的效果
+    #[serde(default)]
+    pub concentration:Option<Concentration>,
+    ///已经结束、还没有清理受影响者的专注效果
+    #[serde(default)]
+    pub broken_concentration:Vec<Concentration>,
+    ///专注时受到的、还没有进行专注检定的伤害
+    #[serde(default)]
+    pub pending_concentration_checks:Vec<i32>,
+    ///临时生命值，受到伤害时先扣除，见[`Player::gain_temp_hp`]
+    #[serde(default)]
+    pub temp_hp:i32,
+    ///每个职业已经花费的生命骰，长休时恢复一部分，见[`rest`]
+    #[serde(default)]
+    pub spent_hit_dice:HashMap<CharacterClass,i32>,
+    ///已经使用的职业资源次数，按资源名字记录
+    #[serde(default)]
+    pub spent_resources:HashMap<String,i32>,
+    ///身上的护甲，见[`Player::equip_armor`]
+    #[serde(default)]
+    pub worn_armor:Option<Armor>,
+    #[serde(default)]
+    pub shield:Option<Armor>,
+    ///天生护甲的基础护甲等级，没有穿甲时加上敏捷调整值
+    #[serde(default)]
+    pub natural_armor:Option<i32>,
     //pub tools:HashMap<String,Tool>,
 }
-#[derive(Copy,Clone,Debug,Default)]
+#[derive(Copy,Clone,Debug,Default,PartialEq,Eq,Hash,Serialize,Deserialize)]
 ///各种属性
 pub enum Abilities{
     #[default] Strength,
@@ -91,6 +232,49 @@ impl Default for AbilityScores{
     }
 }
 
+impl AbilityScores{
+    ///取出某种属性的属性值
+    pub fn get(&self,ability:Abilities)->i32{
+        match ability{
+            Abilities::Strength=>self.strength,
+            Abilities::Dexterity=>self.dexterity,
+            Abilities::Constitution=>self.constitution,
+            Abilities::Intelligence=>self.intelligence,
+            Abilities::Wisdom=>self.wisdom,
+            Abilities::Charisma=>self.charisma,
+        }
+    }
+    ///某种属性的属性值的可变引用
+    pub fn get_mut(&mut self,ability:Abilities)->&mut i32{
+        match ability{
+            Abilities::Strength=>&mut self.strength,
+            Abilities::Dexterity=>&mut self.dexterity,
+            Abilities::Constitution=>&mut self.constitution,
+            Abilities::Intelligence=>&mut self.intelligence,
+            Abilities::Wisdom=>&mut self.wisdom,
+            Abilities::Charisma=>&mut self.charisma,
+        }
+    }
+    ///属性值对应的调整值，即属性值减10后除以2并向下取整
+    /// ```
+    /// use minidnd_eecs_havefun::AbilityScores;
+    /// let scores=AbilityScores{strength:9,dexterity:3,constitution:10,intelligence:11,wisdom:1,charisma:20};
+    /// let modifiers=scores.to_modifiers();
+    /// assert_eq!((modifiers.strength,modifiers.dexterity,modifiers.constitution),(-1,-4,0));
+    /// assert_eq!((modifiers.intelligence,modifiers.wisdom,modifiers.charisma),(0,-5,5));
+    /// ```
+    pub fn to_modifiers(&self)->Modifiers{
+        Modifiers{
+            strength:(self.strength-10).div_euclid(2),
+            dexterity:(self.dexterity-10).div_euclid(2),
+            constitution:(self.constitution-10).div_euclid(2),
+            intelligence:(self.intelligence-10).div_euclid(2),
+            wisdom:(self.wisdom-10).div_euclid(2),
+            charisma:(self.charisma-10).div_euclid(2),
+        }
+    }
+}
+
 #[derive(Copy,Clone,Debug,Default)]
 ///调整值
 pub struct Modifiers{
@@ -101,6 +285,19 @@ pub struct Modifiers{
     pub wisdom:i32,
     pub charisma:i32,
 }
+impl Modifiers{
+    ///取出某种属性对应的值
+    pub fn get(&self,ability:Abilities)->i32{
+        match ability{
+            Abilities::Strength=>self.strength,
+            Abilities::Dexterity=>self.dexterity,
+            Abilities::Constitution=>self.constitution,
+            Abilities::Intelligence=>self.intelligence,
+            Abilities::Wisdom=>self.wisdom,
+            Abilities::Charisma=>self.charisma,
+        }
+    }
+}
 
 #[derive(Copy,Clone,Debug,Serialize,Deserialize)]
 ///默认的三种货币外加银金币和铂金币(1pp=10gp=20ep=100sp=1000cp)
@@ -115,7 +312,7 @@ pub struct Coins{
 pub struct HashedPlayers{
     pub hashed_players:HashMap<String,Player>,
 }
-#[derive(Copy,Clone,Debug,Serialize,Deserialize)]
+#[derive(Copy,Clone,Debug,PartialEq,Eq,Serialize,Deserialize)]
 pub enum CoinType{
     Gold,Silver,Copper,Ep,Pp
 }
@@ -136,36 +333,69 @@ impl Default for Coins{
 pub struct Weapon{
     pub name:String,
     pub category:WeaponCategory,
-    ///武器伤害(x,y)代表xdy，例如（1,6）代表1d6
-    pub damage:(i32,i32), 
+    ///武器伤害骰子表达式，例如1d6。旧存档中的(x,y)会被读取为xdy
+    pub damage:DiceExpr,
     pub damage_type:DamageType,
     pub price:(CoinType,i32),
+    ///魔法武器造成的物理伤害可以无视非魔法抗性与免疫
+    #[serde(default)]
+    pub magical:bool,
 }
-#[derive(Clone,Debug,Serialize,Deserialize)]
+#[derive(Copy,Clone,Debug,PartialEq,Eq,Hash,Serialize,Deserialize)]
 pub enum WeaponCategory{
     SimpleMelee,
     SimpleRanged,
     MartialMelee,
     MartialRanged,
 }
-#[derive(Clone,Debug,Serialize,Deserialize)]
+impl WeaponCategory{
+    ///近战武器使用力量，远程武器使用敏捷
+    pub fn attack_ability(&self)->Abilities{
+        match self{
+            WeaponCategory::SimpleMelee|WeaponCategory::MartialMelee=>Abilities::Strength,
+            WeaponCategory::SimpleRanged|WeaponCategory::MartialRanged=>Abilities::Dexterity,
+        }
+    }
+    pub fn is_melee(&self)->bool{
+        matches!(self,WeaponCategory::SimpleMelee|WeaponCategory::MartialMelee)
+    }
+}
+#[derive(Copy,Clone,Debug,PartialEq,Eq,Hash,Serialize,Deserialize)]
+///伤害类型，来自第5版规则书
 pub enum DamageType{
     Blugeon,//钝击
     Slash,//劈砍
     Pierce,//穿刺
+    Acid,//强酸
+    Cold,//寒冷
+    Fire,//火焰
+    Force,//力场
+    Lightning,//闪电
+    Necrotic,//黯蚀
+    Poison,//毒素
+    Psychic,//心灵
+    Radiant,//光耀
+    Thunder,//雷鸣
 }
-#[derive(Copy,Clone,Debug)]
-#[allow(dead_code)]
-///用于战斗时玩家的位置，并且战斗时玩家默认初始位置是50,50
+#[derive(Copy,Clone,Debug,PartialEq,Eq,Hash,Serialize,Deserialize)]
+///用于战斗时玩家的位置，即战斗地图上方格的坐标，x向东增长，y向南增长。
+/// 默认初始位置是50,50，实际战斗中由[`BattleMap::establish_positions`]决定
 pub struct Position{
-    x:i32,y:i32
+    pub x:i32,pub y:i32
 }
 impl Default for Position{
     fn default() -> Self {
         Self { x: 50, y: 50 }
     }
 }
+impl Position{
+    pub fn new(x:i32,y:i32)->Position{
+        Position{x,y}
+    }
+}
 ///检定所需要的所有函数
+///所有投掷都对`rand::Rng`泛型，带`_with`后缀的函数使用调用者给出的随机数生成器，
+///不带后缀的函数使用`rand::rng()`。给定同一个种子（见[`seeded_rng`]）就能复现整个检定过程。
 pub trait DNDChecker{
     ///通过属性值产生调整值
     fn ability_scores_to_modifiers(&self)->Modifiers;
@@ -173,178 +403,333 @@ pub trait DNDChecker{
     fn proficiency_modifiers(&self)->Modifiers;
     ///获得全部可能的豁免加值
     fn saving_throw_modifiers(&self)->Modifiers;
+    ///当前的状态，检定时自动合并这些状态带来的优劣势。默认没有任何状态
+    fn active_conditions(&self)->&[ActiveCondition]{
+        &[]
+    }
+    ///身上的护甲给涉及ability（以及技能skill）的检定带来的优劣势，与状态带来的合并。默认没有影响
+    fn armor_advantage(&self,_ability:Abilities,_skill:Option<Skill>)->i32{
+        0
+    }
     ///考虑d的大小的投掷 例如3D20就用这个骰子重复投3次
-    fn dice(rng:&mut ThreadRng,upperbound:i32)->Result<i32,&'static str>;
+    ///单次投掷的上界范围应该是2-100
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,DNDChecker,DndError,seeded_rng};
+    /// let mut rng=seeded_rng(7);
+    /// assert!(matches!(Player::dice(&mut rng,101),Err(DndError::UpperboundOutOfRange(101))));
+    /// assert!((1..=6).contains(&Player::dice(&mut rng,6).unwrap()));
+    /// ```
+    fn dice<R:Rng+?Sized>(rng:&mut R,upperbound:i32)->Result<i32,DndError>{
+        if !(2..=100).contains(&upperbound){Err(DndError::UpperboundOutOfRange(upperbound))}
+        else {Ok(rng.random_range(1..upperbound+1))}
+    }
     ///考虑d的大小，次数以及优劣势的投掷
-    fn dice_complex(rng:&mut ThreadRng,upperbound:i32,count:i32,advantage:i32)->i32;
+    ///用户不应该自行调用这个函数
+    ///注意用户给出的上界有误时这个函数将上界变为20.count有误时变为1.
+    ///当要投的骰子数量是1，优势代表着再投掷一次，并且取两者较高值。劣势代表着再投掷一次，取两者较低值。
+    ///只有骰子数量是1时优劣势才会生效
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,DNDChecker,seeded_rng};
+    /// let first=Player::dice_complex(&mut seeded_rng(42),6,3,0);
+    /// let second=Player::dice_complex(&mut seeded_rng(42),6,3,0);
+    /// assert_eq!(first,second);
+    /// assert!((3..=18).contains(&first));
+    /// ```
+    fn dice_complex<R:Rng+?Sized>(rng:&mut R,upperbound:i32,count:i32,advantage:i32)->i32{
+        let roll=|rng:&mut R|Self::dice(rng,upperbound).unwrap_or_else(|_|Self::dice(rng,20).unwrap());
+        if count<=1||count>10 {
+            let dice_1=roll(rng);
+            if advantage==0 {dice_1}
+            else{
+                let dice_2=roll(rng);
+                if advantage==1 {cmp::max(dice_1,dice_2)}
+                else {cmp::min(dice_1,dice_2)}
+            }
+        }
+        else{
+            (0..count).map(|_|roll(rng)).sum()
+        }
+    }
     ///属性检定，大于难度等级DifficultyClass即为成功，或者在需要时返回检定值
     /// 例如对于一次被动察觉检定，我们就需要返回检定值，并在此基础上进行进一步操作。
-    fn ability_check(&self,checker:Abilities,dc:i32,count:i32,advantage:i32)->Result<DNDResult,&'static str>;
-    fn ability_check_stat(&self,checker:Abilities,count:i32,advantage:i32)->Result<i32,&'static str>;
+    ///合法的难度范围是1到50，合法的d骰数量是1-10,合法的优劣势范围是-1到1（-1代表劣势，0代表没有优势或劣势，1代表优势）
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,Abilities,DNDChecker,DndError};
+    /// assert!(matches!(Player::new_by_default().ability_check(Abilities::Strength,51,1,0),Err(DndError::DcOutOfRange(51))));
+    /// assert!(matches!(Player::new_by_default().ability_check(Abilities::Strength,15,11,0),Err(DndError::CountOutOfRange(11))));
+    /// assert!(matches!(Player::new_by_default().ability_check(Abilities::Strength,15,1,-2),Err(DndError::AdvantageOutOfRange(-2))));
+    /// ```
+    /// 默认情况下所有加值都被触发，但最多触发一个
+    /// 本函数先调用不带DifficultyClass的函数获得结果再进行对比。
+    fn ability_check(&self,checker:Abilities,dc:i32,count:i32,advantage:i32)->Result<DNDResult,DndError>{
+        self.ability_check_with(&mut rand::rng(),checker,dc,count,advantage)
+    }
+    ///使用给定随机数生成器的属性检定
+    fn ability_check_with<R:Rng+?Sized>(&self,rng:&mut R,checker:Abilities,dc:i32,count:i32,advantage:i32)->Result<DNDResult,DndError>{
+        check_dc(dc)?;
+        let score=self.ability_check_stat_with(rng,checker,count,advantage)?;
+        Ok(compare_with_dc(score,dc))
+    }
+    /// 合法的d骰数量是1-10,合法的优劣势范围是-1到1（-1代表劣势，0代表没有优势或劣势，1代表优势）
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,Abilities,DNDChecker,DndError};
+    /// assert!(matches!(Player::new_by_default().ability_check_stat(Abilities::Strength,11,0),Err(DndError::CountOutOfRange(11))));
+    /// assert!(matches!(Player::new_by_default().ability_check_stat(Abilities::Strength,1,-2),Err(DndError::AdvantageOutOfRange(-2))));
+    /// ```
+    fn ability_check_stat(&self,checker:Abilities,count:i32,advantage:i32)->Result<i32,DndError>{
+        self.ability_check_stat_with(&mut rand::rng(),checker,count,advantage)
+    }
+    ///使用给定随机数生成器的属性检定，返回检定值。
+    /// advantage会与状态带来的优劣势合并，例如中毒时即使给出优势，最终也是普通投掷
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,Abilities,DNDChecker,Condition,ConditionDuration,ConditionTracker,seeded_rng};
+    /// let mut my_player=Player::new_by_default();
+    /// let first=my_player.ability_check_stat_with(&mut seeded_rng(2024),Abilities::Dexterity,1,1).unwrap();
+    /// let second=my_player.ability_check_stat_with(&mut seeded_rng(2024),Abilities::Dexterity,1,1).unwrap();
+    /// assert_eq!(first,second);
+    /// my_player.add_condition(Condition::Poisoned,ConditionDuration::Rounds(3),None);
+    /// let poisoned=my_player.ability_check_stat_with(&mut seeded_rng(2024),Abilities::Dexterity,1,1).unwrap();
+    /// let plain=Player::new_by_default().ability_check_stat_with(&mut seeded_rng(2024),Abilities::Dexterity,1,0).unwrap();
+    /// assert_eq!(poisoned,plain);
+    /// ```
+    fn ability_check_stat_with<R:Rng+?Sized>(&self,rng:&mut R,checker:Abilities,count:i32,advantage:i32)->Result<i32,DndError>{
+        check_count_and_advantage(count,advantage)?;
+        let advantage=combine_advantage(&[advantage,self.active_conditions().ability_check_advantage(checker),self.armor_advantage(checker,None)]);
+        let modifier=self.ability_scores_to_modifiers();
+        let proficiency=self.proficiency_modifiers();
+        let dice_result=Self::dice_complex(rng,20,count,advantage);
+        Ok(dice_result+modifier.get(checker)+proficiency.get(checker))
+    }
+    ///用骰子表达式代替d20的属性检定，例如"1d20adv+1d4"（神导术）。表达式已经写明了优劣势，因此不再合并状态的影响
+    fn ability_check_expr_with<R:Rng+?Sized>(&self,rng:&mut R,checker:Abilities,dc:i32,expr:&DiceExpr)->Result<DNDResult,DndError>{
+        check_dc(dc)?;
+        Ok(compare_with_dc(self.ability_check_stat_expr_with(rng,checker,expr),dc))
+    }
+    ///用骰子表达式代替d20的属性检定，返回检定值
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,Abilities,DNDChecker,DiceExpr,seeded_rng};
+    /// let my_player=Player::new_by_default();
+    /// let expr=DiceExpr::parse("1d20adv+1d4").unwrap();
+    /// let stat=my_player.ability_check_stat_expr_with(&mut seeded_rng(5),Abilities::Strength,&expr);
+    /// assert!((4..=26).contains(&stat));
+    /// ```
+    fn ability_check_stat_expr_with<R:Rng+?Sized>(&self,rng:&mut R,checker:Abilities,expr:&DiceExpr)->i32{
+        expr.roll(rng).total+self.ability_scores_to_modifiers().get(checker)+self.proficiency_modifiers().get(checker)
+    }
+    ///技能检定时的熟练加值（考虑半熟练与专精），不包括属性调整值
+    fn skill_proficiency_modifier(&self,skill:Skill)->i32;
+    ///技能检定的固定加值，即关键属性调整值加上熟练加值
+    fn skill_modifier(&self,skill:Skill)->i32{
+        self.ability_scores_to_modifiers().get(skill.ability())+self.skill_proficiency_modifier(skill)
+    }
+    ///被动检定值，即10加上技能检定的固定加值，例如被动察觉
+    fn passive_score(&self,skill:Skill)->i32{
+        10+self.skill_modifier(skill)
+    }
+    ///技能检定，合法范围与属性检定相同
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,Skill,Proficiency,DNDChecker};
+    /// let mut my_player=Player::new_by_default();
+    /// //默认敏捷14，1级熟练加值+2，专精即+4
+    /// my_player.skills.insert(Skill::Stealth,Proficiency::Expertise);
+    /// assert_eq!(my_player.skill_modifier(Skill::Stealth),6);
+    /// assert_eq!(my_player.skill_modifier(Skill::Acrobatics),2);
+    /// my_player.jack_of_all_trades=true;
+    /// assert_eq!(my_player.skill_modifier(Skill::Acrobatics),3);
+    /// assert!(my_player.skill_check(Skill::Stealth,51,1,0).is_err());
+    /// ```
+    fn skill_check(&self,skill:Skill,dc:i32,count:i32,advantage:i32)->Result<DNDResult,DndError>{
+        self.skill_check_with(&mut rand::rng(),skill,dc,count,advantage)
+    }
+    ///使用给定随机数生成器的技能检定
+    fn skill_check_with<R:Rng+?Sized>(&self,rng:&mut R,skill:Skill,dc:i32,count:i32,advantage:i32)->Result<DNDResult,DndError>{
+        check_dc(dc)?;
+        let score=self.skill_check_stat_with(rng,skill,count,advantage)?;
+        Ok(compare_with_dc(score,dc))
+    }
+    ///技能检定，返回检定值
+    fn skill_check_stat(&self,skill:Skill,count:i32,advantage:i32)->Result<i32,DndError>{
+        self.skill_check_stat_with(&mut rand::rng(),skill,count,advantage)
+    }
+    ///使用给定随机数生成器的技能检定，返回检定值
+    fn skill_check_stat_with<R:Rng+?Sized>(&self,rng:&mut R,skill:Skill,count:i32,advantage:i32)->Result<i32,DndError>{
+        check_count_and_advantage(count,advantage)?;
+        let advantage=combine_advantage(&[advantage,self.active_conditions().ability_check_advantage(skill.ability()),self.armor_advantage(skill.ability(),Some(skill))]);
+        let dice_result=Self::dice_complex(rng,20,count,advantage);
+        Ok(dice_result+self.skill_modifier(skill))
+    }
     ///豁免检定，大于难度等级DifficultyClass即为成功
-    fn saving_throw(&self,checker:Abilities,dc:i32,count:i32,advantage:i32)->Result<DNDResult,&'static str>;
-    fn saving_throw_stat(&self,checker:Abilities,count:i32,advantage:i32)->Result<i32,&'static str>;
+    ///只需要返回鉴定结果的豁免鉴定函数只是调用了带具体投掷结果的鉴定函数
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,Abilities,DNDChecker,DNDResult};
+    /// let my_player=Player::new_by_default();
+    /// let dnd_result=
+    /// my_player.saving_throw(Abilities::Strength,12,1,1)
+    /// .unwrap_or_else(|e|{println!("Please check again,as {}",e);DNDResult::Tie});
+    /// ```
+    fn saving_throw(&self,checker:Abilities,dc:i32,count:i32,advantage:i32)->Result<DNDResult,DndError>{
+        self.saving_throw_with(&mut rand::rng(),checker,dc,count,advantage)
+    }
+    ///使用给定随机数生成器的豁免检定。麻痹、石化、震慑、昏迷时力量与敏捷豁免自动失败
+    fn saving_throw_with<R:Rng+?Sized>(&self,rng:&mut R,checker:Abilities,dc:i32,count:i32,advantage:i32)->Result<DNDResult,DndError>{
+        check_dc(dc)?;
+        check_count_and_advantage(count,advantage)?;
+        if self.active_conditions().auto_fails_saving_throw(checker) {return Ok(DNDResult::Lose)}
+        let score=self.saving_throw_stat_with(rng,checker,count,advantage)?;
+        Ok(compare_with_dc(score,dc))
+    }
+    /// 需要具体结果的鉴定函数。
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,Abilities,DNDChecker,DNDResult};
+    /// let mut my_player=Player::new_by_default();
+    /// my_player.skills_for_st_strength.insert("skill_for_test".to_string());
+    /// let dnd_stat=my_player.saving_throw_stat(Abilities::Strength,1,1)
+    /// .unwrap_or_else(|e|{println!("Please check again,as {}",e);0});
+    /// assert!(dnd_stat>=5,"dnd_stat={}",dnd_stat);
+    /// ```
+    fn saving_throw_stat(&self,checker:Abilities,count:i32,advantage:i32)->Result<i32,DndError>{
+        self.saving_throw_stat_with(&mut rand::rng(),checker,count,advantage)
+    }
+    ///使用给定随机数生成器的豁免检定，返回检定值。advantage会与状态带来的优劣势合并
+    fn saving_throw_stat_with<R:Rng+?Sized>(&self,rng:&mut R,checker:Abilities,count:i32,advantage:i32)->Result<i32,DndError>{
+        check_count_and_advantage(count,advantage)?;
+        let advantage=combine_advantage(&[advantage,self.active_conditions().saving_throw_advantage(checker),self.armor_advantage(checker,None)]);
+        let modifier=self.ability_scores_to_modifiers();
+        let saving_throw=self.saving_throw_modifiers();
+        let dice_result=Self::dice_complex(rng,20,count,advantage);
+        Ok(dice_result+modifier.get(checker)+saving_throw.get(checker))
+    }
+    ///用骰子表达式代替d20的豁免检定，自动失败的规则依然适用
+    fn saving_throw_expr_with<R:Rng+?Sized>(&self,rng:&mut R,checker:Abilities,dc:i32,expr:&DiceExpr)->Result<DNDResult,DndError>{
+        check_dc(dc)?;
+        if self.active_conditions().auto_fails_saving_throw(checker) {return Ok(DNDResult::Lose)}
+        Ok(compare_with_dc(self.saving_throw_stat_expr_with(rng,checker,expr),dc))
+    }
+    ///用骰子表达式代替d20的豁免检定，返回检定值
+    fn saving_throw_stat_expr_with<R:Rng+?Sized>(&self,rng:&mut R,checker:Abilities,expr:&DiceExpr)->i32{
+        expr.roll(rng).total+self.ability_scores_to_modifiers().get(checker)+self.saving_throw_modifiers().get(checker)
+    }
+}
+///用固定种子构造随机数生成器，同一个种子下的所有投掷结果完全相同
+/// ```
+/// use minidnd_eecs_havefun::{Player,Abilities,DNDChecker,seeded_rng};
+/// let my_player=Player::new_by_default();
+/// let mut rng_1=seeded_rng(1);
+/// let mut rng_2=seeded_rng(1);
+/// for _ in 0..10{
+///     assert_eq!(my_player.saving_throw_stat_with(&mut rng_1,Abilities::Wisdom,1,0).unwrap(),
+///     my_player.saving_throw_stat_with(&mut rng_2,Abilities::Wisdom,1,0).unwrap());
+/// }
+/// ```
+pub fn seeded_rng(seed:u64)->StdRng{
+    StdRng::seed_from_u64(seed)
+}
+fn check_dc(dc:i32)->Result<(),DndError>{
+    if !(1..=50).contains(&dc) {Err(DndError::DcOutOfRange(dc))}
+    else {Ok(())}
+}
+fn check_count_and_advantage(count:i32,advantage:i32)->Result<(),DndError>{
+    if !(1..=10).contains(&count) {Err(DndError::CountOutOfRange(count))}
+    else if !(-1..=1).contains(&advantage) {Err(DndError::AdvantageOutOfRange(advantage))}
+    else {Ok(())}
+}
+fn compare_with_dc(score:i32,dc:i32)->DNDResult{
+    if score>=dc {DNDResult::Win}
+    else {DNDResult::Lose}
 }
 
 ///战斗所需要的5个步骤
-///一场战斗中只能存在两个阵营。因此任何角色必须在开始或者中途加入某一方。
+///一场战斗中可以存在任意多个阵营，每个参与者的阵营取自[`Combatant::faction`]，阵营之间的关系由[`FactionTable`]决定，
+///战斗中可以通过[`CombatEncounter::switch_faction`]改变阵营。
+///参与者可以是任何[`Combatant`]，不同类型的参与者混在一起时使用`Box<dyn Combatant>`，例如玩家对[`Monster`]
 pub trait Combat where Self:DNDChecker+InformationGetter+InformationGetter{
-    ///判定突袭，只在第一轮。对于所有选择隐匿的人，
-    ///DM将躲藏方的隐匿结果即敏捷与另一方每个人的感知结果进行对抗。某个人结果为Lose说明被突袭成功。
-    /// player_1,player_2分别代表两组战斗者（一场战斗只能有两组战斗者）。
-    /// hide_1和hide_2分别代表每个人是(true)否(false)选择隐匿
-    /// 返回的元组，第一个位置表示第一组战斗者，第二个位置表示第二组战斗者
-    /// 每个表项中i32值为0表不存在对应给出名字的玩家，为1表示被突袭，为2表示没有被突袭
-    fn determine_surprise(players_1:&HashMap<String,Player>,players_2:&HashMap<String,Player>,
-    hide_1:&HashMap<String,bool>,hide_2:&HashMap<String,bool>)->
-    (HashMap<String,i32>,HashMap<String,i32>);
-    ///所有参与者投掷先攻骰子（敏捷检定）确定顺序。返回的结果数值较小的代表顺序在前。
-    fn roll_initiative(players_1:&HashMap<String,Player>,players_2:&HashMap<String,Player>)->
-    (HashMap<String,i32>,HashMap<String,i32>);
-    //DM决定所有玩家以及怪物的位置,todo
-    //fn establish_positions(players_1:&HashMap<String,Player>,players_2:&HashMap<String,Player>)->HashMap<String,Position>;
-    //进行一轮，回合数即turn;todo
-    //fn take_turns(players_1:&HashMap<String,Player>,players_2:&HashMap<String,Player>,turn:i32)->();
+    ///判定突袭，只在第一轮。所有选择隐匿的人进行敏捷（隐匿）检定，同一阵营的隐匿者作为群体检定，
+    ///与每个敌对参与者的被动察觉进行对抗。没有察觉到任何威胁的参与者被突袭，规则见[`determine_surprise_with`]。
+    /// hide代表每个人是(true)否(false)选择隐匿，返回每个参与者的突袭状态
+    fn determine_surprise<C:Combatant>(combatants:&HashMap<String,C>,relations:&FactionTable,
+    hide:&HashMap<String,bool>)->Result<HashMap<String,SurpriseStatus>,DndError>;
+    ///所有参与者进行先攻检定（敏捷检定）确定顺序，规则见[`InitiativeTracker`]。
+    /// 返回的先攻表按行动顺序排列，[`InitiativeTracker::ranks`]给出[`CombatEncounter::new`]需要的名次
+    fn roll_initiative<C:Combatant>(combatants:&HashMap<String,C>)->InitiativeTracker;
+    ///DM决定所有玩家以及怪物的位置。各阵营按阵营名字排序，阵营内按名字顺序依次放置，第一个是领头者，
+    /// 规则见[`BattleMap::establish_faction_positions`]。返回每个参与者所在的方格
+    fn establish_positions<C:Combatant>(combatants:&HashMap<String,C>,
+    map:&mut BattleMap,formation:Formation,spacing:Spacing)->Result<HashMap<String,Position>,DndError>;
+    ///进行一轮：按先攻顺序依次开始每个参与者的回合并调用act，DM在act中通过encounter结算行动。
+    /// 返回这一轮结束后的战斗状态，重复调用即步骤5开始新一轮
+    fn take_turns<C:Combatant,F:FnMut(&mut CombatEncounter<C>,&Turn)>(encounter:&mut CombatEncounter<C>,act:F)->EncounterState;
 }
 ///查找或转换一些信息的函数
 pub trait InformationGetter{
     ///经验值转化为等级，具体规则在代码中写出
     /// 注意在其他关联函数中exp小于0很可能导致panic
-    fn exp_to_level(exp:i32)->Result<i32,&'static str>;
+    fn exp_to_level(exp:i32)->Result<i32,DndError>;
     ///将玩家等级或者怪物的挑战等级转化为熟练加值。Option返回None表示level不在范围
     fn level_to_proficiency_modifier(level:i32)->Option<i32>;
     ///将现有货币价值用(用户所需要类型货币数量，铜币数量）表示。如果转换为铜币，元组的第二位为0
     /// 1pp=10gp=20ep=100sp=1000cp
     /// ```
     /// use minidnd_eecs_havefun::{Player,Coins,CoinType,InformationGetter};
-    /// assert_eq!(<Player as InformationGetter>::coins_to_coin(&Coins{gold:100,silver:0,copper:101,ep:1,pp:1},CoinType::Gold),Ok((111,51)));
-    /// assert_eq!(<Player as InformationGetter>::coins_to_coin(&Coins{gold:1,silver:1,copper:1,ep:1,pp:1},CoinType::Copper),Ok((1161,0)));
+    /// assert_eq!(<Player as InformationGetter>::coins_to_coin(&Coins{gold:100,silver:0,copper:101,ep:1,pp:1},CoinType::Gold).ok(),Some((111,51)));
+    /// assert_eq!(<Player as InformationGetter>::coins_to_coin(&Coins{gold:1,silver:1,copper:1,ep:1,pp:1},CoinType::Copper).ok(),Some((1161,0)));
     /// ```
-    fn coins_to_coin(coins:&Coins,coin_type:CoinType)->Result<(i32,i32),&'static str>;
+    fn coins_to_coin(coins:&Coins,coin_type:CoinType)->Result<(i32,i32),DndError>;
 }
 ///用于读档、存档的函数
 pub trait SaveLoad<T,F>{
-    fn save_players(t:&mut F,file_name:&str)->Result<(),&'static str>;
-    fn load_players(file_name:&str)->Result<Box<HashedPlayers>,&'static str>;
+    fn save_players(t:&mut F,file_name:&str)->Result<(),DndError>;
+    fn load_players(file_name:&str)->Result<Box<HashedPlayers>,DndError>;
 }
 impl Player{
+    ///默认玩家，没有穿甲，护甲等级为10加敏捷调整值
     pub fn new_by_default()->Player{
-        Player { name: "Alice".to_string(),walking_speed:30,flying_speed:0,
-        armor:8,exp:0,hp:100,..Default::default() }
+        let mut player=Player { name: "Alice".to_string(),walking_speed:30,flying_speed:0,
+        exp:0,hp:100,max_hp:100,..Default::default() };
+        player.update_armor_class();
+        player
     }
+    #[allow(clippy::too_many_arguments)]
     pub fn new_by_stats(name:String,ability_scores:AbilityScores,coins:Coins,
         walking_speed:i32,flying_speed:i32,armor:i32,exp:i32,hp:i32)->Player{
-        Player { name, ability_scores, coins,walking_speed,flying_speed,armor,exp,hp,..Default::default()}
+        Player { name, ability_scores, coins,walking_speed,flying_speed,armor,exp,hp,max_hp:hp,..Default::default()}
     }
 }
 
-impl DNDChecker for Player{
-    ///合法的难度范围是1到50，合法的d骰数量是1-10,合法的优劣势范围是-1到1（-1代表劣势，0代表没有优势或劣势，1代表优势）
-    /// ```
-    /// use minidnd_eecs_havefun::{Player,Abilities,DNDChecker};
-    /// assert_eq!(Player::new_by_default().ability_check(Abilities::Strength,51,1,0),Err("dc is not in the range of 1 to 50\n"));
-    /// assert_eq!(Player::new_by_default().ability_check(Abilities::Strength,15,11,0),Err("count is not in the range of 1 to 10\n"));
-    /// assert_eq!(Player::new_by_default().ability_check(Abilities::Strength,15,1,-2),Err("advantage is not in the range of -1 to 1\n"));
-    /// ```
-    /// 默认情况下所有加值都被触发，但最多触发一个
-    /// 本函数先调用不带DifficultyClass的函数获得结果再进行对比。
-    fn ability_check(&self,checker:Abilities,dc:i32,count:i32,advantage:i32)->Result<DNDResult,&'static str> {
-        if dc<1||dc>50 {Err("dc is not in the range of 1 to 50\n")}
-        else if count<1||count>10 {Err("count is not in the range of 1 to 10\n")}
-        else if advantage<(-1)||advantage>1{Err("advantage is not in the range of -1 to 1\n")}
-        else{
-            match self.ability_check_stat(checker,count, advantage){
-                Ok(score)=>{
-                    if score>=dc {Ok(DNDResult::Win)}
-                    else if score==dc {Ok(DNDResult::Tie)}
-                    else {Ok(DNDResult::Lose)}
-                },
-                Err(e)=>Err(e),
-            }
-        }
-    }
-    /// 合法的d骰数量是1-10,合法的优劣势范围是-1到1（-1代表劣势，0代表没有优势或劣势，1代表优势）
-    /// ```
-    /// use minidnd_eecs_havefun::{Player,Abilities,DNDChecker};
-    /// assert_eq!(Player::new_by_default().ability_check_stat(Abilities::Strength,11,0),Err("count is not in the range of 1 to 10\n"));
-    /// assert_eq!(Player::new_by_default().ability_check_stat(Abilities::Strength,1,-2),Err("advantage is not in the range of -1 to 1\n"));
-    /// ```
-    fn ability_check_stat(&self,checker:Abilities,count:i32,advantage:i32)->Result<i32,&'static str> {
-        if count<1||count>10 {return Err("count is not in the range of 1 to 10\n")}
-        else if advantage<(-1)||advantage>1{return Err("advantage is not in the range of -1 to 1\n")}
-        else{
-            let modifier=self.ability_scores_to_modifiers();
-            let proficiency=self.proficiency_modifiers();
-            let mut rng=rand::rng();
-            let dice_result=Player::dice_complex(&mut rng,20,count,advantage);
-            match checker{
-                Abilities::Charisma=> {
-                    let total_score=dice_result+modifier.charisma+proficiency.charisma;
-                    Ok(total_score)
-                },
-                Abilities::Constitution=> {
-                    let total_score=dice_result+modifier.constitution+proficiency.constitution;
-                    Ok(total_score)
-                },
-                Abilities::Dexterity=> {
-                    let total_score=dice_result+modifier.dexterity+proficiency.dexterity;
-                    Ok(total_score)
-                },
-                Abilities::Intelligence=> {
-                    let total_score=dice_result+modifier.intelligence+proficiency.intelligence;
-                    Ok(total_score)
-                },
-                Abilities::Strength=> {
-                    let total_score=dice_result+modifier.strength+proficiency.strength;
-                    Ok(total_score)
-                },
-                Abilities::Wisdom=> {
-                    let total_score=dice_result+modifier.wisdom+proficiency.wisdom;
-                    Ok(total_score)
-                },
-            }
-        }
+impl Player{
+    ///当前等级对应的熟练加值。有职业时取决于角色等级（所有职业等级之和），否则取决于经验值对应的等级
+    pub fn proficiency_bonus(&self)->i32{
+        let level=if self.classes.is_empty() {self.experience_level()} else {self.character_level()};
+        <Self as InformationGetter>::level_to_proficiency_modifier(level).unwrap_or(2)
     }
+}
+impl DNDChecker for Player{
     fn ability_scores_to_modifiers(&self)->Modifiers{
-        Modifiers{
-            strength:(self.ability_scores.strength-10)/2,
-            dexterity:(self.ability_scores.dexterity-10)/2,
-            constitution:(self.ability_scores.constitution-10)/2,
-            intelligence:(self.ability_scores.intelligence-10)/2,
-            wisdom:(self.ability_scores.wisdom-10)/2,
-            charisma:(self.ability_scores.charisma-10)/2,
-        }
+        self.ability_scores.to_modifiers()
     }
+    ///不针对任何技能的属性检定没有熟练加值，只有万事通提供一半熟练加值
     fn proficiency_modifiers(&self)->Modifiers {
-        let mut max_strength=0;
-        let mut max_dexterity=0;
-        let mut max_constitution=0;
-        let mut max_intelligence=0;
-        let mut max_wisdom=0;
-        let mut max_charisma=0;
-        let level:i32=<Self as InformationGetter>::exp_to_level(self.exp).unwrap();
-        let proficiency_modifier:i32=<Self as InformationGetter>::level_to_proficiency_modifier(level).unwrap();
-        if !self.skills_for_ac_strength.is_empty(){
-            max_strength=proficiency_modifier;
-        }
-        if !self.skills_for_ac_dexterity.is_empty(){
-            max_dexterity=proficiency_modifier;
-        }
-        if !self.skills_for_ac_constitution.is_empty(){
-            max_constitution=proficiency_modifier;
-        }
-        if !self.skills_for_ac_intelligence.is_empty(){
-            max_intelligence=proficiency_modifier;
-        }
-        if !self.skills_for_ac_wisdom.is_empty(){
-            max_wisdom=proficiency_modifier;
-        }
-        if !self.skills_for_ac_charisma.is_empty(){
-            max_charisma=proficiency_modifier;
+        let half=if self.jack_of_all_trades {Proficiency::Half.bonus(self.proficiency_bonus())} else {0};
+        Modifiers { strength: half, dexterity: half,
+             constitution: half, intelligence: half,
+             wisdom: half, charisma: half, }
+    }
+    ///被动检定值，加上观察入微等专长的加值
+    fn passive_score(&self,skill:Skill)->i32{
+        10+self.skill_modifier(skill)+self.feats.iter().map(|f|f.passive_bonus(skill)).sum::<i32>()
+    }
+    fn active_conditions(&self)->&[ActiveCondition]{
+        &self.conditions
+    }
+    fn armor_advantage(&self,ability:Abilities,skill:Option<Skill>)->i32{
+        self.armor_disadvantage(ability,skill)
+    }
+    fn skill_proficiency_modifier(&self,skill:Skill)->i32{
+        let proficiency_modifier=self.proficiency_bonus();
+        match self.skills.get(&skill){
+            Some(proficiency)=>proficiency.bonus(proficiency_modifier),
+            None if self.jack_of_all_trades=>Proficiency::Half.bonus(proficiency_modifier),
+            None=>0,
         }
-        Modifiers { strength: max_strength, dexterity: max_dexterity,
-             constitution: max_constitution, intelligence: max_intelligence,
-             wisdom: max_wisdom, charisma: max_charisma, }
     }
     fn saving_throw_modifiers(&self)->Modifiers {
         let mut max_strength=0;
@@ -353,8 +738,7 @@ impl DNDChecker for Player{
         let mut max_intelligence=0;
         let mut max_wisdom=0;
         let mut max_charisma=0;
-        let level:i32=<Self as InformationGetter>::exp_to_level(self.exp).unwrap();
-        let proficiency_modifier:i32=<Self as InformationGetter>::level_to_proficiency_modifier(level).unwrap();
+        let proficiency_modifier:i32=self.proficiency_bonus();
         if !self.skills_for_st_strength.is_empty(){
             max_strength=proficiency_modifier;
         }
@@ -377,117 +761,9 @@ impl DNDChecker for Player{
              constitution: max_constitution, intelligence: max_intelligence,
              wisdom: max_wisdom, charisma: max_charisma, }
     }
-    ///单次投掷的上界范围应该是2-100
-    /// ```
-    /// use minidnd_eecs_havefun::{Player,DNDChecker,DNDResult};
-    /// let mut rng=rand::rng();
-    /// assert_eq!(Player::dice(&mut rng,101),Err("upperbound is not in the range of 2-100"));
-    /// ```
-    fn dice(rng:&mut ThreadRng,upperbound:i32)->Result<i32,&'static str> {
-        if upperbound<2||upperbound>100{return Err("upperbound is not in the range of 2-100")}
-        else {Ok(rng.random_range(1..upperbound+1))}
-    }
-    ///用户不应该自行调用这个函数
-    ///注意用户给出的上界有误时这个函数将上界变为20.count有误时变为1.
-    ///当要投的骰子数量是1，优势代表着再投掷一次，并且取两者较高值。劣势代表着再投掷一次，取两者较低值。
-    ///只有骰子数量是1时优劣势才会生效
-    /// ```
-    /// use minidnd_eecs_havefun::{Player,DNDChecker,DNDResult};
-    /// let mut rng=rand::rng();
-    /// let dice_result=Player::dice_complex(&mut rng,20,1,1);
-    /// ```
-    fn dice_complex(rng:&mut ThreadRng,upperbound:i32,count:i32,advantage:i32)->i32 {
-        if count<=1||count>10 {
-            let dice_1=Player::dice(rng, upperbound).unwrap_or(Player::dice(rng, 20).unwrap());
-            if advantage==0 {dice_1}
-            else{
-            let dice_2=Player::dice(rng, upperbound).unwrap_or(Player::dice(rng, 20).unwrap());
-            if advantage==1 { cmp::max(dice_1,dice_2)}
-            else {cmp::min(dice_1,dice_2)}
-            }
-        }
-        else{
-            let mut sum=0;
-            let mut cnt=count;
-            while cnt>=0{
-                cnt-=1;
-                let dice_1=Player::dice(rng, upperbound).unwrap_or(Player::dice(rng, 20).unwrap());
-                sum+=dice_1;
-            }
-            sum
-        }
-    }
-    ///只需要返回鉴定结果的豁免鉴定函数只是调用了带具体投掷结果的鉴定函数
-    /// ```
-    /// use minidnd_eecs_havefun::{Player,Abilities,DNDChecker,DNDResult};
-    /// let my_player=Player::new_by_default();
-    /// let dnd_result=
-    /// my_player.saving_throw(Abilities::Strength,12,1,1)
-    /// .unwrap_or_else(|e|{println!("Please check again,as {}",e);DNDResult::Tie});
-    /// ```
-    fn saving_throw(&self,checker:Abilities,dc:i32,count:i32,advantage:i32)->Result<DNDResult,&'static str> {
-        if dc<1||dc>50 {Err("dc is not in the range of 1 to 50\n")}
-        else if count<1||count>10 {Err("count is not in the range of 1 to 10\n")}
-        else if advantage<(-1)||advantage>1{Err("advantage is not in the range of -1 to 1\n")}
-        else{
-            match self.saving_throw_stat(checker,count,advantage){
-                Ok(score)=>{
-                    if score>=dc {Ok(DNDResult::Win)}
-                    else if score==dc {Ok(DNDResult::Tie)}
-                    else {Ok(DNDResult::Lose)}
-                },
-                Err(e)=>Err(e),
-            }
-        }
-    }
-    /// 需要具体结果的鉴定函数。
-    /// ```
-    /// use minidnd_eecs_havefun::{Player,Abilities,DNDChecker,DNDResult};
-    /// let mut my_player=Player::new_by_default();
-    /// my_player.skills_for_st_strength.insert("skill_for_test".to_string(), 25);
-    /// let dnd_stat=my_player.saving_throw_stat(Abilities::Strength,1,1)
-    /// .unwrap_or_else(|e|{println!("Please check again,as {}",e);0});
-    /// assert!(dnd_stat>=25,"dnd_stat={}",dnd_stat);
-    /// ```
-    fn saving_throw_stat(&self,checker:Abilities,count:i32,advantage:i32)->Result<i32,&'static str> {
-        if count<1||count>10 {Err("count is not in the range of 1 to 10\n")}
-        else if advantage<(-1)||advantage>1{Err("advantage is not in the range of -1 to 1\n")}
-        else {
-            let modifier=self.ability_scores_to_modifiers();
-            let saving_throw=self.saving_throw_modifiers();
-            let mut rng=rand::rng();
-            let dice_result=Player::dice_complex(&mut rng,20,count,advantage);
-            match checker{
-                Abilities::Charisma => {
-                    let total_score = dice_result + modifier.charisma + saving_throw.charisma;
-                    Ok(total_score)
-                },
-                Abilities::Constitution => {
-                    let total_score = dice_result + modifier.constitution + saving_throw.constitution;
-                    Ok(total_score)
-                },
-                Abilities::Dexterity => {
-                    let total_score = dice_result + modifier.dexterity + saving_throw.dexterity;
-                    Ok(total_score)
-                },
-                Abilities::Intelligence => {
-                    let total_score = dice_result + modifier.intelligence + saving_throw.intelligence;
-                    Ok(total_score)
-                },
-                Abilities::Strength => {
-                    let total_score = dice_result + modifier.strength + saving_throw.strength;
-                    Ok(total_score)
-                },
-                Abilities::Wisdom => {
-                    let total_score = dice_result + modifier.wisdom + saving_throw.wisdom;
-                    Ok(total_score)
-                },
-            }
-        }
-    }
 }
 impl InformationGetter for Player{
-    fn exp_to_level(exp:i32)->Result<i32,&'static str> {
+    fn exp_to_level(exp:i32)->Result<i32,DndError> {
         match exp{
             0..300=>Ok(1),
             300..900=>Ok(2),
@@ -509,7 +785,7 @@ impl InformationGetter for Player{
             265000..305000=>Ok(18),
             305000..355000=>Ok(19),
             355000_i32..=i32::MAX=>Ok(20),
-            _=>Err("your exp is not valid.")
+            _=>Err(DndError::InvalidExperience(exp))
         }
     }
     fn level_to_proficiency_modifier(level:i32)->Option<i32> {
@@ -526,11 +802,11 @@ impl InformationGetter for Player{
         }
     }
     ///先把所有货币用铜币计数再用对应货币表示
-    fn coins_to_coin(coins:&Coins,coin_type:CoinType)->Result<(i32,i32),&'static str> {
-        let f=|c:&Coins|(c.gold*100+c.silver*10+c.copper+c.ep*50+c.pp*1000);
+    fn coins_to_coin(coins:&Coins,coin_type:CoinType)->Result<(i32,i32),DndError> {
+        let f=|c:&Coins|c.gold*100+c.silver*10+c.copper+c.ep*50+c.pp*1000;
         let g=f(coins);
-        if g<0 {return Err("Given Coins are wrong as the sum of them is negative")}
-        let h=|n:i32,t:i32|((n/t,n-t*(n/t)));
+        if g<0 {return Err(DndError::NegativeCoins(g))}
+        let h=|n:i32,t:i32|(n/t,n-t*(n/t));
         match coin_type{
             CoinType::Gold=>Ok(h(g,100)),
             CoinType::Silver=>Ok(h(g,10)),
@@ -541,112 +817,144 @@ impl InformationGetter for Player{
     }
 }
 impl SaveLoad<Player,HashedPlayers> for Player {
-    fn load_players(file_name:&str)->Result<Box<HashedPlayers>,&'static str> {
+    ///既能读取`save_players`写出的玩家表，也能读取带hashed_players字段的旧格式。
+    /// 旧存档中skills_for_ac_*里能识别为技能的名字转换为对应技能的熟练，其余的作为工具熟练项
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,HashedPlayers,SaveLoad,Skill,Proficiency,DndError};
+    /// let file=std::env::temp_dir().join("minidnd_legacy_save.json");
+    /// std::fs::write(&file,r#"{"hashed_players":{"Bob":{"name":"Bob",
+    /// "ability_scores":{"strength":15,"dexterity":14,"constitution":13,"intelligence":12,"wisdom":10,"charisma":8},
+    /// "coins":{"gold":10,"silver":50,"copper":100,"ep":0,"pp":0},
+    /// "walking_speed":30,"flying_speed":0,"armor":8,"exp":0,"hp":100,
+    /// "skills_for_ac_strength":[],"skills_for_ac_dexterity":["Stealth","thieves' tools"],
+    /// "skills_for_ac_constitution":[],"skills_for_ac_intelligence":[],"skills_for_ac_wisdom":["察觉"],
+    /// "skills_for_ac_charisma":[],"skills_for_st_strength":[],"skills_for_st_dexterity":[],
+    /// "skills_for_st_constitution":[],"skills_for_st_intelligence":[],"skills_for_st_wisdom":[],
+    /// "skills_for_st_charisma":[],"weapons":{}}}}"#).unwrap();
+    /// let players=<Player as SaveLoad<Player,HashedPlayers>>::load_players(file.to_str().unwrap()).unwrap();
+    /// let bob=&players.hashed_players["Bob"];
+    /// assert_eq!(bob.skills.get(&Skill::Stealth),Some(&Proficiency::Proficient));
+    /// assert_eq!(bob.skills.get(&Skill::Perception),Some(&Proficiency::Proficient));
+    /// assert!(bob.tool_proficiencies.contains("thieves' tools"));
+    /// assert_eq!(bob.max_hp,100);
+    /// //失败时可以匹配错误类型，并通过source取得底层错误
+    /// let missing=<Player as SaveLoad<Player,HashedPlayers>>::load_players("/nonexistent/minidnd.json");
+    /// assert!(matches!(missing,Err(DndError::Io(_))));
+    /// assert!(std::error::Error::source(&missing.unwrap_err()).is_some());
+    /// ```
+    fn load_players(file_name:&str)->Result<Box<HashedPlayers>,DndError> {
         use std::fs::File;
         use std::io::{BufReader};
-        let file=File::open(file_name).map_err(|_|"Failed to open file")?;
+        let file=File::open(file_name)?;
         let reader = BufReader::new(file);
-        let mut players:Box<HashedPlayers>=Box::new(
-        HashedPlayers{hashed_players:HashMap::<String,Player>::new()});
-        *players=serde_json::from_reader(reader).unwrap_or_default();
+        let mut value:serde_json::Value=serde_json::from_reader(reader)?;
+        let mut hashed_players=match value.get_mut("hashed_players"){
+            Some(inner)=>inner.take(),
+            None=>value,
+        };
+        if let Some(players)=hashed_players.as_object_mut(){
+            players.values_mut().for_each(migrate_legacy_skills);
+            players.values_mut().for_each(migrate_max_hp);
+        }
+        let players:Box<HashedPlayers>=Box::new(
+        HashedPlayers{hashed_players:serde_json::from_value(hashed_players)?});
         Ok(players)
     }
-    fn save_players(players:&mut HashedPlayers,file_name:&str)->Result<(),&'static str> {
+    fn save_players(players:&mut HashedPlayers,file_name:&str)->Result<(),DndError> {
         use std::fs::File;
         use std::io::{Write,BufWriter};
-        let file=File::create(file_name).map_err(|_|"Failed to create file")?;
+        let file=File::create(file_name)?;
         let mut writer=BufWriter::new(file);
-        match serde_json::to_string(&players.hashed_players){
-                Ok(player_str)=>writer.write_all(player_str.as_bytes()).map_err(|_|"Failed to write player data\n")?,
-                Err(_)=>return Err("Failed to serialize player\n")
-        }
-        writer.flush().map_err(|_|"Failed to flush writer\n")?;
+        let player_str=serde_json::to_string(&players.hashed_players)?;
+        writer.write_all(player_str.as_bytes())?;
+        writer.flush()?;
         Ok(())
     }
 }
-impl Combat for Player {
-    fn determine_surprise(players_1:&HashMap<String,Player>,players_2:&HashMap<String,Player>,
-    hide_1:&HashMap<String,bool>,hide_2:&HashMap<String,bool>)->
-    (HashMap<String,i32>,HashMap<String,i32>){
-        //wisdom_1和wisdom_2用于存储players_1和players_2中所有player的感知固定值（基准10+能力调整值+熟练加值），注意不是检定值
-        let mut wisdom_1:HashMap<String,i32>=HashMap::new();
-        let mut wisdom_2:HashMap<String,i32>=HashMap::new();
-        //考虑优势时这个值才有意义
-        //let tmp_modifier:i32=10;
-        for (str,player) in players_1{
-            wisdom_1.insert(str.clone(),player.ability_scores_to_modifiers().wisdom+player.proficiency_modifiers().wisdom+10);
-        }
-        for (str,player) in players_2{
-            wisdom_2.insert(str.clone(),player.ability_scores_to_modifiers().wisdom+player.proficiency_modifiers().wisdom+10);
-        }
-        let mut ret_players_1:HashMap<String,i32>=HashMap::new();
-        let mut ret_players_2:HashMap<String,i32>=HashMap::new();
-        //对于每个阵营中需要执行隐匿的玩家，如果该玩家不在该阵营中，在对应阵营的返回哈希表中添加表象表示不存在;
-        //如果存在，先计算该玩家的魅力检定值
-        //对于每个敌方阵营中的玩家，依次用魅力检定值与对方阵营的感知固定值进行对抗，平局时算突袭未成功。
-        for (str,_) in hide_1{
-            match players_1.get(str){
-                None=>{ret_players_1.insert(str.clone(), 0);}
-                Some(s)=>{
-                    let s_tmp_charisma=s.ability_check_stat(Abilities::Charisma, 1, 0).unwrap()
-                +(s.ability_scores_to_modifiers().charisma)+(s.proficiency_modifiers().charisma);
-                    for (str,_) in players_2{
-                    let wisdom=*wisdom_1.get(str).unwrap();
-                    if wisdom >=s_tmp_charisma{
-                    ret_players_2.insert(str.clone(),2);
-                    }
-                    else {ret_players_2.insert(str.clone(),1);}
-                }
-            }
+///旧存档没有生命值上限，取当时的生命值
+fn migrate_max_hp(player:&mut serde_json::Value){
+    let Some(player)=player.as_object_mut() else {return};
+    if player.get("max_hp").and_then(|v|v.as_i64()).unwrap_or(0)>0 {return}
+    let hp=player.get("hp").cloned().unwrap_or(serde_json::Value::from(0));
+    player.insert("max_hp".to_string(),hp);
+}
+///旧存档中按属性划分的六个熟练项集合
+const LEGACY_SKILL_FIELDS:[&str;6]=["skills_for_ac_strength","skills_for_ac_dexterity","skills_for_ac_constitution",
+"skills_for_ac_intelligence","skills_for_ac_wisdom","skills_for_ac_charisma"];
+fn migrate_legacy_skills(player:&mut serde_json::Value){
+    let Some(player)=player.as_object_mut() else {return};
+    let mut skills=serde_json::Map::new();
+    let mut tools=Vec::new();
+    for field in LEGACY_SKILL_FIELDS{
+        let Some(serde_json::Value::Array(names))=player.remove(field) else {continue};
+        for name in names.iter().filter_map(|n|n.as_str()){
+            match name.parse::<Skill>(){
+                Ok(skill)=>{skills.insert(skill.to_string(),serde_json::json!(Proficiency::Proficient));},
+                Err(_)=>tools.push(serde_json::Value::from(name)),
             }
         }
-        for (str,_) in hide_2{
-            match players_1.get(str){
-                None=>{ret_players_2.insert(str.clone(), 0);}
-                Some(s)=>{
-                    let s_tmp_charisma=s.ability_check_stat(Abilities::Charisma, 1, 0).unwrap()
-                +s.ability_scores_to_modifiers().charisma+s.proficiency_modifiers().charisma;
-                    for (str,_) in players_1{
-                    let wisdom=*wisdom_1.get(str).unwrap();
-                    if wisdom >=s_tmp_charisma{
-                    ret_players_1.insert(str.clone(),2);
-                    }
-                    else {ret_players_1.insert(str.clone(),1);}
-                }
-            }
+    }
+    if !skills.is_empty(){
+        let entry=player.entry("skills").or_insert_with(||serde_json::Value::Object(serde_json::Map::new()));
+        if let Some(existing)=entry.as_object_mut(){
+            for (skill,proficiency) in skills{
+                existing.entry(skill).or_insert(proficiency);
             }
         }
-        (ret_players_1,ret_players_2)
-    }
-    //not done yet
-    /*fn establish_positions(players_1:&HashMap<String,Player>,players_2:&HashMap<String,Player>)->HashMap<String,Position> {
-        HashMap::<String,Position>::new()
-    }*/
-    fn roll_initiative(players_1:&HashMap<String,Player>,players_2:&HashMap<String,Player>)->
-    (HashMap<String,i32>,HashMap<String,i32>) {
-        let mut ret_hash_1=HashMap::<String,i32>::new();
-        let mut ret_hash_2=HashMap::<String,i32>::new();
-        let total_cnt:usize=players_1.len()+players_2.len();
-        let mut my_vec:Vec::<(String,i32)>=Vec::with_capacity(total_cnt);
-        for(str,player)in players_1{
-            my_vec.push((str.clone(),player.ability_check_stat(Abilities::Charisma, 1,0).unwrap()));
-        }
-        for(str,player)in players_2{
-            my_vec.push((str.clone(),player.ability_check_stat(Abilities::Charisma, 1,0).unwrap()));
+    }
+    if !tools.is_empty(){
+        let entry=player.entry("tool_proficiencies").or_insert_with(||serde_json::Value::Array(Vec::new()));
+        if let Some(existing)=entry.as_array_mut(){
+            existing.extend(tools);
         }
-        //std sort默认小到大，相等元素保序
-        my_vec.sort();
-        let mut total_cnt_2:i32=TryInto::<i32>::try_into(total_cnt).unwrap();
-        for(str,_)in my_vec{
-            if players_1.contains_key(&str){
-                ret_hash_1.insert(str, total_cnt_2);
-            }
-            else {
-                ret_hash_2.insert(str, total_cnt_2);
+    }
+}
+impl Combat for Player {
+    fn determine_surprise<C:Combatant>(combatants:&HashMap<String,C>,relations:&FactionTable,
+    hide:&HashMap<String,bool>)->Result<HashMap<String,SurpriseStatus>,DndError>{
+        determine_surprise_with(&mut rand::rng(),combatants,relations,hide)
+    }
+    fn establish_positions<C:Combatant>(combatants:&HashMap<String,C>,
+    map:&mut BattleMap,formation:Formation,spacing:Spacing)->Result<HashMap<String,Position>,DndError>{
+        let mut factions:Vec<(&str,&String)>=combatants.iter().map(|(name,c)|(c.faction(),name)).collect();
+        factions.sort();
+        let mut groups:Vec<Vec<String>>=Vec::new();
+        let mut last:Option<&str>=None;
+        for (faction,name) in factions{
+            match groups.last_mut(){
+                Some(group) if last==Some(faction)=>group.push(name.clone()),
+                _=>groups.push(vec![name.clone()]),
             }
-            total_cnt_2-=1;
+            last=Some(faction);
+        }
+        map.establish_faction_positions(&groups,formation,spacing)?;
+        Ok(groups.into_iter().flatten().filter_map(|name|map.positions.get(&name).map(|p|(name,*p))).collect())
+    }
+    fn roll_initiative<C:Combatant>(combatants:&HashMap<String,C>)->InitiativeTracker{
+        InitiativeTracker::roll(&mut rand::rng(),combatants,&HashMap::new())
+    }
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,Combat,CombatEncounter,EncounterState,FactionTable,Outcome};
+    /// use std::collections::HashMap;
+    /// let mut combatants=HashMap::new();
+    /// combatants.insert("Alice".to_string(),Player{faction:"heroes".to_string(),..Player::new_by_default()});
+    /// combatants.insert("Goblin".to_string(),Player{hp:7,faction:"goblins".to_string(),..Player::new_by_default()});
+    /// let relations=FactionTable::new();
+    /// let surprise=Player::determine_surprise(&combatants,&relations,&HashMap::new()).unwrap();
+    /// let initiative=Player::roll_initiative(&combatants);
+    /// let mut encounter=CombatEncounter::new(combatants,relations,&surprise,&initiative.ranks()).unwrap();
+    /// let mut turns=Vec::new();
+    /// let state=Player::take_turns(&mut encounter,|encounter,turn|{
+    ///     turns.push(turn.name.clone());
+    ///     if turn.name=="Alice" {encounter.combatant_mut("Goblin").unwrap().hp-=7;}
+    /// });
+    /// assert!(turns.contains(&"Alice".to_string()));
+    /// assert_eq!(state,EncounterState::Ended(Outcome::Victory(vec!["heroes".to_string()])));
+    /// ```
+    fn take_turns<C:Combatant,F:FnMut(&mut CombatEncounter<C>,&Turn)>(encounter:&mut CombatEncounter<C>,mut act:F)->EncounterState{
+        while let Some(turn)=encounter.next_turn_in_round(){
+            act(encounter,&turn);
         }
-        (ret_hash_1,ret_hash_2)
+        encounter.check_victory()
     }
-    //fn take_turns(players_1:&HashMap<String,Player>,players_2:&HashMap<String,Player>,turn:i32)->()
 }
@@ -0,0 +1,398 @@
+//!怪物与NPC的数据块Stat Block，格式参照怪物图鉴
+//![`Monster`]实现了与[`Player`]相同的[`DNDChecker`]、[`InformationGetter`]以及[`Combat`]，
+//!因此在判定突袭、投先攻时可以和玩家混在一起。怪物的熟练加值由挑战等级决定，没有死亡豁免，生命值降到0即死亡
+use crate::{check_count_and_advantage,Abilities,AbilityScores,ActiveCondition,AttackReport,BattleMap,Coins,CoinType,Combat,Combatant,Concentration,FactionTable,InitiativeTracker,SurpriseStatus,
+CombatEncounter,DamageDefenses,DamageType,DiceExpr,DNDChecker,DndError,EncounterState,Formation,InformationGetter,Modifiers,
+Player,Position,Proficiency,Skill,Spacing,Turn};
+use crate::attack::{resolve_attack,AttackProfile};
+use crate::condition::Conditions;
+use rand::Rng;
+use serde::{Serialize,Deserialize};
+use std::collections::{HashMap,HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Copy,Clone,Debug,Default,PartialEq,Eq,Hash,PartialOrd,Ord,Serialize,Deserialize)]
+#[serde(try_from="String",into="String")]
+///挑战等级CR，除了0到30的整数之外还有1/8、1/4、1/2三个分数等级。序列化为"1/4"这样的字符串
+pub enum ChallengeRating{
+    #[default] Zero,
+    Eighth,
+    Quarter,
+    Half,
+    ///1到30
+    Whole(i32),
+}
+
+impl ChallengeRating{
+    ///击败该挑战等级的怪物获得的经验值
+    /// ```
+    /// use minidnd_eecs_havefun::ChallengeRating;
+    /// assert_eq!(ChallengeRating::Quarter.xp(),50);
+    /// assert_eq!(ChallengeRating::Whole(5).xp(),1800);
+    /// assert_eq!("1/8".parse::<ChallengeRating>().unwrap(),ChallengeRating::Eighth);
+    /// assert_eq!(ChallengeRating::Whole(17).proficiency_bonus(),6);
+    /// assert!("31".parse::<ChallengeRating>().is_err());
+    /// ```
+    pub fn xp(&self)->i32{
+        const XP:[i32;30]=[200,450,700,1100,1800,2300,2900,3900,5000,5900,7200,8400,10000,11500,13000,
+        15000,18000,20000,22000,25000,33000,41000,50000,62000,75000,90000,105000,120000,135000,155000];
+        match self{
+            ChallengeRating::Zero=>10,
+            ChallengeRating::Eighth=>25,
+            ChallengeRating::Quarter=>50,
+            ChallengeRating::Half=>100,
+            ChallengeRating::Whole(cr)=>XP[(*cr).clamp(1,30) as usize-1],
+        }
+    }
+    ///取整后的挑战等级，分数等级为0
+    pub fn level(&self)->i32{
+        match self{
+            ChallengeRating::Whole(cr)=>*cr,
+            _=>0,
+        }
+    }
+    ///该挑战等级的熟练加值，CR0到4为+2，此后每4级加1
+    pub fn proficiency_bonus(&self)->i32{
+        Monster::level_to_proficiency_modifier(self.level()).unwrap_or(2)
+    }
+    ///经验值恰好达到的最高挑战等级
+    pub fn from_xp(xp:i32)->ChallengeRating{
+        let mut best=ChallengeRating::Zero;
+        for cr in [ChallengeRating::Eighth,ChallengeRating::Quarter,ChallengeRating::Half].into_iter().chain((1..=30).map(ChallengeRating::Whole)){
+            if cr.xp()<=xp {best=cr;}
+        }
+        best
+    }
+}
+impl fmt::Display for ChallengeRating{
+    fn fmt(&self,f:&mut fmt::Formatter<'_>)->fmt::Result{
+        match self{
+            ChallengeRating::Zero=>write!(f,"0"),
+            ChallengeRating::Eighth=>write!(f,"1/8"),
+            ChallengeRating::Quarter=>write!(f,"1/4"),
+            ChallengeRating::Half=>write!(f,"1/2"),
+            ChallengeRating::Whole(cr)=>write!(f,"{}",cr),
+        }
+    }
+}
+impl FromStr for ChallengeRating{
+    type Err=DndError;
+    fn from_str(s:&str)->Result<Self,Self::Err>{
+        match s.trim(){
+            "0"=>Ok(ChallengeRating::Zero),
+            "1/8"=>Ok(ChallengeRating::Eighth),
+            "1/4"=>Ok(ChallengeRating::Quarter),
+            "1/2"=>Ok(ChallengeRating::Half),
+            other=>match other.parse::<i32>(){
+                Ok(cr) if (1..=30).contains(&cr)=>Ok(ChallengeRating::Whole(cr)),
+                _=>Err(DndError::InvalidChallengeRating(s.to_string())),
+            },
+        }
+    }
+}
+impl TryFrom<String> for ChallengeRating{
+    type Error=DndError;
+    fn try_from(s:String)->Result<Self,Self::Error>{
+        s.parse()
+    }
+}
+impl From<ChallengeRating> for String{
+    fn from(cr:ChallengeRating)->String{
+        cr.to_string()
+    }
+}
+
+#[derive(Copy,Clone,Debug,Default,PartialEq,Eq,Hash,PartialOrd,Ord,Serialize,Deserialize)]
+///体型
+pub enum Size{
+    Tiny,//微型
+    Small,//小型
+    #[default] Medium,//中型
+    Large,//大型
+    Huge,//巨型
+    Gargantuan,//超巨型
+}
+impl Size{
+    ///该体型怪物的生命骰面数
+    pub fn hit_die(&self)->i32{
+        match self{
+            Size::Tiny=>4,
+            Size::Small=>6,
+            Size::Medium=>8,
+            Size::Large=>10,
+            Size::Huge=>12,
+            Size::Gargantuan=>20,
+        }
+    }
+}
+
+#[derive(Copy,Clone,Debug,Default,PartialEq,Eq,Hash,Serialize,Deserialize)]
+///生物类型
+pub enum CreatureType{
+    Aberration,//异怪
+    Beast,//野兽
+    Celestial,//天界生物
+    Construct,//构装生物
+    Dragon,//龙
+    Elemental,//元素生物
+    Fey,//精类
+    Fiend,//邪魔
+    Giant,//巨人
+    #[default] Humanoid,//类人生物
+    Monstrosity,//怪兽
+    Ooze,//泥怪
+    Plant,//植物
+    Undead,//不死生物
+}
+
+#[derive(Copy,Clone,Debug,Default,PartialEq,Eq,Serialize,Deserialize)]
+///特殊感官的范围（尺），0表示没有
+pub struct Senses{
+    #[serde(default)]
+    pub blindsight:i32,//盲视
+    #[serde(default)]
+    pub darkvision:i32,//黑暗视觉
+    #[serde(default)]
+    pub tremorsense:i32,//震颤感知
+    #[serde(default)]
+    pub truesight:i32,//真实视觉
+}
+
+#[derive(Clone,Debug,PartialEq,Eq,Serialize,Deserialize)]
+///数据块中的一个动作。攻击动作的攻击加值与伤害直接写在数据块里，例如"+4命中，1d6+2穿刺伤害"
+pub struct MonsterAction{
+    pub name:String,
+    ///攻击检定加值，不是攻击的动作为None
+    #[serde(default)]
+    pub attack_bonus:Option<i32>,
+    ///是否是近战攻击，决定倒地、麻痹等状态的影响
+    #[serde(default)]
+    pub melee:bool,
+    ///触及或射程（尺）
+    #[serde(default)]
+    pub range:i32,
+    ///伤害表达式，包括固定加值
+    #[serde(default)]
+    pub damage:Option<DiceExpr>,
+    pub damage_type:DamageType,
+    #[serde(default)]
+    pub magical:bool,
+    #[serde(default)]
+    pub description:String,
+}
+
+#[derive(Clone,Debug,Serialize,Deserialize)]
+///怪物或NPC的数据块
+pub struct Monster{
+    pub name:String,
+    #[serde(default)]
+    pub size:Size,
+    #[serde(default)]
+    pub creature_type:CreatureType,
+    pub armor_class:i32,
+    ///生命骰，例如"2d6"，生命值的期望即为数据块中的平均生命值
+    pub hit_dice:DiceExpr,
+    pub hp:i32,
+    pub max_hp:i32,
+    #[serde(default)]
+    pub walking_speed:i32,
+    #[serde(default)]
+    pub flying_speed:i32,
+    #[serde(default)]
+    pub swimming_speed:i32,
+    #[serde(default)]
+    pub climbing_speed:i32,
+    #[serde(default)]
+    pub burrowing_speed:i32,
+    pub ability_scores:AbilityScores,
+    ///熟练的豁免
+    #[serde(default)]
+    pub saving_throws:HashSet<Abilities>,
+    #[serde(default)]
+    pub skills:HashMap<Skill,Proficiency>,
+    #[serde(default)]
+    pub damage_defenses:DamageDefenses,
+    #[serde(default)]
+    pub senses:Senses,
+    #[serde(default)]
+    pub languages:Vec<String>,
+    pub challenge_rating:ChallengeRating,
+    ///多重攻击时依次使用的动作名字，可以重复
+    #[serde(default)]
+    pub multiattack:Vec<String>,
+    #[serde(default)]
+    pub actions:Vec<MonsterAction>,
+    #[serde(default)]
+    pub conditions:Vec<ActiveCondition>,
+    ///所属阵营的名字
+    #[serde(default)]
+    pub faction:String,
+    ///正在专注的效果
+    #[serde(default)]
+    pub concentration:Option<Concentration>,
+    ///已经结束、还没有清理受影响者的专注效果
+    #[serde(default)]
+    pub broken_concentration:Vec<Concentration>,
+    ///专注时受到的、还没有进行专注检定的伤害
+    #[serde(default)]
+    pub pending_concentration_checks:Vec<i32>,
+}
+
+impl Monster{
+    ///给定名字、挑战等级以及生命骰的怪物，生命值取平均值，属性全为10，其余字段为空
+    pub fn new(name:&str,challenge_rating:ChallengeRating,hit_dice:DiceExpr)->Monster{
+        let (min,max)=hit_dice.bounds();
+        let hp=((min+max)/2).max(1);
+        Monster{name:name.to_string(),size:Size::default(),creature_type:CreatureType::default(),armor_class:10,hit_dice,hp,max_hp:hp,
+        walking_speed:30,flying_speed:0,swimming_speed:0,climbing_speed:0,burrowing_speed:0,
+        ability_scores:AbilityScores{strength:10,dexterity:10,constitution:10,intelligence:10,wisdom:10,charisma:10},
+        saving_throws:HashSet::new(),skills:HashMap::new(),damage_defenses:DamageDefenses::default(),senses:Senses::default(),
+        languages:Vec::new(),challenge_rating,multiattack:Vec::new(),actions:Vec::new(),conditions:Vec::new(),faction:String::new(),
+        concentration:None,broken_concentration:Vec::new(),pending_concentration_checks:Vec::new()}
+    }
+    ///击败该怪物获得的经验值
+    pub fn xp(&self)->i32{
+        self.challenge_rating.xp()
+    }
+    ///熟练加值，由挑战等级决定
+    pub fn proficiency_bonus(&self)->i32{
+        self.challenge_rating.proficiency_bonus()
+    }
+    ///投掷生命骰决定生命值，代替平均值
+    pub fn roll_hit_points<R:Rng+?Sized>(&mut self,rng:&mut R){
+        self.max_hp=self.hit_dice.roll(rng).total.max(1);
+        self.hp=self.max_hp;
+    }
+    ///当前的生命值上限，4级及以上力竭时减半
+    pub fn hit_point_maximum(&self)->i32{
+        self.conditions.adjusted_max_hp(self.max_hp)
+    }
+    ///受到伤害，经过抗性、易伤与免疫调整后扣除，生命值最低为0。石化时对所有伤害具有抗性。返回调整后的伤害。
+    /// 专注时记录一次待进行的专注检定，见[`ConcentrationTracker::resolve_concentration_checks_with`](crate::ConcentrationTracker::resolve_concentration_checks_with)
+    pub fn apply_damage_from(&mut self,amount:i32,damage_type:DamageType,magical:bool)->i32{
+        let damage=self.damage_defenses.adjust_under(amount,damage_type,magical,&self.conditions);
+        if damage>0&&self.concentration.is_some() {self.pending_concentration_checks.push(damage);}
+        self.hp=(self.hp-damage).max(0);
+        damage
+    }
+    ///每回合的移动力，取各种速度中的最大值，并且受到状态影响
+    pub fn speed(&self)->i32{
+        let speed=[self.walking_speed,self.flying_speed,self.swimming_speed,self.climbing_speed,self.burrowing_speed]
+        .into_iter().max().unwrap_or(0).max(0);
+        self.conditions.adjusted_speed(speed)
+    }
+    ///生命值降到0即死亡
+    pub fn is_defeated(&self)->bool{
+        self.hp<=0
+    }
+    ///用名为action_name的攻击动作攻击任何[`Combatant`]，规则与[`attack_with`](crate::attack_with)相同，只是攻击加值与伤害取自数据块
+    /// ```
+    /// use minidnd_eecs_havefun::{Monster,MonsterAction,ChallengeRating,DamageType,DiceExpr,Player,seeded_rng};
+    /// let mut goblin=Monster::new("Goblin",ChallengeRating::Quarter,"2d6".parse().unwrap());
+    /// assert_eq!((goblin.hp,goblin.xp()),(7,50));
+    /// goblin.actions.push(MonsterAction{name:"Scimitar".to_string(),attack_bonus:Some(4),melee:true,range:5,
+    /// damage:Some("1d6+2".parse().unwrap()),damage_type:DamageType::Slash,magical:false,description:String::new()});
+    /// goblin.multiattack=vec!["Scimitar".to_string(),"Scimitar".to_string()];
+    /// let mut alice=Player::new_by_default();
+    /// let reports=goblin.multiattack_with(&mut seeded_rng(4),&mut alice,0).unwrap();
+    /// assert_eq!(reports.len(),2);
+    /// assert_eq!(alice.hp,100-reports.iter().map(|r|r.damage).sum::<i32>());
+    /// assert!(reports.iter().all(|r|r.attack_roll==r.natural+4));
+    /// ```
+    pub fn attack_with<R:Rng+?Sized,T:Combatant+?Sized>(&self,rng:&mut R,action_name:&str,target:&mut T,advantage:i32)->Result<AttackReport,DndError>{
+        check_count_and_advantage(1,advantage)?;
+        let action=self.actions.iter().find(|a|a.name==action_name).ok_or_else(||DndError::WeaponNotFound(action_name.to_string()))?;
+        let (Some(attack_bonus),Some(damage))=(action.attack_bonus,action.damage.as_ref()) else {
+            return Err(DndError::WeaponNotFound(action_name.to_string()))
+        };
+        let profile=AttackProfile{name:&action.name,attack_bonus,damage,damage_bonus:0,
+        damage_type:action.damage_type,magical:action.magical,melee:action.melee,advantage:0};
+        Ok(resolve_attack(rng,&self.name,&self.conditions,&profile,target,advantage))
+    }
+    ///按照多重攻击依次攻击同一个目标，目标倒下后不再继续
+    pub fn multiattack_with<R:Rng+?Sized,T:Combatant+?Sized>(&self,rng:&mut R,target:&mut T,advantage:i32)->Result<Vec<AttackReport>,DndError>{
+        let mut reports=Vec::with_capacity(self.multiattack.len());
+        for action in &self.multiattack{
+            if target.is_defeated() {break}
+            reports.push(self.attack_with(rng,action,target,advantage)?);
+        }
+        Ok(reports)
+    }
+}
+
+impl DNDChecker for Monster{
+    fn ability_scores_to_modifiers(&self)->Modifiers{
+        self.ability_scores.to_modifiers()
+    }
+    ///怪物的属性检定没有熟练加值
+    fn proficiency_modifiers(&self)->Modifiers{
+        Modifiers::default()
+    }
+    ///数据块中列出的豁免加上熟练加值
+    /// ```
+    /// use minidnd_eecs_havefun::{Monster,ChallengeRating,Abilities,DNDChecker};
+    /// let mut ogre=Monster::new("Ogre",ChallengeRating::Whole(2),"7d10+21".parse().unwrap());
+    /// ogre.saving_throws.insert(Abilities::Strength);
+    /// assert_eq!(ogre.saving_throw_modifiers().get(Abilities::Strength),2);
+    /// assert_eq!(ogre.saving_throw_modifiers().get(Abilities::Wisdom),0);
+    /// ```
+    fn saving_throw_modifiers(&self)->Modifiers{
+        let bonus=|ability:Abilities|if self.saving_throws.contains(&ability) {self.proficiency_bonus()} else {0};
+        Modifiers{strength:bonus(Abilities::Strength),dexterity:bonus(Abilities::Dexterity),
+        constitution:bonus(Abilities::Constitution),intelligence:bonus(Abilities::Intelligence),
+        wisdom:bonus(Abilities::Wisdom),charisma:bonus(Abilities::Charisma)}
+    }
+    fn active_conditions(&self)->&[ActiveCondition]{
+        &self.conditions
+    }
+    fn skill_proficiency_modifier(&self,skill:Skill)->i32{
+        self.skills.get(&skill).map_or(0,|p|p.bonus(self.proficiency_bonus()))
+    }
+}
+
+impl InformationGetter for Monster{
+    ///怪物的经验值即击败它获得的经验值，转换为取整后的挑战等级
+    fn exp_to_level(exp:i32)->Result<i32,DndError>{
+        if exp<0 {return Err(DndError::InvalidExperience(exp))}
+        Ok(ChallengeRating::from_xp(exp).level())
+    }
+    ///挑战等级0到30对应的熟练加值，分数挑战等级按0计算
+    fn level_to_proficiency_modifier(level:i32)->Option<i32>{
+        match level{
+            0=>Some(2),
+            _=>Player::level_to_proficiency_modifier(level),
+        }
+    }
+    fn coins_to_coin(coins:&Coins,coin_type:CoinType)->Result<(i32,i32),DndError>{
+        Player::coins_to_coin(coins,coin_type)
+    }
+}
+
+///与玩家的规则完全相同，见[`Combat`]中各个函数的说明
+/// ```
+/// use minidnd_eecs_havefun::{Monster,ChallengeRating,Combat};
+/// use std::collections::HashMap;
+/// let goblins=HashMap::from([("Goblin".to_string(),Monster::new("Goblin",ChallengeRating::Quarter,"2d6".parse().unwrap())),
+/// ("Wolf".to_string(),Monster::new("Wolf",ChallengeRating::Quarter,"2d8+2".parse().unwrap()))]);
+/// let rank=Monster::roll_initiative(&goblins).ranks();
+/// let mut ranks=vec![rank["Goblin"],rank["Wolf"]];
+/// ranks.sort();
+/// assert_eq!(ranks,vec![1,2]);
+/// ```
+impl Combat for Monster{
+    fn determine_surprise<C:Combatant>(combatants:&HashMap<String,C>,relations:&FactionTable,
+    hide:&HashMap<String,bool>)->Result<HashMap<String,SurpriseStatus>,DndError>{
+        Player::determine_surprise(combatants,relations,hide)
+    }
+    fn roll_initiative<C:Combatant>(combatants:&HashMap<String,C>)->InitiativeTracker{
+        Player::roll_initiative(combatants)
+    }
+    fn establish_positions<C:Combatant>(combatants:&HashMap<String,C>,
+    map:&mut BattleMap,formation:Formation,spacing:Spacing)->Result<HashMap<String,Position>,DndError>{
+        Player::establish_positions(combatants,map,formation,spacing)
+    }
+    fn take_turns<C:Combatant,F:FnMut(&mut CombatEncounter<C>,&Turn)>(encounter:&mut CombatEncounter<C>,act:F)->EncounterState{
+        Player::take_turns(encounter,act)
+    }
+}
@@ -0,0 +1,182 @@
+//!兼职，来自第5版玩家手册第6章
+//!加入新职业时，原有职业与新职业的属性值要求都必须满足。兼职只获得新职业的部分熟练项，不获得豁免熟练，
+//!新职业1级的生命值按升级的规则计算。施法者等级由各职业的等级合计，用于查询共同的法术位表
+use crate::{Abilities,AbilityScores,ArmorCategory,CharacterClass,DndError,HitPointGain,LevelUp,Player,Skill,SpellcastingProgression,WeaponCategory};
+use rand::Rng;
+
+///施法者等级1到20对应的1到9环法术位数量
+const SPELL_SLOTS:[[i32;9];20]=[
+    [2,0,0,0,0,0,0,0,0],
+    [3,0,0,0,0,0,0,0,0],
+    [4,2,0,0,0,0,0,0,0],
+    [4,3,0,0,0,0,0,0,0],
+    [4,3,2,0,0,0,0,0,0],
+    [4,3,3,0,0,0,0,0,0],
+    [4,3,3,1,0,0,0,0,0],
+    [4,3,3,2,0,0,0,0,0],
+    [4,3,3,3,1,0,0,0,0],
+    [4,3,3,3,2,0,0,0,0],
+    [4,3,3,3,2,1,0,0,0],
+    [4,3,3,3,2,1,0,0,0],
+    [4,3,3,3,2,1,1,0,0],
+    [4,3,3,3,2,1,1,0,0],
+    [4,3,3,3,2,1,1,1,0],
+    [4,3,3,3,2,1,1,1,0],
+    [4,3,3,3,2,1,1,1,1],
+    [4,3,3,3,3,1,1,1,1],
+    [4,3,3,3,3,2,1,1,1],
+    [4,3,3,3,3,2,2,1,1],
+];
+
+///某个施法者等级的法术位，下标0为1环。施法者等级为0时没有法术位
+pub fn spell_slots_for(caster_level:i32)->[i32;9]{
+    match caster_level{
+        1..=20=>SPELL_SLOTS[caster_level as usize-1],
+        l if l>20=>SPELL_SLOTS[19],
+        _=>[0;9],
+    }
+}
+
+impl CharacterClass{
+    ///属性值是否满足兼职的要求
+    /// ```
+    /// use minidnd_eecs_havefun::{CharacterClass,AbilityScores};
+    /// let scores=AbilityScores{strength:8,dexterity:13,constitution:14,intelligence:10,wisdom:12,charisma:15};
+    /// assert!(CharacterClass::Fighter.meets_multiclass_prerequisites(&scores));
+    /// assert!(!CharacterClass::Monk.meets_multiclass_prerequisites(&scores));
+    /// assert!(!CharacterClass::Paladin.meets_multiclass_prerequisites(&scores));
+    /// ```
+    pub fn meets_multiclass_prerequisites(&self,scores:&AbilityScores)->bool{
+        let at_least=|ability:Abilities|scores.get(ability)>=13;
+        use Abilities::*;
+        match self{
+            CharacterClass::Barbarian=>at_least(Strength),
+            CharacterClass::Bard|CharacterClass::Sorcerer|CharacterClass::Warlock=>at_least(Charisma),
+            CharacterClass::Cleric|CharacterClass::Druid=>at_least(Wisdom),
+            CharacterClass::Fighter=>at_least(Strength)||at_least(Dexterity),
+            CharacterClass::Monk|CharacterClass::Ranger=>at_least(Dexterity)&&at_least(Wisdom),
+            CharacterClass::Paladin=>at_least(Strength)&&at_least(Charisma),
+            CharacterClass::Rogue=>at_least(Dexterity),
+            CharacterClass::Wizard=>at_least(Intelligence),
+        }
+    }
+    ///兼职加入时获得的护甲熟练
+    pub fn multiclass_armor_proficiencies(&self)->&'static [ArmorCategory]{
+        use ArmorCategory::*;
+        match self{
+            CharacterClass::Barbarian=>&[Shield],
+            CharacterClass::Cleric|CharacterClass::Druid|CharacterClass::Fighter|CharacterClass::Paladin|CharacterClass::Ranger=>
+            &[Light,Medium,Shield],
+            CharacterClass::Bard|CharacterClass::Rogue|CharacterClass::Warlock=>&[Light],
+            CharacterClass::Monk|CharacterClass::Sorcerer|CharacterClass::Wizard=>&[],
+        }
+    }
+    ///兼职加入时获得的武器熟练，个别武器（例如武僧的短剑）不在这里列出
+    pub fn multiclass_weapon_proficiencies(&self)->&'static [WeaponCategory]{
+        use WeaponCategory::*;
+        match self{
+            CharacterClass::Barbarian|CharacterClass::Fighter|CharacterClass::Paladin|CharacterClass::Ranger=>
+            &[SimpleMelee,SimpleRanged,MartialMelee,MartialRanged],
+            CharacterClass::Monk|CharacterClass::Warlock=>&[SimpleMelee,SimpleRanged],
+            _=>&[],
+        }
+    }
+    ///兼职加入时获得的工具熟练
+    pub fn multiclass_tool_proficiencies(&self)->&'static [&'static str]{
+        match self{
+            CharacterClass::Rogue=>&["Thieves' tools"],
+            _=>&[],
+        }
+    }
+    ///兼职加入时可以选择的技能数量以及可选的技能
+    pub fn multiclass_skill_choices(&self)->(usize,&'static [Skill]){
+        match self{
+            CharacterClass::Bard|CharacterClass::Ranger|CharacterClass::Rogue=>(1,self.skill_choices().1),
+            _=>(0,&[]),
+        }
+    }
+}
+
+impl Player{
+    ///兼职加入一个新职业，算作角色等级升一级。原有职业与新职业的属性值要求都必须满足，
+    /// 只获得新职业的兼职熟练项与1级特性，生命值按照升级的规则增加
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,CharacterClass,ArmorCategory,HitPointGain,Skill,seeded_rng};
+    /// let mut player=Player::new_by_default();
+    /// player.choose_class(CharacterClass::Fighter,&[Skill::Athletics,Skill::Perception]).unwrap();
+    /// let mut rng=seeded_rng(1);
+    /// //默认智力12，魅力8
+    /// player.exp=900;
+    /// assert!(player.multiclass(&mut rng,CharacterClass::Wizard,&[],HitPointGain::Average).is_err());
+    /// player.multiclass(&mut rng,CharacterClass::Rogue,&[Skill::Stealth],HitPointGain::Average).unwrap();
+    /// assert_eq!((player.character_level(),player.class_level(CharacterClass::Rogue)),(2,1));
+    /// assert_eq!(player.max_hp,11+6);
+    /// assert!(player.tool_proficiencies.contains("Thieves' tools"));
+    /// //兼职不获得豁免熟练
+    /// assert!(player.skills_for_st_intelligence.is_empty());
+    /// assert!(player.multiclass(&mut rng,CharacterClass::Rogue,&[Skill::Stealth],HitPointGain::Average).is_err());
+    /// ```
+    pub fn multiclass<R:Rng+?Sized>(&mut self,rng:&mut R,class:CharacterClass,skills:&[Skill],hp:HitPointGain)->Result<LevelUp,DndError>{
+        if self.classes.is_empty() {return Err(DndError::NoClass)}
+        if self.class_level(class)>0 {return Err(DndError::ClassAlreadyChosen(class))}
+        self.check_experience()?;
+        if let Some((unmet,_))=self.classes.iter().chain([(class,0)].iter())
+        .find(|(c,_)|!c.meets_multiclass_prerequisites(&self.ability_scores)) {
+            return Err(DndError::MulticlassPrerequisite(*unmet))
+        }
+        let (count,choices)=class.multiclass_skill_choices();
+        self.choose_skills(count,choices,skills)?;
+        self.armor_proficiencies.extend(class.multiclass_armor_proficiencies());
+        self.weapon_proficiencies.extend(class.multiclass_weapon_proficiencies());
+        self.tool_proficiencies.extend(class.multiclass_tool_proficiencies().iter().map(|t|t.to_string()));
+        let hp_gained=self.gain_hit_points(rng,class,hp);
+        self.classes.push((class,1));
+        let features=self.gain_features(class,1);
+        self.update_armor_class();
+        Ok(LevelUp{class,level:1,hp_gained,features})
+    }
+    ///施法者等级，契约魔法不计算在内。只有一个施法职业时按照该职业自己的成长计算（半施法者向上取整），
+    /// 兼职多个施法职业时全施法者等级与半施法者等级的一半（向下取整）相加
+    pub fn caster_level(&self)->i32{
+        let casters:Vec<(SpellcastingProgression,i32)>=self.classes.iter()
+        .map(|(class,levels)|(class.spellcasting_progression(),*levels))
+        .filter(|(p,_)|matches!(p,SpellcastingProgression::Full|SpellcastingProgression::Half)).collect();
+        match casters.as_slice(){
+            [(SpellcastingProgression::Half,levels)]=>if *levels>=2 {(levels+1)/2} else {0},
+            _=>casters.iter().map(|(p,levels)|if *p==SpellcastingProgression::Full {*levels} else {levels/2}).sum(),
+        }
+    }
+    ///1到9环的法术位数量，契约魔法的法术位见[`Player::pact_magic_slots`]
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,CharacterClass};
+    /// let mut player=Player::new_by_default();
+    /// player.classes=vec![(CharacterClass::Paladin,3)];
+    /// assert_eq!(player.spell_slots()[..2],[3,0]);
+    /// //兼职：法师5级加圣武士3级，施法者等级为5+1=6
+    /// player.classes.push((CharacterClass::Wizard,5));
+    /// assert_eq!(player.caster_level(),6);
+    /// assert_eq!(player.spell_slots()[..4],[4,3,3,0]);
+    /// ```
+    pub fn spell_slots(&self)->[i32;9]{
+        spell_slots_for(self.caster_level())
+    }
+    ///邪术师契约魔法的法术位，返回（数量，环阶），没有邪术师等级时为(0,0)
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,CharacterClass};
+    /// let mut player=Player::new_by_default();
+    /// player.classes=vec![(CharacterClass::Sorcerer,3),(CharacterClass::Warlock,5)];
+    /// assert_eq!(player.pact_magic_slots(),(2,3));
+    /// assert_eq!(player.spell_slots()[..3],[4,2,0]);
+    /// ```
+    pub fn pact_magic_slots(&self)->(i32,i32){
+        let level=self.class_level(CharacterClass::Warlock);
+        let count=match level{
+            0=>0,
+            1=>1,
+            2..=10=>2,
+            11..=16=>3,
+            _=>4,
+        };
+        (count,((level+1)/2).min(5))
+    }
+}
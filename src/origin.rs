@@ -0,0 +1,405 @@
+//!种族与背景，来自第5版玩家手册第2章与第4章
+//!种族提供属性值提升、体型、速度、黑暗视觉、语言、伤害抗性以及种族特性；背景提供技能、工具、语言熟练以及背景特性。
+//!应用到玩家时记录实际带来的改变，撤销时只撤销这些改变，因此可以随时更换种族或背景重新构建角色
+use crate::{Abilities,ArmorCategory,DamageType,Player,Proficiency,Senses,Size,Skill,Weapon};
+use serde::{Serialize,Deserialize};
+
+#[derive(Clone,Debug,PartialEq,Eq,Serialize,Deserialize)]
+///种族
+pub struct Race{
+    pub name:String,
+    pub size:Size,
+    ///行走速度（尺）
+    pub walking_speed:i32,
+    ///飞行速度（尺），0表示不能飞行
+    pub flying_speed:i32,
+    ///黑暗视觉的范围（尺），0表示没有
+    pub darkvision:i32,
+    pub ability_increases:Vec<(Abilities,i32)>,
+    pub languages:Vec<String>,
+    pub resistances:Vec<DamageType>,
+    pub skills:Vec<Skill>,
+    pub tools:Vec<String>,
+    ///熟练的具体武器的名字
+    #[serde(default)]
+    pub weapons:Vec<String>,
+    ///熟练的护甲类别
+    #[serde(default)]
+    pub armor:Vec<ArmorCategory>,
+    ///种族特性的名字
+    pub traits:Vec<String>,
+}
+
+#[derive(Clone,Debug,PartialEq,Eq,Serialize,Deserialize)]
+///背景。需要自选的语言与工具（例如侍僧的两门语言）不在构造函数中给出，可以直接加入对应的字段
+pub struct Background{
+    pub name:String,
+    pub skills:Vec<Skill>,
+    pub tools:Vec<String>,
+    pub languages:Vec<String>,
+    ///背景特性的名字
+    pub feature:String,
+}
+
+#[derive(Clone,Debug,Default,PartialEq,Eq,Serialize,Deserialize)]
+///应用种族或背景时实际带来的改变，用于撤销。玩家原本已经拥有的熟练项等不会记录在这里，撤销时也不会失去
+pub struct Grants{
+    pub ability_increases:Vec<(Abilities,i32)>,
+    pub skills:Vec<Skill>,
+    pub tools:Vec<String>,
+    pub languages:Vec<String>,
+    pub resistances:Vec<DamageType>,
+    #[serde(default)]
+    pub weapons:Vec<String>,
+    #[serde(default)]
+    pub armor:Vec<ArmorCategory>,
+    pub features:Vec<String>,
+    ///应用种族之前的体型、行走速度、飞行速度与黑暗视觉
+    pub previous_size:Size,
+    pub previous_walking_speed:i32,
+    pub previous_flying_speed:i32,
+    pub previous_darkvision:i32,
+}
+
+fn strings(items:&[&str])->Vec<String>{
+    items.iter().map(|s|s.to_string()).collect()
+}
+
+impl Race{
+    ///没有任何加成的种族，其余种族在此基础上构造
+    pub fn new(name:&str,size:Size,walking_speed:i32)->Race{
+        Race{name:name.to_string(),size,walking_speed,flying_speed:0,darkvision:0,ability_increases:Vec::new(),
+        languages:strings(&["Common"]),resistances:Vec::new(),skills:Vec::new(),tools:Vec::new(),weapons:Vec::new(),armor:Vec::new(),traits:Vec::new()}
+    }
+    ///人类：所有属性+1
+    pub fn human()->Race{
+        let mut race=Race::new("Human",Size::Medium,30);
+        race.ability_increases=[Abilities::Strength,Abilities::Dexterity,Abilities::Constitution,
+        Abilities::Intelligence,Abilities::Wisdom,Abilities::Charisma].iter().map(|a|(*a,1)).collect();
+        race
+    }
+    ///矮人，tool为工具熟练中选择的一种工匠工具：铁匠工具、酿酒工具或石匠工具
+    fn dwarf(name:&str,increase:(Abilities,i32),extra_trait:&str,tool:&str)->Race{
+        let mut race=Race::new(name,Size::Medium,25);
+        race.darkvision=60;
+        race.ability_increases=vec![(Abilities::Constitution,2),increase];
+        race.languages.push("Dwarvish".to_string());
+        race.resistances.push(DamageType::Poison);
+        race.weapons=strings(&["Battleaxe","Handaxe","Light hammer","Warhammer"]);
+        race.tools.push(tool.to_string());
+        race.traits=strings(&["Dwarven Resilience","Dwarven Combat Training","Tool Proficiency","Stonecunning",extra_trait]);
+        race
+    }
+    ///丘陵矮人，tool为选择的工匠工具
+    pub fn hill_dwarf(tool:&str)->Race{
+        Race::dwarf("Hill Dwarf",(Abilities::Wisdom,1),"Dwarven Toughness",tool)
+    }
+    ///山地矮人，tool为选择的工匠工具。熟练轻甲与中甲
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,Race,ArmorCategory};
+    /// let mut player=Player::new_by_default();
+    /// player.armor_proficiencies.insert(ArmorCategory::Light);
+    /// player.set_race(Race::mountain_dwarf("Smith's tools"));
+    /// assert!(player.armor_proficiencies.contains(&ArmorCategory::Medium));
+    /// assert!(player.named_weapon_proficiencies.contains("Warhammer"));
+    /// assert!(player.tool_proficiencies.contains("Smith's tools"));
+    /// player.remove_race();
+    /// //原本就熟练的轻甲不会失去
+    /// assert!(player.armor_proficiencies.contains(&ArmorCategory::Light));
+    /// assert!(!player.armor_proficiencies.contains(&ArmorCategory::Medium));
+    /// assert!(player.named_weapon_proficiencies.is_empty()&&player.tool_proficiencies.is_empty());
+    /// ```
+    pub fn mountain_dwarf(tool:&str)->Race{
+        let mut race=Race::dwarf("Mountain Dwarf",(Abilities::Strength,2),"Dwarven Armor Training",tool);
+        race.armor=vec![ArmorCategory::Light,ArmorCategory::Medium];
+        race
+    }
+    fn elf(name:&str,increase:(Abilities,i32),walking_speed:i32,extra_trait:&str)->Race{
+        let mut race=Race::new(name,Size::Medium,walking_speed);
+        race.darkvision=60;
+        race.ability_increases=vec![(Abilities::Dexterity,2),increase];
+        race.languages.push("Elvish".to_string());
+        race.skills.push(Skill::Perception);
+        race.weapons=strings(&["Longsword","Shortsword","Shortbow","Longbow"]);
+        race.traits=strings(&["Fey Ancestry","Trance","Elf Weapon Training",extra_trait]);
+        race
+    }
+    ///高等精灵，language为额外选择的一门语言
+    pub fn high_elf(language:&str)->Race{
+        let mut race=Race::elf("High Elf",(Abilities::Intelligence,1),30,"Cantrip");
+        race.languages.push(language.to_string());
+        race.traits.push("Extra Language".to_string());
+        race
+    }
+    ///木精灵
+    pub fn wood_elf()->Race{
+        Race::elf("Wood Elf",(Abilities::Wisdom,1),35,"Mask of the Wild")
+    }
+    fn halfling(name:&str,increase:(Abilities,i32),extra_trait:&str)->Race{
+        let mut race=Race::new(name,Size::Small,25);
+        race.ability_increases=vec![(Abilities::Dexterity,2),increase];
+        race.languages.push("Halfling".to_string());
+        race.traits=strings(&["Lucky","Brave","Halfling Nimbleness",extra_trait]);
+        race
+    }
+    ///轻足半身人
+    pub fn lightfoot_halfling()->Race{
+        Race::halfling("Lightfoot Halfling",(Abilities::Charisma,1),"Naturally Stealthy")
+    }
+    ///敦实半身人
+    pub fn stout_halfling()->Race{
+        let mut race=Race::halfling("Stout Halfling",(Abilities::Constitution,1),"Stout Resilience");
+        race.resistances.push(DamageType::Poison);
+        race
+    }
+    ///龙裔，ancestry为龙族血统对应的伤害类型，决定吐息与抗性
+    pub fn dragonborn(ancestry:DamageType)->Race{
+        let mut race=Race::new("Dragonborn",Size::Medium,30);
+        race.ability_increases=vec![(Abilities::Strength,2),(Abilities::Charisma,1)];
+        race.languages.push("Draconic".to_string());
+        race.resistances.push(ancestry);
+        race.traits=strings(&["Draconic Ancestry","Breath Weapon"]);
+        race
+    }
+    fn gnome(name:&str,increase:(Abilities,i32),extra_trait:&str)->Race{
+        let mut race=Race::new(name,Size::Small,25);
+        race.darkvision=60;
+        race.ability_increases=vec![(Abilities::Intelligence,2),increase];
+        race.languages.push("Gnomish".to_string());
+        race.traits=strings(&["Gnome Cunning",extra_trait]);
+        race
+    }
+    ///森林侏儒
+    pub fn forest_gnome()->Race{
+        Race::gnome("Forest Gnome",(Abilities::Dexterity,1),"Speak with Small Beasts")
+    }
+    ///岩石侏儒
+    pub fn rock_gnome()->Race{
+        let mut race=Race::gnome("Rock Gnome",(Abilities::Constitution,1),"Artificer's Lore");
+        race.tools.push("Tinker's tools".to_string());
+        race
+    }
+    ///半精灵，魅力+2，另外两项属性各+1，并且熟练两项技能
+    pub fn half_elf(increases:[Abilities;2],skills:[Skill;2])->Race{
+        let mut race=Race::new("Half-Elf",Size::Medium,30);
+        race.darkvision=60;
+        race.ability_increases=vec![(Abilities::Charisma,2),(increases[0],1),(increases[1],1)];
+        race.languages.push("Elvish".to_string());
+        race.skills=skills.to_vec();
+        race.traits=strings(&["Fey Ancestry","Skill Versatility"]);
+        race
+    }
+    ///半兽人
+    pub fn half_orc()->Race{
+        let mut race=Race::new("Half-Orc",Size::Medium,30);
+        race.darkvision=60;
+        race.ability_increases=vec![(Abilities::Strength,2),(Abilities::Constitution,1)];
+        race.languages.push("Orc".to_string());
+        race.skills.push(Skill::Intimidation);
+        race.traits=strings(&["Relentless Endurance","Savage Attacks"]);
+        race
+    }
+    ///提夫林
+    pub fn tiefling()->Race{
+        let mut race=Race::new("Tiefling",Size::Medium,30);
+        race.darkvision=60;
+        race.ability_increases=vec![(Abilities::Intelligence,1),(Abilities::Charisma,2)];
+        race.languages.push("Infernal".to_string());
+        race.resistances.push(DamageType::Fire);
+        race.traits=strings(&["Hellish Resistance","Infernal Legacy"]);
+        race
+    }
+}
+
+impl Background{
+    pub fn new(name:&str,skills:[Skill;2],tools:&[&str],feature:&str)->Background{
+        Background{name:name.to_string(),skills:skills.to_vec(),tools:strings(tools),languages:Vec::new(),feature:feature.to_string()}
+    }
+    ///侍僧，另外可以自选两门语言
+    pub fn acolyte()->Background{
+        Background::new("Acolyte",[Skill::Insight,Skill::Religion],&[],"Shelter of the Faithful")
+    }
+    ///罪犯
+    pub fn criminal()->Background{
+        Background::new("Criminal",[Skill::Deception,Skill::Stealth],&["Thieves' tools","Gaming set"],"Criminal Contact")
+    }
+    ///平民英雄
+    pub fn folk_hero()->Background{
+        Background::new("Folk Hero",[Skill::AnimalHandling,Skill::Survival],&["Artisan's tools","Vehicles (land)"],"Rustic Hospitality")
+    }
+    ///隐士，另外可以自选一门语言
+    pub fn hermit()->Background{
+        Background::new("Hermit",[Skill::Medicine,Skill::Religion],&["Herbalism kit"],"Discovery")
+    }
+    ///贵族，另外可以自选一门语言
+    pub fn noble()->Background{
+        Background::new("Noble",[Skill::History,Skill::Persuasion],&["Gaming set"],"Position of Privilege")
+    }
+    ///贤者，另外可以自选两门语言
+    pub fn sage()->Background{
+        Background::new("Sage",[Skill::Arcana,Skill::History],&[],"Researcher")
+    }
+    ///士兵
+    pub fn soldier()->Background{
+        Background::new("Soldier",[Skill::Athletics,Skill::Intimidation],&["Gaming set","Vehicles (land)"],"Military Rank")
+    }
+    ///流浪儿
+    pub fn urchin()->Background{
+        Background::new("Urchin",[Skill::SleightOfHand,Skill::Stealth],&["Disguise kit","Thieves' tools"],"City Secrets")
+    }
+}
+
+impl Player{
+    ///设置种族，已有的种族先被撤销
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,Race,Size,Skill,DamageType};
+    /// let mut player=Player::new_by_default();
+    /// player.skills.insert(Skill::Perception,minidnd_eecs_havefun::Proficiency::Proficient);
+    /// player.set_race(Race::wood_elf());
+    /// assert_eq!((player.ability_scores.dexterity,player.ability_scores.wisdom),(16,11));
+    /// assert_eq!((player.walking_speed,player.senses.darkvision),(35,60));
+    /// assert!(player.languages.contains("Elvish"));
+    /// player.set_race(Race::stout_halfling());
+    /// assert_eq!((player.ability_scores.dexterity,player.ability_scores.wisdom,player.ability_scores.constitution),(16,10,14));
+    /// assert_eq!((player.size,player.walking_speed,player.senses.darkvision),(Size::Small,25,0));
+    /// assert!(player.damage_defenses.resistances.contains(&DamageType::Poison));
+    /// assert!(!player.languages.contains("Elvish"));
+    /// assert_eq!(player.remove_race().unwrap().name,"Stout Halfling");
+    /// assert_eq!((player.ability_scores.dexterity,player.walking_speed,player.size),(14,30,Size::Medium));
+    /// //玩家原本就熟练察觉，撤销精灵种族时不会失去
+    /// assert!(player.skills.contains_key(&Skill::Perception));
+    /// assert!(player.damage_defenses.resistances.is_empty());
+    /// ```
+    pub fn set_race(&mut self,race:Race){
+        self.remove_race();
+        let constitution=self.ability_scores.to_modifiers().constitution;
+        let mut grants=Grants{previous_size:self.size,previous_walking_speed:self.walking_speed,
+        previous_flying_speed:self.flying_speed,previous_darkvision:self.senses.darkvision,..Grants::default()};
+        for (ability,amount) in &race.ability_increases{
+            *self.ability_scores.get_mut(*ability)+=amount;
+            grants.ability_increases.push((*ability,*amount));
+        }
+        self.size=race.size;
+        self.walking_speed=race.walking_speed;
+        self.flying_speed=race.flying_speed;
+        self.senses.darkvision=self.senses.darkvision.max(race.darkvision);
+        for resistance in &race.resistances{
+            if self.damage_defenses.resistances.insert(*resistance) {grants.resistances.push(*resistance);}
+        }
+        self.grant_proficiencies(&mut grants,&race.skills,&race.tools,&race.languages);
+        for weapon in &race.weapons{
+            if self.named_weapon_proficiencies.insert(weapon.clone()) {grants.weapons.push(weapon.clone());}
+        }
+        for armor in &race.armor{
+            if self.armor_proficiencies.insert(*armor) {grants.armor.push(*armor);}
+        }
+        grants.features=race.traits.clone();
+        self.features.extend(race.traits.iter().cloned());
+        self.race=Some(race);
+        self.race_grants=grants;
+        self.update_armor_class();
+        self.update_constitution_hit_points(constitution);
+    }
+    ///撤销种族带来的所有改变，返回原来的种族。种族改变体质调整值时，生命值上限按角色等级追溯调整
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,CharacterClass,Race,Skill};
+    /// let mut player=Player::new_by_default();
+    /// player.choose_class(CharacterClass::Fighter,&[Skill::Athletics,Skill::Perception]).unwrap();
+    /// assert_eq!((player.hp,player.max_hp),(11,11));
+    /// //矮人体质+2，体质13变为15
+    /// player.set_race(Race::hill_dwarf("Smith's tools"));
+    /// assert_eq!((player.hp,player.max_hp),(12,12));
+    /// player.remove_race();
+    /// assert_eq!((player.hp,player.max_hp),(11,11));
+    /// ```
+    pub fn remove_race(&mut self)->Option<Race>{
+        let race=self.race.take()?;
+        let grants=std::mem::take(&mut self.race_grants);
+        let constitution=self.ability_scores.to_modifiers().constitution;
+        for (ability,amount) in &grants.ability_increases{
+            *self.ability_scores.get_mut(*ability)-=amount;
+        }
+        self.size=grants.previous_size;
+        self.walking_speed=grants.previous_walking_speed;
+        self.flying_speed=grants.previous_flying_speed;
+        self.senses=Senses{darkvision:grants.previous_darkvision,..self.senses};
+        for resistance in &grants.resistances{
+            self.damage_defenses.resistances.remove(resistance);
+        }
+        self.revoke_proficiencies(&grants);
+        self.update_armor_class();
+        self.update_constitution_hit_points(constitution);
+        Some(race)
+    }
+    ///设置背景，已有的背景先被撤销
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,Background,Skill};
+    /// let mut player=Player::new_by_default();
+    /// let mut sage=Background::sage();
+    /// sage.languages=vec!["Draconic".to_string(),"Celestial".to_string()];
+    /// player.set_background(sage);
+    /// assert!(player.skills.contains_key(&Skill::Arcana)&&player.languages.contains("Celestial"));
+    /// player.set_background(Background::urchin());
+    /// assert!(!player.skills.contains_key(&Skill::Arcana)&&player.skills.contains_key(&Skill::Stealth));
+    /// assert!(player.tool_proficiencies.contains("Thieves' tools"));
+    /// assert_eq!(player.features,vec!["City Secrets".to_string()]);
+    /// player.remove_background();
+    /// assert!(player.skills.is_empty()&&player.tool_proficiencies.is_empty()&&player.languages.is_empty());
+    /// ```
+    pub fn set_background(&mut self,background:Background){
+        self.remove_background();
+        let mut grants=Grants::default();
+        self.grant_proficiencies(&mut grants,&background.skills,&background.tools,&background.languages);
+        grants.features=vec![background.feature.clone()];
+        self.features.push(background.feature.clone());
+        self.background=Some(background);
+        self.background_grants=grants;
+    }
+    ///撤销背景带来的所有改变，返回原来的背景
+    pub fn remove_background(&mut self)->Option<Background>{
+        let background=self.background.take()?;
+        let grants=std::mem::take(&mut self.background_grants);
+        self.revoke_proficiencies(&grants);
+        Some(background)
+    }
+    ///获得玩家原本没有的技能、工具与语言熟练，并记录下来
+    fn grant_proficiencies(&mut self,grants:&mut Grants,skills:&[Skill],tools:&[String],languages:&[String]){
+        for skill in skills{
+            if !self.skills.contains_key(skill) {
+                self.skills.insert(*skill,Proficiency::Proficient);
+                grants.skills.push(*skill);
+            }
+        }
+        for tool in tools{
+            if self.tool_proficiencies.insert(tool.clone()) {grants.tools.push(tool.clone());}
+        }
+        for language in languages{
+            if self.languages.insert(language.clone()) {grants.languages.push(language.clone());}
+        }
+    }
+    ///是否熟练某件武器：熟练它的类别，或者熟练这件武器本身（不区分大小写）
+    pub fn is_proficient_with(&self,weapon:&Weapon)->bool{
+        self.weapon_proficiencies.contains(&weapon.category)
+        ||self.named_weapon_proficiencies.iter().any(|w|w.eq_ignore_ascii_case(&weapon.name))
+    }
+    fn revoke_proficiencies(&mut self,grants:&Grants){
+        for skill in &grants.skills{
+            self.skills.remove(skill);
+        }
+        for tool in &grants.tools{
+            self.tool_proficiencies.remove(tool);
+        }
+        for language in &grants.languages{
+            self.languages.remove(language);
+        }
+        for weapon in &grants.weapons{
+            self.named_weapon_proficiencies.remove(weapon);
+        }
+        for armor in &grants.armor{
+            self.armor_proficiencies.remove(armor);
+        }
+        for feature in &grants.features{
+            if let Some(i)=self.features.iter().position(|f|f==feature) {self.features.remove(i);}
+        }
+    }
+}
@@ -0,0 +1,266 @@
+//!骰子与检定的精确概率分布
+//!所有分布都是解析计算得到的，与`dice_complex`以及`DiceExpr::roll`的投掷规则完全一致
+use crate::dice::{DiceExpr,DiceTerm,TermKind};
+use crate::{check_count_and_advantage,check_dc,Abilities,DNDChecker,DndError,Skill};
+use crate::condition::{combine_advantage,Conditions};
+
+///精确计算时最多允许的状态数，超过时返回错误而不是长时间计算
+const MAX_STATES:usize=50_000_000;
+
+#[derive(Clone,Debug,PartialEq)]
+///离散概率分布，probabilities\[i\]是取值为offset+i的概率
+pub struct Distribution{
+    pub offset:i32,
+    pub probabilities:Vec<f64>,
+}
+
+impl Distribution{
+    ///必然取值为value的分布
+    pub fn constant(value:i32)->Distribution{
+        Distribution{offset:value,probabilities:vec![1.0]}
+    }
+    ///一个1到sides的均匀骰子
+    pub fn uniform(sides:i32)->Distribution{
+        Distribution{offset:1,probabilities:vec![1.0/sides as f64;sides as usize]}
+    }
+    ///最小可能取值
+    pub fn min(&self)->i32{
+        self.offset
+    }
+    ///最大可能取值
+    pub fn max(&self)->i32{
+        self.offset+self.probabilities.len() as i32-1
+    }
+    ///取值恰好为value的概率
+    pub fn probability_of(&self,value:i32)->f64{
+        if value<self.min()||value>self.max() {0.0}
+        else {self.probabilities[(value-self.offset) as usize]}
+    }
+    ///取值不小于value的概率，即检定对难度value成功的概率
+    pub fn at_least(&self,value:i32)->f64{
+        (value.max(self.min())..=self.max()).map(|v|self.probability_of(v)).sum()
+    }
+    ///取值不大于value的概率
+    pub fn at_most(&self,value:i32)->f64{
+        (self.min()..=value.min(self.max())).map(|v|self.probability_of(v)).sum()
+    }
+    ///期望
+    pub fn mean(&self)->f64{
+        self.iter().map(|(v,p)|v as f64*p).sum()
+    }
+    ///按取值从小到大遍历(取值,概率)
+    pub fn iter(&self)->impl Iterator<Item=(i32,f64)>+'_{
+        self.probabilities.iter().enumerate().map(move|(i,&p)|(self.offset+i as i32,p))
+    }
+    ///整体加上一个常数
+    pub fn shift(&self,bonus:i32)->Distribution{
+        Distribution{offset:self.offset+bonus,probabilities:self.probabilities.clone()}
+    }
+    ///取相反数
+    pub fn negate(&self)->Distribution{
+        let mut probabilities=self.probabilities.clone();
+        probabilities.reverse();
+        Distribution{offset:-self.max(),probabilities}
+    }
+    ///两个独立随机变量之和的分布
+    pub fn convolve(&self,other:&Distribution)->Distribution{
+        let mut probabilities=vec![0.0;self.probabilities.len()+other.probabilities.len()-1];
+        for (i,p) in self.probabilities.iter().enumerate(){
+            for (j,q) in other.probabilities.iter().enumerate(){
+                probabilities[i+j]+=p*q;
+            }
+        }
+        Distribution{offset:self.offset+other.offset,probabilities}
+    }
+    ///两个独立随机变量中较大者（优势）或较小者（劣势）的分布
+    pub fn advantage(&self,other:&Distribution,advantage:i32)->Distribution{
+        if advantage==0 {return self.clone()}
+        let min=self.min().min(other.min());
+        let max=self.max().max(other.max());
+        let probabilities=(min..=max).map(|v|{
+            if advantage>0 {
+                self.at_most(v)*other.at_most(v)-self.at_most(v-1)*other.at_most(v-1)
+            }
+            else {
+                self.at_least(v)*other.at_least(v)-self.at_least(v+1)*other.at_least(v+1)
+            }
+        }).collect();
+        Distribution{offset:min,probabilities}
+    }
+    ///去掉两端概率为0的取值
+    fn trim(mut self)->Distribution{
+        let start=self.probabilities.iter().position(|&p|p>0.0).unwrap_or(0);
+        let end=self.probabilities.iter().rposition(|&p|p>0.0).map_or(1,|e|e+1);
+        self.probabilities=self.probabilities[start..end].to_vec();
+        self.offset+=start as i32;
+        self
+    }
+}
+
+///与`dice_complex`完全对应的分布，包括上界与数量不合法时的退化规则
+/// ```
+/// use minidnd_eecs_havefun::probability::dice_complex_distribution;
+/// let d20_adv=dice_complex_distribution(20,1,1);
+/// assert!((d20_adv.probability_of(20)-39.0/400.0).abs()<1e-12);
+/// let three_d6=dice_complex_distribution(6,3,0);
+/// assert!((three_d6.mean()-10.5).abs()<1e-12);
+/// assert_eq!((three_d6.min(),three_d6.max()),(3,18));
+/// ```
+pub fn dice_complex_distribution(upperbound:i32,count:i32,advantage:i32)->Distribution{
+    let sides=if (2..=100).contains(&upperbound) {upperbound} else {20};
+    let die=Distribution::uniform(sides);
+    if count<=1||count>10 {
+        match advantage{
+            0=>die,
+            a if a>0=>die.advantage(&die,1),
+            _=>die.advantage(&die,-1),
+        }
+    }
+    else{
+        (1..count).fold(die.clone(),|acc,_|acc.convolve(&die))
+    }
+}
+
+impl DiceTerm{
+    ///单个骰子考虑重投之后的面值分布
+    fn die_distribution(&self)->Distribution{
+        let mut die=Distribution::uniform(self.sides);
+        if let Some(reroll)=self.reroll{
+            let rerolled:f64=(1..=self.sides).filter(|&v|reroll.matches(v)).count() as f64/self.sides as f64;
+            for v in 1..=self.sides{
+                let index=(v-1) as usize;
+                let first=if reroll.matches(v) {0.0} else {1.0/self.sides as f64};
+                die.probabilities[index]=if reroll.once {first+rerolled/self.sides as f64}
+                else {first/(1.0-rerolled)};
+            }
+        }
+        die
+    }
+    ///投一次整组骰子（不考虑优劣势）时保留骰子之和的分布
+    fn group_distribution(&self)->Result<Distribution,DndError>{
+        let die=self.die_distribution();
+        let keep=match self.keep{
+            None=>return Ok((1..self.count).fold(die.clone(),|acc,_|acc.convolve(&die))),
+            Some(keep)=>keep,
+        };
+        let n=self.count as usize;
+        let k=keep.kept_count(self.count) as usize;
+        let max_sum=k*self.sides as usize;
+        if self.sides as usize*(n+1)*(k+1)*(max_sum+1)>MAX_STATES {return Err(DndError::DistributionTooLarge)}
+        //按照保留的那一端依次处理每种面值，table[used][kept][sum]为概率
+        let faces:Vec<i32>=if keep.keeps_highest() {(1..=self.sides).rev().collect()} else {(1..=self.sides).collect()};
+        let mut table=vec![vec![vec![0.0;max_sum+1];k+1];n+1];
+        table[0][0][0]=1.0;
+        for face in faces{
+            let p=die.probability_of(face);
+            let mut next=vec![vec![vec![0.0;max_sum+1];k+1];n+1];
+            for used in 0..=n{
+                for kept in 0..=k{
+                    for sum in 0..=max_sum{
+                        let current=table[used][kept][sum];
+                        if current==0.0 {continue}
+                        let remaining=n-used;
+                        let mut weight=1.0;
+                        for c in 0..=remaining{
+                            if c>0 {weight*=p*(remaining-c+1) as f64/c as f64;}
+                            if weight==0.0 {break}
+                            let take=c.min(k-kept);
+                            next[used+c][kept+take][sum+take*face as usize]+=current*weight;
+                        }
+                    }
+                }
+            }
+            table=next;
+        }
+        Ok(Distribution{offset:0,probabilities:table[n][k].clone()}.trim())
+    }
+    ///整组骰子考虑优劣势之后的分布
+    pub fn distribution(&self)->Result<Distribution,DndError>{
+        let group=self.group_distribution()?;
+        Ok(group.advantage(&group,self.advantage))
+    }
+}
+
+impl DiceExpr{
+    ///表达式总值的精确分布
+    /// ```
+    /// use minidnd_eecs_havefun::DiceExpr;
+    /// let stats=DiceExpr::parse("4d6kh3").unwrap().distribution().unwrap();
+    /// assert!((stats.mean()-15869.0/1296.0).abs()<1e-9);
+    /// assert!((stats.probability_of(18)-21.0/1296.0).abs()<1e-12);
+    /// let gwf=DiceExpr::parse("2d6ro<2").unwrap().distribution().unwrap();
+    /// assert!((gwf.mean()-2.0*25.0/6.0).abs()<1e-9);
+    /// assert!(DiceExpr::parse("2147483647+1").unwrap().distribution().is_err());
+    /// ```
+    pub fn distribution(&self)->Result<Distribution,DndError>{
+        let (low,high)=self.wide_bounds();
+        if low<i32::MIN as i64||high>i32::MAX as i64 {return Err(DndError::DistributionTooLarge)}
+        let mut total=Distribution::constant(0);
+        for term in &self.terms{
+            let term_distribution=match &term.kind{
+                TermKind::Constant(c)=>Distribution::constant(*c),
+                TermKind::Dice(d)=>d.distribution()?,
+            };
+            total=if term.sign<0 {total.convolve(&term_distribution.negate())}
+            else {total.convolve(&term_distribution)};
+        }
+        Ok(total)
+    }
+}
+
+///属性检定与豁免检定的成功率。加值的来源与随机版本完全相同
+pub trait CheckProbability:DNDChecker{
+    ///属性检定检定值的分布
+    fn ability_check_distribution(&self,checker:Abilities,count:i32,advantage:i32)->Result<Distribution,DndError>{
+        check_count_and_advantage(count,advantage)?;
+        let advantage=combine_advantage(&[advantage,self.active_conditions().ability_check_advantage(checker),self.armor_advantage(checker,None)]);
+        let bonus=self.ability_scores_to_modifiers().get(checker)+self.proficiency_modifiers().get(checker);
+        Ok(dice_complex_distribution(20,count,advantage).shift(bonus))
+    }
+    ///属性检定对难度dc成功（即`ability_check`返回Win）的概率
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,Abilities,CheckProbability};
+    /// //默认力量15，调整值+2，需要投出13以上
+    /// let chance=Player::new_by_default().ability_check_chance(Abilities::Strength,15,1,0).unwrap();
+    /// assert!((chance-0.4).abs()<1e-12);
+    /// ```
+    fn ability_check_chance(&self,checker:Abilities,dc:i32,count:i32,advantage:i32)->Result<f64,DndError>{
+        check_dc(dc)?;
+        Ok(self.ability_check_distribution(checker,count,advantage)?.at_least(dc))
+    }
+    ///豁免检定检定值的分布
+    fn saving_throw_distribution(&self,checker:Abilities,count:i32,advantage:i32)->Result<Distribution,DndError>{
+        check_count_and_advantage(count,advantage)?;
+        let advantage=combine_advantage(&[advantage,self.active_conditions().saving_throw_advantage(checker),self.armor_advantage(checker,None)]);
+        let bonus=self.ability_scores_to_modifiers().get(checker)+self.saving_throw_modifiers().get(checker);
+        Ok(dice_complex_distribution(20,count,advantage).shift(bonus))
+    }
+    ///豁免检定对难度dc成功（即`saving_throw`返回Win）的概率，自动失败时为0
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,Abilities,CheckProbability};
+    /// //默认敏捷14，调整值+2，优势下DC15的敏捷豁免需要至少一次投出13以上
+    /// let chance=Player::new_by_default().saving_throw_chance(Abilities::Dexterity,15,1,1).unwrap();
+    /// assert!((chance-0.64).abs()<1e-12);
+    /// ```
+    fn saving_throw_chance(&self,checker:Abilities,dc:i32,count:i32,advantage:i32)->Result<f64,DndError>{
+        check_dc(dc)?;
+        let distribution=self.saving_throw_distribution(checker,count,advantage)?;
+        if self.active_conditions().auto_fails_saving_throw(checker) {return Ok(0.0)}
+        Ok(distribution.at_least(dc))
+    }
+    ///技能检定对难度dc成功的概率
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,Skill,Proficiency,CheckProbability};
+    /// let mut my_player=Player::new_by_default();
+    /// my_player.skills.insert(Skill::Stealth,Proficiency::Proficient);
+    /// //敏捷+2，熟练+2，需要投出11以上
+    /// assert!((my_player.skill_check_chance(Skill::Stealth,15,1,0).unwrap()-0.5).abs()<1e-12);
+    /// ```
+    fn skill_check_chance(&self,skill:Skill,dc:i32,count:i32,advantage:i32)->Result<f64,DndError>{
+        check_dc(dc)?;
+        check_count_and_advantage(count,advantage)?;
+        let advantage=combine_advantage(&[advantage,self.active_conditions().ability_check_advantage(skill.ability()),self.armor_advantage(skill.ability(),Some(skill))]);
+        Ok(dice_complex_distribution(20,count,advantage).shift(self.skill_modifier(skill)).at_least(dc))
+    }
+}
+impl<T:DNDChecker> CheckProbability for T{}
@@ -0,0 +1,289 @@
+//!休息，来自第5版玩家手册第8章
+//!短休至少1小时，期间可以花费生命骰恢复生命值（每颗生命骰投掷后加体质调整值），并恢复短休恢复的职业资源与契约魔法法术位。
+//!长休至少8小时，恢复全部生命值与法术位、最多相当于角色等级一半的生命骰以及所有职业资源，力竭降低一级。
+//!短休期间的战斗会打断短休；长休被累计至少1小时的战斗、行军等剧烈活动打断。被打断的休息没有任何效果
+use crate::{AbilityScores,CharacterClass,DNDChecker,DndError,Player};
+use crate::condition::{Condition,ConditionDuration,ConditionTracker,Conditions};
+use rand::Rng;
+use serde::{Serialize,Deserialize};
+
+#[derive(Copy,Clone,Debug,PartialEq,Eq,Hash,Serialize,Deserialize)]
+///休息的种类，同时表示职业资源在哪种休息后恢复
+pub enum RestKind{
+    Short,//短休，长休同样会恢复
+    Long,//长休
+}
+
+impl RestKind{
+    ///完成休息所需的分钟数
+    pub fn duration_minutes(&self)->i32{
+        match self{
+            RestKind::Short=>60,
+            RestKind::Long=>8*60,
+        }
+    }
+}
+
+#[derive(Clone,Debug,PartialEq,Eq,Serialize,Deserialize)]
+///一个职业资源，例如狂暴、气、引导神力
+pub struct ClassResource{
+    pub name:String,
+    ///每次休息后恢复到的使用次数或点数
+    pub maximum:i32,
+    pub recovery:RestKind,
+}
+
+#[derive(Clone,Debug,PartialEq,Eq,Serialize,Deserialize)]
+///一次正在进行的休息
+/// ```
+/// use minidnd_eecs_havefun::{Rest,RestKind};
+/// let mut rest=Rest::new(RestKind::Long);
+/// rest.pass(4*60);
+/// //遭遇战斗，打了10分钟
+/// assert!(!rest.encounter(10));
+/// rest.pass(4*60);
+/// assert!(rest.is_complete());
+/// let mut rest=Rest::new(RestKind::Short);
+/// assert!(rest.encounter(1));
+/// rest.pass(60);
+/// assert!(!rest.is_complete());
+/// ```
+pub struct Rest{
+    pub kind:RestKind,
+    ///已经休息的分钟数
+    pub minutes:i32,
+    ///休息期间剧烈活动的分钟数，不计入休息时间
+    pub strenuous_minutes:i32,
+}
+
+#[derive(Clone,Debug,Default,PartialEq,Eq,Serialize,Deserialize)]
+///休息的结果
+pub struct RestReport{
+    pub hp_regained:i32,
+    ///花费的生命骰以及每颗投出的结果（已加上体质调整值）
+    pub hit_dice_spent:Vec<(CharacterClass,i32)>,
+    pub hit_dice_regained:i32,
+    ///恢复的职业资源的名字
+    pub resources_recovered:Vec<String>,
+}
+
+impl Rest{
+    pub fn new(kind:RestKind)->Rest{
+        Rest{kind,minutes:0,strenuous_minutes:0}
+    }
+    ///安静地休息了若干分钟
+    pub fn pass(&mut self,minutes:i32){
+        self.minutes+=minutes.max(0);
+    }
+    ///休息期间发生了持续若干分钟的战斗或其他剧烈活动，返回休息是否因此被打断
+    pub fn encounter(&mut self,minutes:i32)->bool{
+        self.strenuous_minutes+=minutes.max(1);
+        self.is_interrupted()
+    }
+    ///短休期间的任何战斗都会打断短休，长休需要累计至少1小时的剧烈活动
+    pub fn is_interrupted(&self)->bool{
+        match self.kind{
+            RestKind::Short=>self.strenuous_minutes>0,
+            RestKind::Long=>self.strenuous_minutes>=60,
+        }
+    }
+    ///休息时间足够并且没有被打断
+    pub fn is_complete(&self)->bool{
+        !self.is_interrupted()&&self.minutes>=self.kind.duration_minutes()
+    }
+}
+
+impl CharacterClass{
+    ///某个职业等级拥有的职业资源，部分资源的次数取决于属性值。契约魔法的法术位见[`Player::pact_magic_slots`]
+    /// ```
+    /// use minidnd_eecs_havefun::{CharacterClass,AbilityScores,RestKind};
+    /// let scores=AbilityScores::default();
+    /// let fighter=CharacterClass::Fighter.resources(9,&scores);
+    /// assert_eq!(fighter.iter().map(|r|(r.name.as_str(),r.maximum)).collect::<Vec<_>>(),
+    /// [("Second Wind",1),("Action Surge",1),("Indomitable",1)]);
+    /// assert_eq!(fighter[2].recovery,RestKind::Long);
+    /// //魅力8，至少1次
+    /// assert_eq!(CharacterClass::Bard.resources(1,&scores)[0].maximum,1);
+    /// assert_eq!(CharacterClass::Bard.resources(5,&scores)[0].recovery,RestKind::Short);
+    /// ```
+    pub fn resources(&self,level:i32,scores:&AbilityScores)->Vec<ClassResource>{
+        let modifiers=scores.to_modifiers();
+        let mut resources=Vec::new();
+        let mut add=|name:&str,maximum:i32,recovery:RestKind|if maximum>0 {
+            resources.push(ClassResource{name:name.to_string(),maximum,recovery});
+        };
+        use RestKind::*;
+        match self{
+            CharacterClass::Barbarian=>{
+                //20级时狂暴次数无限
+                let rages=match level {1..=2=>2,3..=5=>3,6..=11=>4,12..=16=>5,17..=19=>6,_=>i32::MAX};
+                add("Rage",rages,Long);
+            },
+            CharacterClass::Bard=>add("Bardic Inspiration",modifiers.charisma.max(1),if level>=5 {Short} else {Long}),
+            CharacterClass::Cleric=>add("Channel Divinity",match level {1=>0,2..=5=>1,6..=17=>2,_=>3},Short),
+            CharacterClass::Druid=>add("Wild Shape",if level>=2 {2} else {0},Short),
+            CharacterClass::Fighter=>{
+                add("Second Wind",1,Short);
+                add("Action Surge",match level {1=>0,2..=16=>1,_=>2},Short);
+                add("Indomitable",match level {1..=8=>0,9..=12=>1,13..=16=>2,_=>3},Long);
+            },
+            CharacterClass::Monk=>add("Ki",if level>=2 {level} else {0},Short),
+            CharacterClass::Paladin=>{
+                add("Divine Sense",1+modifiers.charisma.max(0),Long);
+                add("Lay on Hands",5*level,Long);
+                add("Channel Divinity",if level>=3 {1} else {0},Short);
+            },
+            CharacterClass::Sorcerer=>add("Sorcery Points",if level>=2 {level} else {0},Long),
+            CharacterClass::Wizard=>add("Arcane Recovery",1,Long),
+            CharacterClass::Ranger|CharacterClass::Rogue|CharacterClass::Warlock=>{},
+        }
+        resources
+    }
+}
+
+impl Player{
+    ///获得临时生命值。临时生命值不叠加，只保留较高的一次
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,DamageType};
+    /// let mut player=Player::new_by_default();
+    /// player.gain_temp_hp(8);
+    /// player.gain_temp_hp(5);
+    /// assert_eq!(player.apply_damage(10,DamageType::Slash),10);
+    /// assert_eq!((player.temp_hp,player.hp),(0,98));
+    /// ```
+    pub fn gain_temp_hp(&mut self,amount:i32){
+        self.temp_hp=self.temp_hp.max(amount);
+    }
+    ///所有职业的资源，多个职业提供同名资源时（例如引导神力）取次数较多的一个，按名字排序
+    pub fn class_resources(&self)->Vec<ClassResource>{
+        let mut resources:Vec<ClassResource>=Vec::new();
+        for (class,level) in &self.classes{
+            for resource in class.resources(*level,&self.ability_scores){
+                match resources.iter_mut().find(|r|r.name==resource.name){
+                    Some(existing) if existing.maximum<resource.maximum=>*existing=resource,
+                    Some(_)=>{},
+                    None=>resources.push(resource),
+                }
+            }
+        }
+        resources.sort_by(|a,b|a.name.cmp(&b.name));
+        resources
+    }
+    ///某个职业资源剩余的次数，没有这个资源时为None
+    pub fn resource_remaining(&self,name:&str)->Option<i32>{
+        let resource=self.class_resources().into_iter().find(|r|r.name==name)?;
+        Some(resource.maximum.saturating_sub(self.spent_resources.get(name).copied().unwrap_or(0)))
+    }
+    ///使用amount次职业资源，返回剩余的次数
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,CharacterClass};
+    /// let mut monk=Player::new_by_default();
+    /// monk.classes=vec![(CharacterClass::Monk,3)];
+    /// assert_eq!(monk.spend_resource("Ki",2).ok(),Some(1));
+    /// assert!(monk.spend_resource("Ki",2).is_err());
+    /// assert!(monk.spend_resource("Rage",1).is_err());
+    /// ```
+    pub fn spend_resource(&mut self,name:&str,amount:i32)->Result<i32,DndError>{
+        let remaining=self.resource_remaining(name).ok_or_else(||DndError::UnknownResource(name.to_string()))?;
+        if amount>remaining {return Err(DndError::ResourceExhausted(name.to_string()))}
+        *self.spent_resources.entry(name.to_string()).or_insert(0)+=amount.max(0);
+        Ok(remaining-amount.max(0))
+    }
+    ///某个职业剩余的生命骰数量
+    pub fn available_hit_dice(&self,class:CharacterClass)->i32{
+        (self.class_level(class)-self.spent_hit_dice.get(&class).copied().unwrap_or(0)).max(0)
+    }
+    ///结束一次休息。短休时依次花费hit_dice中给出的生命骰，长休时忽略hit_dice。
+    /// 休息被打断或者时间不够时返回错误；生命值为0的角色无法从长休中获益
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,CharacterClass,Rest,RestKind,DamageType,seeded_rng};
+    /// use minidnd_eecs_havefun::condition::{Condition,ConditionDuration,ConditionTracker,Conditions};
+    /// let mut player=Player::new_by_default();
+    /// player.classes=vec![(CharacterClass::Fighter,3),(CharacterClass::Wizard,2)];
+    /// player.apply_damage(60,DamageType::Slash);
+    /// player.spend_resource("Action Surge",1).unwrap();
+    /// //3级战士还没有不屈
+    /// assert!(player.spend_resource("Indomitable",1).is_err());
+    /// player.expend_spell_slot(1).unwrap();
+    /// player.add_condition(Condition::Exhaustion(2),ConditionDuration::Indefinite,None);
+    /// let mut rng=seeded_rng(2);
+    /// let mut rest=Rest::new(RestKind::Short);
+    /// rest.pass(60);
+    /// assert!(player.finish_rest_with(&mut rng,&rest,&[(CharacterClass::Wizard,3)]).is_err());
+    /// let report=player.finish_rest_with(&mut rng,&rest,&[(CharacterClass::Fighter,2),(CharacterClass::Wizard,1)]).unwrap();
+    /// assert_eq!(report.hit_dice_spent.len(),3);
+    /// assert_eq!(player.hp,40+report.hp_regained);
+    /// assert_eq!(player.resource_remaining("Action Surge"),Some(1));
+    /// assert_eq!(player.available_hit_dice(CharacterClass::Fighter),1);
+    /// let mut rest=Rest::new(RestKind::Long);
+    /// rest.pass(8*60);
+    /// let report=player.finish_rest_with(&mut rng,&rest,&[]).unwrap();
+    /// //角色等级5，最多恢复2颗生命骰，大的生命骰优先
+    /// assert_eq!(report.hit_dice_regained,2);
+    /// assert_eq!((player.available_hit_dice(CharacterClass::Fighter),player.available_hit_dice(CharacterClass::Wizard)),(3,1));
+    /// assert_eq!((player.hp,player.available_spell_slots()[0]),(100,3));
+    /// assert_eq!(player.conditions.exhaustion_level(),1);
+    /// ```
+    pub fn finish_rest_with<R:Rng+?Sized>(&mut self,rng:&mut R,rest:&Rest,hit_dice:&[(CharacterClass,i32)])->Result<RestReport,DndError>{
+        if rest.is_interrupted() {return Err(DndError::RestInterrupted)}
+        let required=rest.kind.duration_minutes();
+        if rest.minutes<required {return Err(DndError::RestIncomplete{minutes:rest.minutes,required})}
+        match rest.kind{
+            RestKind::Short=>self.short_rest(rng,hit_dice),
+            RestKind::Long=>self.long_rest(),
+        }
+    }
+    fn short_rest<R:Rng+?Sized>(&mut self,rng:&mut R,hit_dice:&[(CharacterClass,i32)])->Result<RestReport,DndError>{
+        for (i,(class,count)) in hit_dice.iter().enumerate(){
+            let requested:i32=hit_dice[..=i].iter().filter(|(c,_)|c==class).map(|(_,n)|n).sum();
+            if *count<0||requested>self.available_hit_dice(*class) {return Err(DndError::NoHitDice(*class))}
+        }
+        let mut report=RestReport::default();
+        let constitution=self.ability_scores_to_modifiers().constitution;
+        for (class,count) in hit_dice{
+            for _ in 0..*count{
+                let roll=(Player::dice_complex(rng,class.hit_die(),1,0)+constitution).max(0);
+                *self.spent_hit_dice.entry(*class).or_insert(0)+=1;
+                report.hp_regained+=self.heal(roll);
+                report.hit_dice_spent.push((*class,roll));
+            }
+        }
+        report.resources_recovered=self.recover_resources(RestKind::Short);
+        self.recover_pact_magic_slots();
+        Ok(report)
+    }
+    fn long_rest(&mut self)->Result<RestReport,DndError>{
+        if self.hp<=0 {return Err(DndError::CannotRest(self.name.clone()))}
+        //先降低力竭，从4级降到3级时恢复到完整的生命值上限
+        if self.conditions.exhaustion_level()>0 {
+            self.add_condition(Condition::Exhaustion(-1),ConditionDuration::Indefinite,None);
+        }
+        let mut report=RestReport{hp_regained:self.heal(self.max_hp),..RestReport::default()};
+        self.temp_hp=0;
+        let mut regain=(self.character_level()/2).max(1);
+        let mut classes:Vec<CharacterClass>=self.spent_hit_dice.keys().copied().collect();
+        classes.sort_by_key(|c|(-c.hit_die(),*c));
+        for class in classes{
+            let spent=self.spent_hit_dice.entry(class).or_insert(0);
+            let regained=regain.min(*spent);
+            *spent-=regained;
+            regain-=regained;
+            report.hit_dice_regained+=regained;
+        }
+        self.spent_hit_dice.retain(|_,spent|*spent>0);
+        report.resources_recovered=self.recover_resources(RestKind::Long);
+        self.recover_all_spell_slots();
+        self.recover_pact_magic_slots();
+        Ok(report)
+    }
+    ///恢复休息后恢复的职业资源，返回恢复了的资源名字。长休恢复所有资源
+    fn recover_resources(&mut self,kind:RestKind)->Vec<String>{
+        let mut recovered=Vec::new();
+        for resource in self.class_resources(){
+            if (kind==RestKind::Long||resource.recovery==RestKind::Short)&&self.spent_resources.remove(&resource.name).is_some_and(|n|n>0) {
+                recovered.push(resource.name);
+            }
+        }
+        recovered
+    }
+}
@@ -0,0 +1,118 @@
+//!18种技能以及对应的关键属性，来自第5版规则书
+use crate::{Abilities,DndError};
+use serde::{Serialize,Deserialize};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Copy,Clone,Debug,PartialEq,Eq,Hash,PartialOrd,Ord,Serialize,Deserialize)]
+///技能
+pub enum Skill{
+    Athletics,//运动
+    Acrobatics,//特技
+    SleightOfHand,//巧手
+    Stealth,//隐匿
+    Arcana,//奥秘
+    History,//历史
+    Investigation,//调查
+    Nature,//自然
+    Religion,//宗教
+    AnimalHandling,//驯兽
+    Insight,//洞悉
+    Medicine,//医药
+    Perception,//察觉
+    Survival,//求生
+    Deception,//欺瞒
+    Intimidation,//威吓
+    Performance,//表演
+    Persuasion,//游说
+}
+
+#[derive(Copy,Clone,Debug,PartialEq,Eq,Hash,Serialize,Deserialize)]
+///熟练程度。半熟练向下取整，专精为双倍熟练加值
+pub enum Proficiency{
+    Half,
+    Proficient,
+    Expertise,
+}
+
+impl Skill{
+    ///全部18种技能
+    pub const ALL:[Skill;18]=[
+        Skill::Athletics,Skill::Acrobatics,Skill::SleightOfHand,Skill::Stealth,
+        Skill::Arcana,Skill::History,Skill::Investigation,Skill::Nature,Skill::Religion,
+        Skill::AnimalHandling,Skill::Insight,Skill::Medicine,Skill::Perception,Skill::Survival,
+        Skill::Deception,Skill::Intimidation,Skill::Performance,Skill::Persuasion,
+    ];
+    ///技能对应的关键属性
+    /// ```
+    /// use minidnd_eecs_havefun::{Skill,Abilities};
+    /// assert!(matches!(Skill::Stealth.ability(),Abilities::Dexterity));
+    /// assert!(matches!(Skill::Perception.ability(),Abilities::Wisdom));
+    /// ```
+    pub fn ability(&self)->Abilities{
+        match self{
+            Skill::Athletics=>Abilities::Strength,
+            Skill::Acrobatics|Skill::SleightOfHand|Skill::Stealth=>Abilities::Dexterity,
+            Skill::Arcana|Skill::History|Skill::Investigation|Skill::Nature|Skill::Religion=>Abilities::Intelligence,
+            Skill::AnimalHandling|Skill::Insight|Skill::Medicine|Skill::Perception|Skill::Survival=>Abilities::Wisdom,
+            Skill::Deception|Skill::Intimidation|Skill::Performance|Skill::Persuasion=>Abilities::Charisma,
+        }
+    }
+    ///技能的中文名
+    pub fn chinese_name(&self)->&'static str{
+        match self{
+            Skill::Athletics=>"运动",
+            Skill::Acrobatics=>"特技",
+            Skill::SleightOfHand=>"巧手",
+            Skill::Stealth=>"隐匿",
+            Skill::Arcana=>"奥秘",
+            Skill::History=>"历史",
+            Skill::Investigation=>"调查",
+            Skill::Nature=>"自然",
+            Skill::Religion=>"宗教",
+            Skill::AnimalHandling=>"驯兽",
+            Skill::Insight=>"洞悉",
+            Skill::Medicine=>"医药",
+            Skill::Perception=>"察觉",
+            Skill::Survival=>"求生",
+            Skill::Deception=>"欺瞒",
+            Skill::Intimidation=>"威吓",
+            Skill::Performance=>"表演",
+            Skill::Persuasion=>"游说",
+        }
+    }
+}
+
+impl Proficiency{
+    ///给定熟练加值时该熟练程度实际提供的加值
+    pub fn bonus(&self,proficiency_modifier:i32)->i32{
+        match self{
+            Proficiency::Half=>proficiency_modifier/2,
+            Proficiency::Proficient=>proficiency_modifier,
+            Proficiency::Expertise=>proficiency_modifier*2,
+        }
+    }
+}
+
+impl fmt::Display for Skill{
+    fn fmt(&self,f:&mut fmt::Formatter<'_>)->fmt::Result{
+        write!(f,"{:?}",self)
+    }
+}
+
+///英文名（忽略大小写、空格与下划线）或者中文名都可以解析为技能，用于读取旧存档中的熟练项
+/// ```
+/// use minidnd_eecs_havefun::Skill;
+/// assert_eq!("sleight of hand".parse::<Skill>().ok(),Some(Skill::SleightOfHand));
+/// assert_eq!("察觉".parse::<Skill>().ok(),Some(Skill::Perception));
+/// assert!("thieves' tools".parse::<Skill>().is_err());
+/// ```
+impl FromStr for Skill{
+    type Err=DndError;
+    fn from_str(s:&str)->Result<Self,Self::Err>{
+        let normalized:String=s.chars().filter(|c|!c.is_whitespace()&&*c!='_'&&*c!='-').collect::<String>().to_lowercase();
+        Skill::ALL.iter().copied()
+        .find(|skill|skill.to_string().to_lowercase()==normalized||skill.chinese_name()==normalized)
+        .ok_or_else(||DndError::UnknownSkill(s.to_string()))
+    }
+}
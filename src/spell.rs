@@ -0,0 +1,404 @@
+//!施法，来自第5版玩家手册第10章
+//!每个施法职业有自己的施法属性，法术豁免难度为8+熟练加值+施法属性调整值，法术攻击加值为熟练加值+施法属性调整值。
+//!1环及以上的法术需要消耗不低于法术环阶的法术位，戏法不消耗法术位，伤害骰数量在角色等级5、11、17时增加。
+//!范围法术瞄准战斗地图上的一个方格，范围内的每个目标各自进行豁免
+use crate::{check_count_and_advantage,Abilities,AreaShape,AttackReport,BattleMap,CharacterClass,Combatant,ConcentrationTracker,DamageType,DiceExpr,
+DNDChecker,DNDResult,DndError,Player,Position};
+use crate::attack::{resolve_attack,AttackProfile};
+use crate::combatant::dyn_rng;
+use rand::Rng;
+use serde::{Serialize,Deserialize};
+use std::collections::HashMap;
+
+#[derive(Copy,Clone,Debug,PartialEq,Eq,Hash,Serialize,Deserialize)]
+///法术学派
+pub enum School{
+    Abjuration,//防护
+    Conjuration,//咒法
+    Divination,//预言
+    Enchantment,//惑控
+    Evocation,//塑能
+    Illusion,//幻术
+    Necromancy,//死灵
+    Transmutation,//变化
+}
+
+#[derive(Copy,Clone,Debug,Default,PartialEq,Eq,Serialize,Deserialize)]
+///施法时间
+pub enum CastingTime{
+    #[default] Action,//动作
+    BonusAction,//附赠动作
+    Reaction,//反应
+    Minutes(i32),
+    Hours(i32),
+}
+
+#[derive(Copy,Clone,Debug,PartialEq,Eq,Serialize,Deserialize)]
+///施法距离
+pub enum SpellRange{
+    Personal,//自身，范围从施法者出发
+    Touch,//触碰，即5尺
+    Feet(i32),
+    Sight,//视线所及
+    Unlimited,//无限
+}
+
+#[derive(Clone,Debug,Default,PartialEq,Eq,Serialize,Deserialize)]
+///施法成分
+pub struct Components{
+    pub verbal:bool,
+    pub somatic:bool,
+    ///材料成分的描述，不需要材料时为None
+    #[serde(default)]
+    pub material:Option<String>,
+}
+
+#[derive(Copy,Clone,Debug,Default,PartialEq,Eq,Serialize,Deserialize)]
+///法术的持续时间
+pub enum SpellDuration{
+    #[default] Instantaneous,//立即
+    Rounds(i32),
+    Minutes(i32),
+    Hours(i32),
+    UntilDispelled,//直到被解除
+}
+
+#[derive(Copy,Clone,Debug,PartialEq,Eq,Serialize,Deserialize)]
+///法术如何对目标生效
+pub enum SpellResolution{
+    ///法术攻击，melee为true时是近战法术攻击
+    Attack{melee:bool},
+    ///目标进行豁免检定，成功则不受伤害或者只受一半伤害，见[`Spell::half_on_save`]
+    Save(Abilities),
+    ///自动生效，例如魔法飞弹
+    Automatic,
+}
+
+#[derive(Clone,Debug,PartialEq,Eq,Serialize,Deserialize)]
+///一个法术，法术表见[`SpellCatalog`](crate::SpellCatalog)
+pub struct Spell{
+    pub name:String,
+    ///环阶，0为戏法
+    pub level:i32,
+    pub school:School,
+    #[serde(default)]
+    pub casting_time:CastingTime,
+    pub range:SpellRange,
+    pub components:Components,
+    #[serde(default)]
+    pub duration:SpellDuration,
+    ///是否需要专注
+    #[serde(default)]
+    pub concentration:bool,
+    pub resolution:SpellResolution,
+    ///豁免成功时是否受到一半伤害
+    #[serde(default)]
+    pub half_on_save:bool,
+    ///以法术自身环阶施放时的伤害，戏法为1级时的伤害
+    pub damage:Option<DiceExpr>,
+    pub damage_type:DamageType,
+    ///每高出法术环阶一环额外增加的伤害
+    #[serde(default)]
+    pub higher_levels:Option<DiceExpr>,
+    ///效果范围，单体法术为None
+    #[serde(default)]
+    pub area:Option<AreaShape>,
+}
+
+#[derive(Clone,Debug,PartialEq,Eq,Serialize,Deserialize)]
+///一次施法的结果
+pub struct SpellReport{
+    pub caster:String,
+    pub target:String,
+    pub spell:String,
+    ///消耗的法术位环阶，戏法为0
+    pub slot_level:i32,
+    ///法术攻击的结果，其余方式为None
+    pub attack:Option<AttackReport>,
+    ///目标是否通过了豁免，不需要豁免时为None
+    pub saved:Option<bool>,
+    ///实际造成的伤害
+    pub damage:i32,
+    ///目标是否因为这次伤害失去了专注
+    #[serde(default)]
+    pub concentration_lost:bool,
+}
+
+///戏法伤害骰的倍数：角色等级1为1倍，5为2倍，11为3倍，17为4倍
+/// ```
+/// use minidnd_eecs_havefun::cantrip_dice;
+/// assert_eq!([1,4,5,10,11,17,20].map(cantrip_dice),[1,1,2,2,3,4,4]);
+/// ```
+pub fn cantrip_dice(character_level:i32)->i32{
+    match character_level{
+        i32::MIN..=4=>1,
+        5..=10=>2,
+        11..=16=>3,
+        _=>4,
+    }
+}
+
+impl CharacterClass{
+    ///施法属性，不施法的职业为None
+    pub fn spellcasting_ability(&self)->Option<Abilities>{
+        match self{
+            CharacterClass::Bard|CharacterClass::Paladin|CharacterClass::Sorcerer|CharacterClass::Warlock=>Some(Abilities::Charisma),
+            CharacterClass::Cleric|CharacterClass::Druid|CharacterClass::Ranger=>Some(Abilities::Wisdom),
+            CharacterClass::Wizard=>Some(Abilities::Intelligence),
+            _=>None,
+        }
+    }
+}
+
+impl Spell{
+    ///以slot_level环施放时的伤害骰。戏法按cantrip_dice倍增伤害骰，升环施放时每高一环加上higher_levels
+    /// ```
+    /// use minidnd_eecs_havefun::SpellCatalog;
+    /// let catalog=SpellCatalog::srd();
+    /// assert_eq!(catalog.get("Burning Hands").unwrap().damage_at(3,1).unwrap().to_string(),"3d6+1d6+1d6");
+    /// assert_eq!(catalog.get("Fire Bolt").unwrap().damage_at(0,3).unwrap().to_string(),"3d10");
+    /// ```
+    pub fn damage_at(&self,slot_level:i32,cantrip_dice:i32)->Option<DiceExpr>{
+        let mut damage=self.damage.clone()?;
+        if self.level==0 {return Some(damage.multiplied_dice(cantrip_dice))}
+        if let Some(higher)=&self.higher_levels{
+            for _ in self.level..slot_level{
+                damage.terms.extend(higher.terms.iter().cloned());
+            }
+        }
+        Some(damage)
+    }
+    ///施法距离（尺），自身为0，触碰为5，视线所及与无限为None
+    pub fn range_feet(&self)->Option<i32>{
+        match self.range{
+            SpellRange::Personal=>Some(0),
+            SpellRange::Touch=>Some(5),
+            SpellRange::Feet(feet)=>Some(feet),
+            SpellRange::Sight|SpellRange::Unlimited=>None,
+        }
+    }
+}
+
+///一次施法中所有目标共用的数值
+struct Casting{
+    slot_level:i32,
+    attack_bonus:i32,
+    dc:i32,
+    damage:Option<DiceExpr>,
+    ///豁免与自动生效的法术只投一次伤害，所有目标共用
+    rolled:Option<i32>,
+}
+
+impl Player{
+    ///某个职业的施法属性，玩家没有这个职业或者职业不能施法时返回错误
+    fn spellcasting_modifier(&self,class:CharacterClass)->Result<i32,DndError>{
+        if self.class_level(class)==0 {return Err(DndError::NotInClass(class))}
+        let ability=class.spellcasting_ability().ok_or(DndError::NotASpellcaster(class))?;
+        Ok(self.ability_scores_to_modifiers().get(ability))
+    }
+    ///某个职业的法术豁免难度：8+熟练加值+施法属性调整值
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,CharacterClass};
+    /// let mut player=Player::new_by_default();
+    /// player.classes=vec![(CharacterClass::Wizard,5),(CharacterClass::Cleric,1)];
+    /// player.ability_scores.intelligence=18;
+    /// //角色等级6，熟练加值+3
+    /// assert_eq!(player.spell_save_dc(CharacterClass::Wizard).ok(),Some(15));
+    /// assert_eq!(player.spell_attack_bonus(CharacterClass::Cleric).ok(),Some(3));
+    /// assert!(player.spell_save_dc(CharacterClass::Druid).is_err());
+    /// player.classes.push((CharacterClass::Fighter,1));
+    /// assert!(player.spell_attack_bonus(CharacterClass::Fighter).is_err());
+    /// ```
+    pub fn spell_save_dc(&self,class:CharacterClass)->Result<i32,DndError>{
+        Ok(8+self.proficiency_bonus()+self.spellcasting_modifier(class)?)
+    }
+    ///某个职业的法术攻击加值：熟练加值+施法属性调整值
+    pub fn spell_attack_bonus(&self,class:CharacterClass)->Result<i32,DndError>{
+        Ok(self.proficiency_bonus()+self.spellcasting_modifier(class)?)
+    }
+    ///戏法伤害骰的倍数，取决于角色等级
+    pub fn cantrip_dice(&self)->i32{
+        let level=if self.classes.is_empty() {self.experience_level()} else {self.character_level()};
+        cantrip_dice(level)
+    }
+    ///剩余的1到9环法术位，下标0为1环
+    pub fn available_spell_slots(&self)->[i32;9]{
+        let mut slots=self.spell_slots();
+        for (slot,expended) in slots.iter_mut().zip(self.expended_spell_slots){
+            *slot=(*slot-expended).max(0);
+        }
+        slots
+    }
+    ///剩余的契约魔法法术位数量
+    pub fn available_pact_magic_slots(&self)->i32{
+        (self.pact_magic_slots().0-self.expended_pact_magic_slots).max(0)
+    }
+    ///消耗一个level环的法术位。优先使用普通法术位，没有时使用同一环阶的契约魔法法术位
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,CharacterClass};
+    /// let mut player=Player::new_by_default();
+    /// player.classes=vec![(CharacterClass::Wizard,1),(CharacterClass::Warlock,1)];
+    /// player.expend_spell_slot(1).unwrap();
+    /// player.expend_spell_slot(1).unwrap();
+    /// assert_eq!((player.available_spell_slots()[0],player.available_pact_magic_slots()),(0,1));
+    /// player.expend_spell_slot(1).unwrap();
+    /// assert!(player.expend_spell_slot(1).is_err());
+    /// assert!(player.expend_spell_slot(10).is_err());
+    /// player.recover_spell_slot(1);
+    /// assert_eq!(player.available_spell_slots()[0],1);
+    /// player.recover_pact_magic_slots();
+    /// assert_eq!(player.available_pact_magic_slots(),1);
+    /// ```
+    pub fn expend_spell_slot(&mut self,level:i32)->Result<(),DndError>{
+        if !(1..=9).contains(&level) {return Err(DndError::InvalidSpellLevel(level))}
+        let index=level as usize-1;
+        if self.available_spell_slots()[index]>0 {
+            self.expended_spell_slots[index]+=1;
+        }else if self.pact_magic_slots().1==level&&self.available_pact_magic_slots()>0 {
+            self.expended_pact_magic_slots+=1;
+        }else {
+            return Err(DndError::NoSpellSlot(level))
+        }
+        Ok(())
+    }
+    ///恢复一个level环的普通法术位，没有消耗过时不变
+    pub fn recover_spell_slot(&mut self,level:i32){
+        if (1..=9).contains(&level) {
+            let expended=&mut self.expended_spell_slots[level as usize-1];
+            *expended=(*expended-1).max(0);
+        }
+    }
+    ///恢复所有普通法术位
+    pub fn recover_all_spell_slots(&mut self){
+        self.expended_spell_slots=[0;9];
+    }
+    ///恢复所有契约魔法法术位
+    pub fn recover_pact_magic_slots(&mut self){
+        self.expended_pact_magic_slots=0;
+    }
+    ///以class的施法能力用slot_level环的法术位对目标施放spell，目标需要能进行豁免检定。
+    /// 戏法不消耗法术位，slot_level被忽略；其余法术先消耗法术位，slot_level不能低于法术环阶。
+    /// 法术攻击与武器攻击一样结算，advantage会与状态带来的优劣势合并；需要豁免的法术使用[`DNDChecker::saving_throw_with`]
+    /// 对抗法术豁免难度。法术伤害都是魔法伤害，目标受到伤害后自动进行专注检定；需要专注的法术使施法者开始专注
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,CharacterClass,SpellCatalog,seeded_rng};
+    /// let catalog=SpellCatalog::srd();
+    /// let mut wizard=Player::new_by_default();
+    /// wizard.classes=vec![(CharacterClass::Wizard,5)];
+    /// let mut goblin=Player{name:"Goblin".to_string(),armor:13,hp:50,..Player::new_by_default()};
+    /// let mut rng=seeded_rng(3);
+    /// let report=wizard.cast_spell_with(&mut rng,CharacterClass::Wizard,catalog.get("Fire Bolt").unwrap(),0,&mut goblin,0).unwrap();
+    /// let attack=report.attack.unwrap();
+    /// //智力12调整值+1，熟练+3
+    /// assert_eq!(attack.attack_bonus,4);
+    /// assert_eq!(goblin.hp,50-report.damage);
+    /// if attack.hit&&!attack.critical {assert_eq!(attack.damage_dice.len(),2);}
+    /// let shatter=catalog.get("Shatter").unwrap();
+    /// assert!(wizard.cast_spell_with(&mut rng,CharacterClass::Wizard,shatter,1,&mut goblin,0).is_err());
+    /// let report=wizard.cast_spell_with(&mut rng,CharacterClass::Wizard,shatter,3,&mut goblin,0).unwrap();
+    /// assert!(report.saved.is_some()&&report.damage>0);
+    /// assert_eq!(wizard.available_spell_slots()[..3],[4,3,1]);
+    /// ```
+    pub fn cast_spell_with<R:Rng+?Sized,T:Combatant+DNDChecker>(&mut self,rng:&mut R,class:CharacterClass,spell:&Spell,
+    slot_level:i32,target:&mut T,advantage:i32)->Result<SpellReport,DndError>{
+        check_count_and_advantage(1,advantage)?;
+        let casting=self.begin_casting(rng,class,spell,slot_level)?;
+        self.resolve_spell(rng,spell,&casting,target,advantage)
+    }
+    ///以瞄准的方格施放范围法术，范围由施法者在地图上的位置与target决定，见[`BattleMap::area_squares`]。
+    /// 施法距离按施法者到target的距离检查，自身为起点的法术不检查。
+    /// 范围内在combatants中的每个参与者（包括施法者自己）都会受到影响：豁免法术只投一次伤害，每个目标各自豁免，
+    /// 法术允许时豁免成功受到一半伤害（向下取整）。返回按名字排序的每个目标的结果
+    /// ```
+    /// use minidnd_eecs_havefun::{Player,CharacterClass,SpellCatalog,BattleMap,Position,seeded_rng};
+    /// use std::collections::HashMap;
+    /// let catalog=SpellCatalog::srd();
+    /// let mut wizard=Player::new_by_default();
+    /// wizard.classes=vec![(CharacterClass::Wizard,5)];
+    /// let mut map=BattleMap::new(40,40).unwrap();
+    /// map.place("Alice",Position::new(0,0)).unwrap();
+    /// let mut combatants=HashMap::new();
+    /// for (i,name) in ["Orc 1","Orc 2","Orc 3"].iter().enumerate(){
+    ///     map.place(name,Position::new(20+i as i32*3,20)).unwrap();
+    ///     combatants.insert(name.to_string(),Player{name:name.to_string(),hp:100,..Player::new_by_default()});
+    /// }
+    /// let fireball=catalog.get("Fireball").unwrap();
+    /// let reports=wizard.cast_area_spell_with(&mut seeded_rng(8),CharacterClass::Wizard,fireball,3,&map,Position::new(21,20),&mut combatants).unwrap();
+    /// //半径20尺，第三个兽人在25尺外
+    /// assert_eq!(reports.iter().map(|r|r.target.as_str()).collect::<Vec<_>>(),["Orc 1","Orc 2"]);
+    /// for report in &reports{
+    ///     assert_eq!(combatants[&report.target].hp,100-report.damage);
+    ///     assert!(report.damage>0);
+    /// }
+    /// let (full,half)=(reports.iter().map(|r|r.damage).max(),reports.iter().map(|r|r.damage).min());
+    /// if reports[0].saved!=reports[1].saved {assert_eq!(full.unwrap()/2,half.unwrap());}
+    /// //施法距离150尺
+    /// assert!(wizard.cast_area_spell_with(&mut seeded_rng(8),CharacterClass::Wizard,fireball,3,&map,Position::new(39,39),&mut combatants).is_err());
+    /// ```
+    #[allow(clippy::too_many_arguments)]
+    pub fn cast_area_spell_with<R:Rng+?Sized,C:Combatant+DNDChecker>(&mut self,rng:&mut R,class:CharacterClass,spell:&Spell,
+    slot_level:i32,map:&BattleMap,target:Position,combatants:&mut HashMap<String,C>)->Result<Vec<SpellReport>,DndError>{
+        let area=spell.area.ok_or_else(||DndError::NoAreaOfEffect(spell.name.clone()))?;
+        let origin=*map.positions.get(&self.name).ok_or_else(||DndError::UnknownCombatant(self.name.clone()))?;
+        if let Some(range)=spell.range_feet().filter(|_|spell.range!=SpellRange::Personal){
+            let distance=BattleMap::distance_feet(origin,target);
+            if distance>range {return Err(DndError::TargetOutOfRange{distance,range})}
+        }
+        let casting=self.begin_casting(rng,class,spell,slot_level)?;
+        let mut reports=Vec::new();
+        for name in map.in_area(origin,target,area){
+            if let Some(combatant)=combatants.get_mut(&name){
+                reports.push(self.resolve_spell(rng,spell,&casting,combatant,0)?);
+            }
+        }
+        Ok(reports)
+    }
+    ///检查施法能力并消耗法术位，投出共用的伤害
+    fn begin_casting<R:Rng+?Sized>(&mut self,rng:&mut R,class:CharacterClass,spell:&Spell,slot_level:i32)->Result<Casting,DndError>{
+        let attack_bonus=self.spell_attack_bonus(class)?;
+        let dc=self.spell_save_dc(class)?;
+        if !self.armor_proficient() {return Err(DndError::ArmorNotProficient(self.name.clone()))}
+        let slot_level=if spell.level==0 {0} else {
+            if slot_level<spell.level {return Err(DndError::InvalidSpellLevel(slot_level))}
+            self.expend_spell_slot(slot_level)?;
+            slot_level
+        };
+        if spell.concentration {self.concentrate(&spell.name);}
+        let damage=spell.damage_at(slot_level,self.cantrip_dice());
+        let rolled=match spell.resolution{
+            SpellResolution::Attack{..}=>None,
+            _=>damage.as_ref().map(|d|d.roll(rng).total.max(0)),
+        };
+        Ok(Casting{slot_level,attack_bonus,dc,damage,rolled})
+    }
+    ///对一个目标结算法术
+    fn resolve_spell<R:Rng+?Sized,T:Combatant+DNDChecker>(&self,rng:&mut R,spell:&Spell,casting:&Casting,
+    target:&mut T,advantage:i32)->Result<SpellReport,DndError>{
+        let mut report=SpellReport{caster:self.name.clone(),target:target.name().to_string(),spell:spell.name.clone(),
+        slot_level:casting.slot_level,attack:None,saved:None,damage:0,concentration_lost:false};
+        let mut dealt=casting.rolled.unwrap_or(0);
+        match spell.resolution{
+            SpellResolution::Attack{melee}=>{
+                let no_damage=DiceExpr{terms:Vec::new()};
+                let profile=AttackProfile{name:&spell.name,attack_bonus:casting.attack_bonus,damage:casting.damage.as_ref().unwrap_or(&no_damage),
+                damage_bonus:0,damage_type:spell.damage_type,magical:true,melee,advantage:0};
+                let attack=resolve_attack(rng,&self.name,&self.conditions,&profile,target,advantage);
+                report.damage=attack.damage;
+                report.concentration_lost=attack.concentration_lost;
+                report.attack=Some(attack);
+                return Ok(report)
+            },
+            SpellResolution::Save(ability)=>{
+                let saved=matches!(target.saving_throw_with(rng,ability,casting.dc,1,0)?,DNDResult::Win);
+                report.saved=Some(saved);
+                if saved {dealt=if spell.half_on_save {dealt/2} else {0};}
+            },
+            SpellResolution::Automatic=>{},
+        }
+        if dealt>0 {
+            report.damage=target.take_damage(dealt,spell.damage_type,true,false);
+            report.concentration_lost=report.damage>0&&!target.resolve_concentration_checks(&mut dyn_rng(rng));
+        }
+        Ok(report)
+    }
+}
@@ -0,0 +1,81 @@
+//!法术表。法术以JSON数据描述，格式与[`Spell`]的序列化结果相同
+//!内置的法术表（spells.json）收录了第5版SRD中常用的伤害法术，也可以从JSON字符串或文件读入自定义的法术
+use crate::{DndError,School,Spell};
+use std::collections::HashMap;
+
+#[derive(Clone,Debug,Default)]
+///按名字索引的法术表
+pub struct SpellCatalog{
+    spells:HashMap<String,Spell>,
+}
+
+impl SpellCatalog{
+    ///空的法术表
+    pub fn new()->SpellCatalog{
+        SpellCatalog::default()
+    }
+    ///内置的法术表
+    /// ```
+    /// use minidnd_eecs_havefun::{SpellCatalog,School,AreaShape,SpellRange};
+    /// let catalog=SpellCatalog::srd();
+    /// let fireball=catalog.get("Fireball").unwrap();
+    /// assert_eq!((fireball.level,fireball.school,fireball.range),(3,School::Evocation,SpellRange::Feet(150)));
+    /// assert_eq!(fireball.area,Some(AreaShape::Sphere{radius:20}));
+    /// assert!(fireball.half_on_save&&!fireball.concentration);
+    /// assert!(catalog.get("Moonbeam").unwrap().concentration);
+    /// assert!(catalog.of_level(0).iter().all(|s|s.area.is_none()));
+    /// ```
+    pub fn srd()->SpellCatalog{
+        SpellCatalog::from_json(include_str!("spells.json")).expect("the built-in spell list is valid")
+    }
+    ///从JSON数组读入法术表
+    /// ```
+    /// use minidnd_eecs_havefun::SpellCatalog;
+    /// let json=r#"[{"name":"Frostbite","level":0,"school":"Evocation","range":{"Feet":60},
+    /// "components":{"verbal":true,"somatic":true},"resolution":{"Save":"Constitution"},"damage":"1d6","damage_type":"Cold"}]"#;
+    /// let catalog=SpellCatalog::from_json(json).unwrap();
+    /// assert_eq!(catalog.get("Frostbite").unwrap().damage_at(0,2).unwrap().to_string(),"2d6");
+    /// assert!(SpellCatalog::from_json(r#"[{"name":"Broken"}]"#).is_err());
+    /// ```
+    pub fn from_json(json:&str)->Result<SpellCatalog,DndError>{
+        let spells:Vec<Spell>=serde_json::from_str(json)?;
+        let mut catalog=SpellCatalog::new();
+        for spell in spells{
+            catalog.insert(spell);
+        }
+        Ok(catalog)
+    }
+    ///从JSON文件读入法术表
+    pub fn load(file_name:&str)->Result<SpellCatalog,DndError>{
+        SpellCatalog::from_json(&std::fs::read_to_string(file_name)?)
+    }
+    ///加入一个法术，返回同名的旧法术
+    pub fn insert(&mut self,spell:Spell)->Option<Spell>{
+        self.spells.insert(spell.name.clone(),spell)
+    }
+    pub fn get(&self,name:&str)->Option<&Spell>{
+        self.spells.get(name)
+    }
+    ///某个环阶的所有法术，按名字排序
+    pub fn of_level(&self,level:i32)->Vec<&Spell>{
+        self.sorted(|s|s.level==level)
+    }
+    ///某个学派的所有法术，按名字排序
+    pub fn of_school(&self,school:School)->Vec<&Spell>{
+        self.sorted(|s|s.school==school)
+    }
+    fn sorted<F:Fn(&Spell)->bool>(&self,filter:F)->Vec<&Spell>{
+        let mut spells:Vec<&Spell>=self.spells.values().filter(|s|filter(s)).collect();
+        spells.sort_by(|a,b|a.name.cmp(&b.name));
+        spells
+    }
+    pub fn iter(&self)->impl Iterator<Item=&Spell>{
+        self.spells.values()
+    }
+    pub fn len(&self)->usize{
+        self.spells.len()
+    }
+    pub fn is_empty(&self)->bool{
+        self.spells.is_empty()
+    }
+}
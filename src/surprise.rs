@@ -0,0 +1,70 @@
+//!突袭，对应战斗步骤中的第1步
+//!选择隐匿的参与者进行敏捷（隐匿）检定，与每个敌对参与者的被动察觉对抗，检定值高于被动察觉才不会被察觉。
+//!同一阵营的隐匿者进行群体检定：至少一半的人成功则整个阵营成功。
+//!一个参与者只有在没有察觉到任何威胁时才会被突袭，即所有敌对阵营都全员隐匿并且群体检定都成功
+use crate::{Combatant,DndError,FactionTable};
+use crate::combatant::dyn_rng;
+use rand::Rng;
+use serde::{Serialize,Deserialize};
+use std::collections::HashMap;
+
+#[derive(Copy,Clone,Debug,Default,PartialEq,Eq,Hash,Serialize,Deserialize)]
+///一个参与者在战斗开始时的突袭状态
+pub enum SurpriseStatus{
+    #[default] NotSurprised,//察觉到了至少一个威胁
+    Surprised,//没有察觉到任何威胁，跳过第一轮的回合，并且在这个回合结束前不能使用反应
+    CannotBeSurprised,//警觉等特性使其无法被突袭
+}
+
+///判定突袭，hide代表每个人是(true)否(false)选择隐匿，返回每个参与者的突袭状态。
+/// 已经倒下的参与者不算作威胁。hide中出现了不存在的参与者时返回错误
+/// ```
+/// use minidnd_eecs_havefun::{Player,Feat,FactionTable,SurpriseStatus,determine_surprise_with,seeded_rng};
+/// use std::collections::HashMap;
+/// let member=|name:&str,faction:&str|Player{name:name.to_string(),faction:faction.to_string(),..Player::new_by_default()};
+/// let mut rogue=member("Rogue","bandits");
+/// rogue.ability_scores.dexterity=30;
+/// let mut alice=member("Alice","party");
+/// alice.feats.insert(Feat::Alert);
+/// let combatants=HashMap::from([("Rogue".to_string(),rogue),("Alice".to_string(),alice),("Bob".to_string(),member("Bob","party"))]);
+/// let hide=HashMap::from([("Rogue".to_string(),true)]);
+/// let status=determine_surprise_with(&mut seeded_rng(1),&combatants,&FactionTable::new(),&hide).unwrap();
+/// assert_eq!(status["Alice"],SurpriseStatus::CannotBeSurprised);
+/// assert_eq!(status["Bob"],SurpriseStatus::Surprised);
+/// //强盗的敌人都在明处
+/// assert_eq!(status["Rogue"],SurpriseStatus::NotSurprised);
+/// let hide=HashMap::from([("Carol".to_string(),true)]);
+/// assert!(determine_surprise_with(&mut seeded_rng(1),&combatants,&FactionTable::new(),&hide).is_err());
+/// ```
+pub fn determine_surprise_with<C:Combatant,R:Rng+?Sized>(rng:&mut R,combatants:&HashMap<String,C>,relations:&FactionTable,
+hide:&HashMap<String,bool>)->Result<HashMap<String,SurpriseStatus>,DndError>{
+    let mut hiders:Vec<&String>=hide.iter().filter(|(_,hiding)|**hiding).map(|(name,_)|name).collect();
+    hiders.sort();
+    let mut stealth:HashMap<&str,i32>=HashMap::new();
+    for name in hiders{
+        let hider=combatants.get(name).ok_or_else(||DndError::UnknownCombatant(name.clone()))?;
+        stealth.insert(name,hider.stealth_roll(&mut dyn_rng(rng)));
+    }
+    let mut factions:HashMap<&str,Vec<&str>>=HashMap::new();
+    for (name,combatant) in combatants.iter().filter(|(_,c)|!c.is_defeated()){
+        factions.entry(combatant.faction()).or_default().push(name);
+    }
+    let mut ret=HashMap::new();
+    for (name,observer) in combatants{
+        let status=if !observer.can_be_surprised() {SurpriseStatus::CannotBeSurprised}
+        else {
+            let passive=observer.passive_perception();
+            let mut threats=factions.iter().filter(|(faction,_)|relations.is_hostile(faction,observer.faction())).peekable();
+            let unnoticed=threats.peek().is_some()&&threats.all(|(_,members)|group_hidden(members,&stealth,passive));
+            if unnoticed {SurpriseStatus::Surprised} else {SurpriseStatus::NotSurprised}
+        };
+        ret.insert(name.clone(),status);
+    }
+    Ok(ret)
+}
+
+///一个阵营是否在某个观察者面前成功隐匿：所有成员都选择了隐匿，并且至少一半成员的检定值高于被动察觉
+fn group_hidden(members:&[&str],stealth:&HashMap<&str,i32>,passive:i32)->bool{
+    let rolls:Option<Vec<i32>>=members.iter().map(|m|stealth.get(m).copied()).collect();
+    rolls.is_some_and(|rolls|rolls.iter().filter(|roll|**roll>passive).count()*2>=rolls.len())
+}